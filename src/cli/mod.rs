@@ -0,0 +1,43 @@
+pub mod scan;
+
+use crate::tui::render::app::run_tui;
+use clap::{Arg, ArgAction, Command, value_parser};
+use std::{error::Error, path::PathBuf};
+
+/// Builds the command line interface.
+fn command() -> Command {
+    Command::new("rmpr")
+        .about("Ratatui music player")
+        .subcommand(
+            Command::new("scan")
+                .about("Computes ReplayGain 2.0 values for files or directories")
+                .arg(
+                    Arg::new("write")
+                        .long("write")
+                        .short('w')
+                        .action(ArgAction::SetTrue)
+                        .help("Write the computed values as tags"),
+                )
+                .arg(
+                    Arg::new("paths")
+                        .required(true)
+                        .num_args(1..)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+}
+
+/// Runs the subcommand given on the command line, or the TUI if there is none.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    match command().get_matches().subcommand() {
+        Some(("scan", args)) => {
+            let paths: Vec<PathBuf> = args
+                .get_many::<PathBuf>("paths")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            scan::run_scan(&paths, args.get_flag("write"))
+        }
+        _ => run_tui(),
+    }
+}
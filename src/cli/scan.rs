@@ -0,0 +1,55 @@
+use crate::data::{
+    files::collect_playable,
+    loudness::scanner::{ScanEvent, scan},
+};
+use std::{error::Error, path::PathBuf};
+
+/// Scans the given paths headlessly, printing progress to stderr and results to stdout.
+pub fn run_scan(paths: &[PathBuf], write: bool) -> Result<(), Box<dyn Error>> {
+    let files: Vec<PathBuf> = paths
+        .iter()
+        .flat_map(|path| collect_playable(path))
+        .collect();
+    if files.is_empty() {
+        return Err("no playable files found".into());
+    }
+
+    let mut errors = 0;
+    scan(&files, write, |event| match event {
+        ScanEvent::Measured { done, total, path } => {
+            eprintln!("[{}/{}] {}", done, total, path.display());
+        }
+        ScanEvent::Tagged {
+            path,
+            gain,
+            written,
+        } => {
+            let display = |value: Option<f32>, precision: usize| match value {
+                Some(value) => format!("{:.*}", precision, value),
+                None => "-".to_string(),
+            };
+            println!(
+                "track {} dB peak {}  album {} dB peak {}  {}",
+                display(gain.track_gain, 2),
+                display(gain.track_peak, 6),
+                display(gain.album_gain, 2),
+                display(gain.album_peak, 6),
+                path.display()
+            );
+            if let Some(Err(error)) = written {
+                errors += 1;
+                eprintln!("failed to write tags to {}: {}", path.display(), error);
+            }
+        }
+        ScanEvent::Failed { path, error } => {
+            errors += 1;
+            eprintln!("failed to decode {}: {}", path.display(), error);
+        }
+        ScanEvent::Finished => {}
+    });
+
+    match errors {
+        0 => Ok(()),
+        errors => Err(format!("{} files failed", errors).into()),
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Encapsulates the saved playback positions, in milliseconds, keyed by file path.
+#[derive(Deserialize, Serialize, Default)]
+pub struct Bookmarks {
+    positions: BTreeMap<String, u64>,
+}
+
+impl Bookmarks {
+    /// Returns the path of the bookmarks file in the user's data directory.
+    fn file_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rmpr/bookmarks.toml"))
+    }
+
+    /// Loads the saved bookmarks, or none if the file is missing or unreadable.
+    pub fn load() -> Self {
+        Bookmarks::file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the bookmarks to the user's data directory.
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Bookmarks::file_path().ok_or("no data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the saved position of a file.
+    pub fn get(&self, path: &Path) -> Option<Duration> {
+        self.positions
+            .get(&*path.to_string_lossy())
+            .map(|millis| Duration::from_millis(*millis))
+    }
+
+    /// Saves the position of a file.
+    pub fn set(&mut self, path: &Path, pos: Duration) {
+        self.positions
+            .insert(path.to_string_lossy().to_string(), pos.as_millis() as u64);
+    }
+
+    /// Forgets the position of a file, e.g. once it was played to the end.
+    pub fn remove(&mut self, path: &Path) {
+        self.positions.remove(&*path.to_string_lossy());
+    }
+}
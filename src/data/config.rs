@@ -1,5 +1,5 @@
 use serde::Deserialize;
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 /// Encapsulates themeing data.
 #[derive(Deserialize, Clone)] // Clone is needed for lines 69-76 in tui and 140-141 in browser
@@ -24,6 +24,8 @@ pub struct Colors {
     pub track_num: String,
     pub volume: String,
     pub year: String,
+    pub spectrum: String,
+    pub oscilloscope: String,
 }
 
 impl Default for Colors {
@@ -48,6 +50,8 @@ impl Default for Colors {
             track_num: "#FF00FF".to_string(),
             volume: "#598EFF".to_string(),
             year: "#598EFF".to_string(),
+            spectrum: "#598EFF".to_string(),
+            oscilloscope: "#00FF00".to_string(),
         }
     }
 }
@@ -57,6 +61,9 @@ impl Default for Colors {
 #[serde(default)]
 pub struct Directories {
     pub music_directory: PathBuf,
+    /// Files under these directories resume from their bookmark automatically.
+    pub audiobook_roots: Vec<PathBuf>,
+    pub podcast_directory: PathBuf,
 }
 
 impl Default for Directories {
@@ -68,6 +75,13 @@ impl Default for Directories {
                     path
                 })
                 .unwrap(),
+            audiobook_roots: Vec::new(),
+            podcast_directory: dirs::home_dir()
+                .map(|mut path| {
+                    path.push("Podcasts");
+                    path
+                })
+                .unwrap(),
         }
     }
 }
@@ -77,32 +91,268 @@ impl Default for Directories {
 #[serde(default)]
 pub struct Controls {
     pub vol_delta: i16,
+    /// Step in dB when adjusting an equalizer band.
+    pub eq_delta: f32,
+    pub speed_delta: f32,
+    /// Seconds skipped when seeking.
+    pub seek_delta: i64,
 }
 
 impl Default for Controls {
     fn default() -> Self {
-        Controls { vol_delta: 2 }
+        Controls {
+            vol_delta: 2,
+            eq_delta: 1.0,
+            speed_delta: 0.1,
+            seek_delta: 5,
+        }
     }
 }
 
-/// Encapsulates all config.toml parameters.
+/// How ReplayGain tags are applied during playback.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    Off,
+    Track,
+    Album,
+    /// Album gain while consecutive tracks of the same album are queued, track gain otherwise.
+    Auto,
+}
+
+/// How volume levels map to amplitude.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeCurve {
+    Linear,
+    /// Level cubed, close to perceived loudness.
+    Cubic,
+    /// Level spread evenly over `volume_db_range` decibels.
+    Db,
+}
+
+/// Encapsulates playback data.
 #[derive(Deserialize)]
-pub struct ConfigData {
-    pub colors: Colors,
-    pub directories: Directories,
-    pub controls: Controls,
+#[serde(default)]
+pub struct Playback {
+    pub replay_gain: ReplayGainMode,
+    /// Extra gain in dB applied on top of the ReplayGain value.
+    pub replay_gain_preamp: f32,
+    /// Lowers the gain when the tagged peak would clip.
+    pub replay_gain_clip_prevention: bool,
+    /// Keeps the pitch when the playback speed changes, instead of resampling.
+    pub preserve_pitch: bool,
+    /// Output device name as listed in the device picker, "default", or "null" to discard audio.
+    pub output_device: String,
+    /// Linear by default, like earlier versions; "cubic" or "db" follow perceived loudness more closely.
+    pub volume_curve: VolumeCurve,
+    /// Range in dB covered by the "db" volume curve.
+    pub volume_db_range: f32,
+    /// Highest volume level in percent, up to 150. Levels above 100 are softly limited.
+    pub max_volume: i16,
+    /// Files at least this many minutes long get a resume bookmark, like files with chapters or under an audiobook root.
+    pub bookmark_min_minutes: u64,
+    /// How many seconds the sleep timer fades out for before stopping, 0 to stop at once.
+    pub sleep_fade_seconds: u64,
+    /// Length in milliseconds of the volume ramps on pause, resume, stop, skip and seek, so they don't click. 0 turns them off.
+    pub transition_fade_ms: u64,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            replay_gain: ReplayGainMode::Off,
+            replay_gain_preamp: 0.0,
+            replay_gain_clip_prevention: true,
+            preserve_pitch: true,
+            output_device: "default".to_string(),
+            volume_curve: VolumeCurve::Linear,
+            volume_db_range: 60.0,
+            max_volume: 100,
+            bookmark_min_minutes: 30,
+            sleep_fade_seconds: 30,
+            transition_fade_ms: 15,
+        }
+    }
+}
+
+/// Encapsulates loudness scanner data.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Scanner {
+    /// Writes the computed ReplayGain values back to the files, off by default like the CLI's `--write`.
+    pub write_tags: bool,
+}
+
+/// Shelf filter gain in dB at a corner frequency in Hz.
+#[derive(Deserialize, Clone, Copy)]
+pub struct Shelf {
+    pub frequency: f32,
+    pub gain: f32,
+}
+
+/// Encapsulates an equalizer preset. Band gains are in dB, from 31 Hz to 16 kHz.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EqPreset {
+    pub preamp: f32,
+    pub bands: [f32; 10],
+    pub low_shelf: Option<Shelf>,
+    pub high_shelf: Option<Shelf>,
+}
+
+/// Encapsulates equalizer data.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Equalizer {
+    pub enabled: bool,
+    pub preset: String,
+    pub presets: BTreeMap<String, EqPreset>,
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Equalizer {
+            enabled: false,
+            preset: "flat".to_string(),
+            presets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Equalizer {
+    /// Returns the preset names in display order, with the built-in flat preset first.
+    pub fn preset_names(&self) -> Vec<String> {
+        let mut names = vec!["flat".to_string()];
+        names.extend(self.presets.keys().filter(|name| *name != "flat").cloned());
+        names
+    }
+
+    /// Returns the named preset, or a flat one if it doesn't exist.
+    pub fn get_preset(&self, name: &str) -> EqPreset {
+        self.presets.get(name).cloned().unwrap_or_default()
+    }
 }
 
-impl Default for ConfigData {
+/// Encapsulates visualizer data.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Visualizer {
+    /// How many times per second the visualizer tab is redrawn.
+    pub frame_rate: u32,
+    /// Draws the seekbar as the waveform of the track.
+    pub waveform_seekbar: bool,
+}
+
+impl Default for Visualizer {
+    fn default() -> Self {
+        Visualizer {
+            frame_rate: 30,
+            waveform_seekbar: false,
+        }
+    }
+}
+
+/// How cover art is drawn in the terminal.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphicsProtocol {
+    /// Picked from the terminal the player runs in.
+    Auto,
+    Kitty,
+    Sixel,
+    /// Two pixels per cell with "▀", which works in any terminal with true colour.
+    Halfblocks,
+    Off,
+}
+
+/// Encapsulates cover art data.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CoverArt {
+    pub protocol: GraphicsProtocol,
+}
+
+impl Default for CoverArt {
+    fn default() -> Self {
+        CoverArt {
+            protocol: GraphicsProtocol::Auto,
+        }
+    }
+}
+
+/// Encapsulates file organiser data.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Organiser {
+    /// Where files are moved to, relative to the music directory. The extension is kept.
+    pub pattern: String,
+    /// How tags are read from the paths of files, matched against their last directories.
+    pub tag_pattern: String,
+}
+
+impl Default for Organiser {
     fn default() -> Self {
-        ConfigData {
-            colors: Colors::default(),
-            directories: Directories::default(),
-            controls: Controls::default(),
+        Organiser {
+            pattern: "{albumartist}/{year} - {album}/{disc}{track:02} {title}".to_string(),
+            tag_pattern: "{artist}/{album}/{track} - {title}".to_string(),
         }
     }
 }
 
+/// A listening statistic the browser can show next to each track.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StatColumn {
+    Rating,
+    Plays,
+    Skips,
+    LastPlayed,
+}
+
+/// Encapsulates listening statistics data.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Statistics {
+    /// How much of a track has to be heard for it to count as played, between 0 and 1.
+    pub play_fraction: f64,
+    /// Also writes ratings to the files, as POPM frames (MP3) or FMPS_RATING (FLAC, MP4).
+    pub mirror_ratings: bool,
+    pub columns: Vec<StatColumn>,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Statistics {
+            play_fraction: 0.5,
+            mirror_ratings: false,
+            columns: Vec::new(),
+        }
+    }
+}
+
+/// Encapsulates all config.toml parameters.
+#[derive(Deserialize, Default)]
+pub struct ConfigData {
+    pub colors: Colors,
+    pub directories: Directories,
+    pub controls: Controls,
+    #[serde(default)]
+    pub playback: Playback,
+    #[serde(default)]
+    pub scanner: Scanner,
+    #[serde(default)]
+    pub equalizer: Equalizer,
+    #[serde(default)]
+    pub visualizer: Visualizer,
+    #[serde(default)]
+    pub cover_art: CoverArt,
+    #[serde(default)]
+    pub organiser: Organiser,
+    #[serde(default)]
+    pub statistics: Statistics,
+}
+
 /// Loads the ConfigData from config.toml.
 pub fn load_config() -> ConfigData {
     let config_path = dirs::config_dir()
@@ -0,0 +1,88 @@
+use super::{Image, fits};
+use jpeg_decoder::{Decoder, PixelFormat};
+
+/// Decodes a baseline or progressive JPEG, greyscale or colour. CMYK and lossless pictures aren't supported.
+pub fn decode(data: &[u8]) -> Option<Image> {
+    let mut decoder = Decoder::new(data);
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    if !fits(width, height) {
+        return None;
+    }
+    let raw = decoder.decode().ok()?;
+    let pixels: Vec<[u8; 3]> = match info.pixel_format {
+        PixelFormat::L8 => raw.iter().map(|value| [*value; 3]).collect(),
+        PixelFormat::RGB24 => raw
+            .chunks_exact(3)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect(),
+        _ => return None,
+    };
+    match pixels.len() == width * height {
+        true => Some(Image {
+            width,
+            height,
+            pixels,
+        }),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16×8 baseline colour picture, red on the left half and blue on the right.
+    const COLOUR: &[u8] = include_bytes!("../../../test-files/cover.jpg");
+    /// 8×8 baseline greyscale picture, flat at 200.
+    const GREY: &[u8] = include_bytes!("../../../test-files/cover-grey.jpg");
+
+    fn close(pixel: [u8; 3], expected: [u8; 3]) -> bool {
+        pixel
+            .iter()
+            .zip(expected)
+            .all(|(value, expected)| value.abs_diff(expected) <= 2)
+    }
+
+    #[test]
+    fn decodes_colour() {
+        let image = decode(COLOUR).unwrap();
+        assert_eq!((image.width, image.height), (16, 8));
+        for y in 0..8 {
+            for x in 0..16 {
+                let expected = match x < 8 {
+                    true => [254, 0, 0],
+                    false => [0, 0, 254],
+                };
+                let pixel = image.pixel(x, y);
+                assert!(close(pixel, expected), "{pixel:?} at {x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_greyscale() {
+        let image = decode(GREY).unwrap();
+        assert_eq!((image.width, image.height), (8, 8));
+        assert!(image.pixels.iter().all(|pixel| close(*pixel, [200; 3])));
+    }
+
+    #[test]
+    fn broken_pictures_are_rejected_without_panicking() {
+        for end in 0..COLOUR.len() {
+            decode(&COLOUR[..end]);
+        }
+        for index in 2..COLOUR.len() {
+            for flip in [0x01, 0x10, 0xFF] {
+                let mut broken = COLOUR.to_vec();
+                broken[index] ^= flip;
+                decode(&broken);
+            }
+        }
+
+        let progressive: Vec<u8> = [&COLOUR[..2], &[0xFF, 0xC2, 0, 2]].concat();
+        assert!(decode(&progressive).is_none());
+        assert!(decode(&COLOUR[..COLOUR.len() / 4]).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+pub mod jpeg;
+pub mod png;
+
+use crate::data::{cue::resolve, files::is_stream, metadata::audio_probe::read_visuals};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Names of pictures looked for next to a track, most likely covers first.
+const FOLDER_NAMES: [&str; 5] = ["cover", "folder", "front", "album", "albumart"];
+/// Extensions of pictures that can be decoded.
+const EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+/// Largest picture that gets decoded, to avoid huge allocations from broken headers.
+const MAX_PIXELS: usize = 64 * 1024 * 1024;
+
+/// A decoded picture, in rows of RGB pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    /// Returns the pixel at a position.
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Scales the picture to a size, averaging the pixels each new one covers.
+    pub fn resize(&self, width: usize, height: usize) -> Image {
+        let (width, height) = (width.max(1), height.max(1));
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (top, bottom) = span(y, height, self.height);
+            for x in 0..width {
+                let (left, right) = span(x, width, self.width);
+                let mut sum = [0u32; 3];
+                for source in top..bottom {
+                    for pixel in
+                        &self.pixels[source * self.width + left..source * self.width + right]
+                    {
+                        for (total, channel) in sum.iter_mut().zip(pixel) {
+                            *total += *channel as u32;
+                        }
+                    }
+                }
+                let count = ((bottom - top) * (right - left)) as u32;
+                pixels.push(sum.map(|total| (total / count) as u8));
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Scales the picture to fit a size, keeping its aspect ratio.
+    pub fn fit(&self, width: usize, height: usize) -> Image {
+        let scale = (width as f64 / self.width as f64).min(height as f64 / self.height as f64);
+        self.resize(
+            (self.width as f64 * scale).round() as usize,
+            (self.height as f64 * scale).round() as usize,
+        )
+    }
+}
+
+/// Returns the source pixels a resized pixel covers, at least one.
+fn span(index: usize, size: usize, source: usize) -> (usize, usize) {
+    let start = index * source / size;
+    let end = ((index + 1) * source / size).max(start + 1).min(source);
+    (start.min(source - 1), end)
+}
+
+/// Decodes a JPEG or PNG picture, telling them apart by their signature.
+pub fn decode(data: &[u8]) -> Option<Image> {
+    let image = match data {
+        [0xFF, 0xD8, ..] => jpeg::decode(data)?,
+        [0x89, b'P', b'N', b'G', ..] => png::decode(data)?,
+        _ => return None,
+    };
+    match image.width > 0 && image.height > 0 {
+        true => Some(image),
+        false => None,
+    }
+}
+
+/// Checks the size of a picture before its pixels are allocated.
+fn fits(width: usize, height: usize) -> bool {
+    width > 0 && height > 0 && width.saturating_mul(height) <= MAX_PIXELS
+}
+
+/// Finds the cover of a track, from the pictures embedded in it or a cover file in its directory.
+/// Tracks of CUE sheets use the file they're cut from.
+pub fn find_cover(path: &Path) -> Option<Image> {
+    if is_stream(path) {
+        return None;
+    }
+    let file = resolve(path).map_or(path.to_path_buf(), |(_, track)| track.file);
+
+    read_visuals(&file)
+        .iter()
+        .find_map(|visual| decode(&visual.data))
+        .or_else(|| {
+            folder_images(file.parent()?)
+                .iter()
+                .find_map(|image| decode(&fs::read(image).ok()?))
+        })
+}
+
+/// Returns the cover pictures in a directory, such as cover.jpg or Folder.png, most likely first.
+fn folder_images(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut images: Vec<(usize, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+            let extension = path.extension()?.to_string_lossy().to_lowercase();
+            let rank = FOLDER_NAMES.iter().position(|name| *name == stem)?;
+            EXTENSIONS
+                .contains(&extension.as_str())
+                .then_some((rank, path))
+        })
+        .collect();
+    images.sort();
+    images.into_iter().map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::TempDir;
+
+    #[test]
+    fn resizing_averages_the_covered_pixels() {
+        let image = Image {
+            width: 4,
+            height: 2,
+            pixels: vec![
+                [0, 0, 0],
+                [200, 0, 0],
+                [0, 100, 0],
+                [0, 100, 0],
+                [0, 0, 0],
+                [200, 0, 0],
+                [0, 0, 50],
+                [0, 0, 50],
+            ],
+        };
+        let resized = image.resize(2, 1);
+        assert_eq!(resized.pixels, vec![[100, 0, 0], [0, 50, 25]]);
+        assert_eq!(image.fit(2, 2).width, 2);
+        assert_eq!(image.fit(2, 2).height, 1);
+    }
+
+    #[test]
+    fn folder_covers_are_found_by_name() {
+        let dir = TempDir::new("cover");
+        for name in ["Folder.PNG", "cover.jpg", "back.jpg", "cover.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let images = folder_images(dir.path());
+        let names: Vec<_> = images
+            .iter()
+            .map(|image| image.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["cover.jpg", "Folder.PNG"]);
+    }
+}
@@ -0,0 +1,134 @@
+use super::{Image, fits};
+use png::{ColorType, Decoder, Transformations};
+
+/// Decodes a PNG of any bit depth, colour type and interlacing. Transparent pixels are blended over black.
+pub fn decode(data: &[u8]) -> Option<Image> {
+    let mut decoder = Decoder::new(data);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+    let (width, height) = (reader.info().width as usize, reader.info().height as usize);
+    if !fits(width, height) {
+        return None;
+    }
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).ok()?;
+    let channels = match frame.color_type {
+        ColorType::Grayscale => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::Rgb => 3,
+        ColorType::Rgba => 4,
+        ColorType::Indexed => return None,
+    };
+
+    let pixels: Vec<[u8; 3]> = buffer[..frame.buffer_size()]
+        .chunks_exact(channels)
+        .map(|pixel| {
+            let (rgb, alpha) = match channels {
+                1 => ([pixel[0]; 3], 255),
+                2 => ([pixel[0]; 3], pixel[1]),
+                3 => ([pixel[0], pixel[1], pixel[2]], 255),
+                _ => ([pixel[0], pixel[1], pixel[2]], pixel[3]),
+            };
+            rgb.map(|channel| (channel as u32 * alpha as u32 / 255) as u8)
+        })
+        .collect();
+    match pixels.len() == width * height {
+        true => Some(Image {
+            width,
+            height,
+            pixels,
+        }),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use png::{BitDepth, Encoder};
+
+    fn encode(
+        width: u32,
+        height: u32,
+        depth: BitDepth,
+        colour: ColorType,
+        plte: &[u8],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut png = Vec::new();
+        let mut encoder = Encoder::new(&mut png, width, height);
+        encoder.set_color(colour);
+        encoder.set_depth(depth);
+        if !plte.is_empty() {
+            encoder.set_palette(plte.to_vec());
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        png
+    }
+
+    #[test]
+    fn decodes_palettes_grey_and_alpha() {
+        let image = decode(&encode(
+            3,
+            1,
+            BitDepth::Two,
+            ColorType::Indexed,
+            &[0, 0, 0, 255, 0, 0, 0, 0, 255],
+            &[0b0001_1000],
+        ))
+        .unwrap();
+        assert_eq!(image.pixels, vec![[0, 0, 0], [255, 0, 0], [0, 0, 255]]);
+
+        let image = decode(&encode(
+            2,
+            1,
+            BitDepth::One,
+            ColorType::Grayscale,
+            &[],
+            &[0b0100_0000],
+        ))
+        .unwrap();
+        assert_eq!(image.pixels, vec![[0, 0, 0], [255, 255, 255]]);
+
+        let image = decode(&encode(
+            1,
+            1,
+            BitDepth::Eight,
+            ColorType::Rgba,
+            &[],
+            &[200, 100, 50, 128],
+        ))
+        .unwrap();
+        assert_eq!(image.pixels, vec![[100, 50, 25]]);
+
+        let image = decode(&encode(
+            2,
+            1,
+            BitDepth::Sixteen,
+            ColorType::Rgb,
+            &[],
+            &[10, 0, 20, 0, 30, 0, 255, 255, 0, 0, 128, 0],
+        ))
+        .unwrap();
+        assert_eq!(image.pixels, vec![[10, 20, 30], [255, 0, 128]]);
+
+        assert!(decode(b"\x89PNG\r\n\x1a\n").is_none());
+    }
+
+    #[test]
+    fn broken_pictures_are_rejected_without_panicking() {
+        let data: Vec<u8> = (0..8 * 6 * 3).map(|i| (i * 7) as u8).collect();
+        let png = encode(8, 6, BitDepth::Eight, ColorType::Rgb, &[], &data);
+        for end in 0..png.len() {
+            decode(&png[..end]);
+        }
+        for index in 8..png.len() {
+            let mut broken = png.clone();
+            broken[index] ^= 0x10;
+            decode(&broken);
+        }
+        assert!(decode(&png[..png.len() / 2]).is_none());
+    }
+}
@@ -0,0 +1,394 @@
+use crate::data::metadata::replay_gain::{ReplayGain, parse_db};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// CD frames per second, the unit of INDEX times.
+const FRAMES_PER_SEC: u64 = 75;
+/// Prefix of the virtual file names of the tracks in a sheet, e.g. "album.cue/track03".
+const TRACK_PREFIX: &str = "track";
+
+/// Encapsulates the album-level data of a CUE sheet.
+#[derive(Clone, Default)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub date: Option<i32>,
+    pub genre: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+/// Encapsulates one track of a CUE sheet: a range of an audio file.
+#[derive(Clone, Default)]
+pub struct CueTrack {
+    pub number: u16,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub file: PathBuf,
+    /// Position of INDEX 01.
+    pub start: Duration,
+    /// Start of the next track in the same file, or None to play to the end of the file.
+    /// The next track's pregap (INDEX 00) is part of this track, so consecutive tracks play without gaps.
+    pub end: Option<Duration>,
+    pub replay_gain: ReplayGain,
+}
+
+impl CueTrack {
+    /// Returns the track's duration, given the length of the underlying file when the track runs to its end.
+    pub fn duration(&self, file_duration: Option<f64>) -> Option<f64> {
+        match self.end {
+            Some(end) => Some(end.saturating_sub(self.start).as_secs_f64()),
+            None => file_duration.map(|duration| (duration - self.start.as_secs_f64()).max(0.0)),
+        }
+    }
+}
+
+/// Splits a CUE line argument list, honouring double quotes.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut arg = String::new();
+        match c == '"' {
+            true => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    arg.push(c);
+                }
+            }
+            false => {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    arg.push(c);
+                    chars.next();
+                }
+            }
+        }
+        args.push(arg);
+    }
+    args
+}
+
+/// Parses an INDEX time in mm:ss:ff.
+fn parse_time(value: &str) -> Option<Duration> {
+    let mut parts = value.split(':').map(|part| part.parse::<u64>().ok());
+    let (min, sec, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(Duration::from_millis(
+        ((min * 60 + sec) * FRAMES_PER_SEC + frames) * 1000 / FRAMES_PER_SEC,
+    ))
+}
+
+/// Parses the text of a CUE sheet. FILE names are resolved against `base_dir`, or all replaced by `file` for embedded sheets.
+pub fn parse(text: &str, base_dir: &Path, file: Option<&Path>) -> CueSheet {
+    let mut sheet = CueSheet::default();
+    let mut album_gain = ReplayGain::default();
+    let mut current_file = file.map(Path::to_path_buf).unwrap_or_default();
+    // Whether each track has an INDEX 01; those without have no start to play from.
+    let mut indexed = Vec::new();
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let args = split_args(line);
+        let Some(command) = args.first() else {
+            continue;
+        };
+        let value = args.get(1).cloned();
+        let track = sheet.tracks.last_mut();
+        match (command.to_ascii_uppercase().as_str(), track) {
+            ("FILE", _) => {
+                if file.is_none()
+                    && let Some(name) = value
+                {
+                    current_file = base_dir.join(name);
+                }
+            }
+            ("TRACK", _) => {
+                sheet.tracks.push(CueTrack {
+                    number: value.and_then(|number| number.parse().ok()).unwrap_or(0),
+                    file: current_file.clone(),
+                    ..CueTrack::default()
+                });
+                indexed.push(false);
+            }
+            ("TITLE", Some(track)) => track.title = value,
+            ("TITLE", None) => sheet.title = value,
+            ("PERFORMER", Some(track)) => track.performer = value,
+            ("PERFORMER", None) => sheet.performer = value,
+            ("INDEX", Some(track)) => {
+                if value.as_deref() == Some("01")
+                    && let Some(start) = args.get(2).and_then(|time| parse_time(time))
+                {
+                    track.start = start;
+                    if let Some(indexed) = indexed.last_mut() {
+                        *indexed = true;
+                    }
+                }
+            }
+            ("REM", track) => {
+                let rem = args.get(2).map(String::as_str).unwrap_or_default();
+                let gain = match track {
+                    Some(track) => &mut track.replay_gain,
+                    None => &mut album_gain,
+                };
+                match value.unwrap_or_default().to_ascii_uppercase().as_str() {
+                    "DATE" => sheet.date = rem.get(..4).and_then(|year| year.parse().ok()),
+                    "GENRE" => sheet.genre = Some(rem.to_string()),
+                    "REPLAYGAIN_TRACK_GAIN" => gain.track_gain = parse_db(rem),
+                    "REPLAYGAIN_TRACK_PEAK" => gain.track_peak = parse_db(rem),
+                    "REPLAYGAIN_ALBUM_GAIN" => album_gain.album_gain = parse_db(rem),
+                    "REPLAYGAIN_ALBUM_PEAK" => album_gain.album_peak = parse_db(rem),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Tracks of a file must start in order; one that goes back would have a negative length.
+    let mut previous: Option<(PathBuf, Duration)> = None;
+    let mut indexed = indexed.into_iter();
+    sheet.tracks.retain(|track| {
+        let ordered = previous
+            .as_ref()
+            .is_none_or(|(file, start)| *file != track.file || track.start >= *start);
+        let keep = indexed.next().unwrap_or(false) && ordered;
+        if keep {
+            previous = Some((track.file.clone(), track.start));
+        }
+        keep
+    });
+
+    let starts: Vec<(PathBuf, Duration)> = sheet
+        .tracks
+        .iter()
+        .map(|track| (track.file.clone(), track.start))
+        .collect();
+    for (index, track) in sheet.tracks.iter_mut().enumerate() {
+        track.end = starts
+            .get(index + 1)
+            .filter(|(file, _)| *file == track.file)
+            .map(|(_, start)| *start);
+        track.performer = track.performer.take().or(sheet.performer.clone());
+        track.replay_gain.album_gain = album_gain.album_gain;
+        track.replay_gain.album_peak = album_gain.album_peak;
+    }
+    sheet
+}
+
+/// Returns true if the path is a .cue file.
+pub fn is_cue(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+}
+
+/// Reads the CUESHEET Vorbis comment of a FLAC file.
+fn embedded_sheet(path: &Path) -> Option<String> {
+    if !path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"))
+    {
+        return None;
+    }
+    let tag = metaflac::Tag::read_from_path(path).ok()?;
+    tag.get_vorbis("CUESHEET")?.next().map(str::to_string)
+}
+
+/// Reads a sheet from a .cue file, or from the CUESHEET comment embedded in an audio file.
+pub fn read_sheet(path: &Path) -> Option<CueSheet> {
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    match is_cue(path) {
+        true => {
+            let bytes = fs::read(path).ok()?;
+            Some(parse(&String::from_utf8_lossy(&bytes), base_dir, None))
+        }
+        false => Some(parse(&embedded_sheet(path)?, base_dir, Some(path))),
+    }
+}
+
+/// Returns the virtual path of a track in a sheet.
+pub fn track_path(sheet: &Path, number: u16) -> PathBuf {
+    sheet.join(format!("{}{:02}", TRACK_PREFIX, number))
+}
+
+/// Returns the virtual paths of every track of a sheet.
+pub fn track_paths(sheet_path: &Path, sheet: &CueSheet) -> Vec<PathBuf> {
+    sheet
+        .tracks
+        .iter()
+        .map(|track| track_path(sheet_path, track.number))
+        .collect()
+}
+
+/// Finds the sheets among the files of a directory, either .cue files or audio files with an embedded sheet.
+///
+/// Returns the virtual paths of their tracks, and the audio files they cover, which shouldn't be listed on their own.
+pub fn sheet_tracks(files: &[PathBuf]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut tracks = Vec::new();
+    let mut covered = Vec::new();
+    for path in files.iter().filter(|path| is_cue(path)) {
+        let Some(sheet) = read_sheet(path) else {
+            continue;
+        };
+        if sheet.tracks.is_empty() || !sheet.tracks.iter().all(|track| track.file.is_file()) {
+            continue;
+        }
+        tracks.extend(track_paths(path, &sheet));
+        covered.extend(sheet.tracks.into_iter().map(|track| track.file));
+    }
+    for path in files.iter().filter(|path| !is_cue(path)) {
+        if covered.contains(path) {
+            continue;
+        }
+        if let Some(sheet) = read_sheet(path)
+            && !sheet.tracks.is_empty()
+        {
+            tracks.extend(track_paths(path, &sheet));
+            covered.push(path.clone());
+        }
+    }
+    (tracks, covered)
+}
+
+/// Returns true if the path names a track inside a sheet rather than a real file.
+pub fn is_virtual(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(TRACK_PREFIX))
+        && path.parent().is_some_and(Path::is_file)
+}
+
+/// Resolves a virtual track path to its sheet and track.
+pub fn resolve(path: &Path) -> Option<(CueSheet, CueTrack)> {
+    if !is_virtual(path) {
+        return None;
+    }
+    let number: u16 = path
+        .file_name()?
+        .to_string_lossy()
+        .strip_prefix(TRACK_PREFIX)?
+        .parse()
+        .ok()?;
+    let sheet = read_sheet(path.parent()?)?;
+    let track = sheet
+        .tracks
+        .iter()
+        .find(|track| track.number == number)?
+        .clone();
+    Some((sheet, track))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "\u{feff}REM GENRE Jazz
+REM DATE 1959
+REM REPLAYGAIN_ALBUM_GAIN -7.50 dB
+PERFORMER \"Miles Davis\"
+TITLE \"Kind of Blue\"
+FILE \"kind of blue.flac\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"So What\"
+    REM REPLAYGAIN_TRACK_GAIN -6.20 dB
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Freddie Freeloader\"
+    PERFORMER \"Miles Davis Sextet\"
+    INDEX 00 09:20:30
+    INDEX 01 09:22:00
+FILE \"bonus.flac\" WAVE
+  TRACK 03 AUDIO
+    TITLE Bonus
+    INDEX 01 00:01:37
+";
+
+    #[test]
+    fn parses_album_and_tracks() {
+        let sheet = parse(SHEET, Path::new("/music"), None);
+        assert_eq!(sheet.title.as_deref(), Some("Kind of Blue"));
+        assert_eq!(sheet.date, Some(1959));
+        assert_eq!(sheet.genre.as_deref(), Some("Jazz"));
+        assert_eq!(sheet.tracks.len(), 3);
+
+        let [first, second, third] = &sheet.tracks[..] else {
+            unreachable!()
+        };
+        assert_eq!(first.performer.as_deref(), Some("Miles Davis"));
+        assert_eq!(second.performer.as_deref(), Some("Miles Davis Sextet"));
+        assert_eq!(third.title.as_deref(), Some("Bonus"));
+        assert_eq!(first.file, Path::new("/music/kind of blue.flac"));
+        assert_eq!(third.file, Path::new("/music/bonus.flac"));
+
+        assert_eq!(first.replay_gain.track_gain, Some(-6.2));
+        assert_eq!(second.replay_gain.track_gain, None);
+        assert_eq!(second.replay_gain.album_gain, Some(-7.5));
+    }
+
+    #[test]
+    fn tracks_end_where_the_next_one_starts() {
+        let sheet = parse(SHEET, Path::new("/music"), None);
+        let [first, second, third] = &sheet.tracks[..] else {
+            unreachable!()
+        };
+        // The pregap of track 2 stays with track 1.
+        assert_eq!(first.end, Some(second.start));
+        assert_eq!(second.start, Duration::from_secs(562));
+        // The last track of a file runs to its end.
+        assert_eq!(second.end, None);
+        assert_eq!(third.start, Duration::from_millis(1000 + 37 * 1000 / 75));
+        assert_eq!(second.duration(Some(600.0)), Some(38.0));
+    }
+
+    #[test]
+    fn tracks_out_of_order_or_without_a_start_are_dropped() {
+        let sheet = parse(
+            "FILE \"a.flac\" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:10:00
+  TRACK 02 AUDIO
+    INDEX 00 00:20:00
+  TRACK 03 AUDIO
+    INDEX 01 00:05:00
+  TRACK 04 AUDIO
+    INDEX 01 00:30:00
+FILE \"b.flac\" WAVE
+  TRACK 05 AUDIO
+    INDEX 01 00:00:00
+",
+            Path::new("/music"),
+            None,
+        );
+        let numbers: Vec<u16> = sheet.tracks.iter().map(|track| track.number).collect();
+        assert_eq!(numbers, [1, 4, 5]);
+        assert_eq!(sheet.tracks[0].duration(None), Some(20.0));
+        assert_eq!(sheet.tracks[2].start, Duration::ZERO);
+
+        let backwards = CueTrack {
+            start: Duration::from_secs(30),
+            end: Some(Duration::from_secs(10)),
+            ..CueTrack::default()
+        };
+        assert_eq!(backwards.duration(None), Some(0.0));
+        let past_the_end = CueTrack {
+            end: None,
+            ..backwards
+        };
+        assert_eq!(past_the_end.duration(Some(20.0)), Some(0.0));
+    }
+
+    #[test]
+    fn embedded_sheets_point_at_their_file() {
+        let file = Path::new("/music/album.flac");
+        let sheet = parse(SHEET, Path::new("/music"), Some(file));
+        assert!(sheet.tracks.iter().all(|track| track.file == file));
+    }
+}
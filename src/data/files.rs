@@ -0,0 +1,52 @@
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf},
+};
+
+/// Extensions of the files that can be played.
+pub const PLAYABLE_EXTS: [&str; 6] = ["flac", "m4a", "m4b", "mp3", "ogg", "wav"];
+
+/// Returns true if the path has a playable extension.
+pub fn is_playable(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        PLAYABLE_EXTS.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str())
+    })
+}
+
+/// Returns true if the path is an http(s) URL rather than a file.
+pub fn is_stream(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    ["http://", "https://"].iter().any(|scheme| {
+        path.get(..scheme.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// Returns the playable files under a path, recursing into directories and skipping hidden entries.
+pub fn collect_playable(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return match is_playable(path) {
+            true => vec![path.to_path_buf()],
+            false => Vec::new(),
+        };
+    }
+
+    let Ok(entries) = read_dir(path) else {
+        return Vec::new();
+    };
+    let mut children: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    children.sort();
+
+    children
+        .iter()
+        .flat_map(|child| collect_playable(child))
+        .collect()
+}
@@ -0,0 +1,390 @@
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned, pki_types::ServerName};
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+/// Redirects followed before a request gives up.
+const MAX_REDIRECTS: usize = 5;
+/// How long connecting and each read may take.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The parts of an http or https URL needed to make a request.
+#[derive(Debug, PartialEq)]
+pub struct Url {
+    pub secure: bool,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Url {
+    /// Parses an http or https URL. IPv6 hosts are written in brackets, as in `http://[::1]:8000/`.
+    pub fn parse(url: &str) -> Result<Self, Box<dyn Error>> {
+        let (secure, rest) = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => (false, rest),
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("https") => (true, rest),
+            _ => return Err(format!("not an http url: {}", url).into()),
+        };
+        let default_port = match secure {
+            true => 443,
+            false => 80,
+        };
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let authority = authority.rsplit('@').next().unwrap_or_default();
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => match bracketed.split_once(']') {
+                Some((host, "")) => (host, default_port),
+                Some((host, port)) => match port.strip_prefix(':') {
+                    Some(port) => (host, port.parse()?),
+                    None => return Err(format!("malformed host in {}", url).into()),
+                },
+                None => return Err(format!("unclosed [ in {}", url).into()),
+            },
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, port.parse()?),
+                None => (authority, default_port),
+            },
+        };
+        if host.is_empty() {
+            return Err(format!("no host in {}", url).into());
+        }
+        let path = match path.starts_with('?') {
+            true => format!("/{}", path),
+            false => path.to_string(),
+        };
+        Ok(Self {
+            secure,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// Returns the host as written in a URL, with IPv6 hosts in brackets and the port unless it's the default.
+    fn authority(&self) -> String {
+        let host = match self.host.contains(':') {
+            true => format!("[{}]", self.host),
+            false => self.host.clone(),
+        };
+        match (self.secure, self.port) {
+            (true, 443) | (false, 80) => host,
+            _ => format!("{}:{}", host, self.port),
+        }
+    }
+
+    /// Resolves the target of a redirect, which may be relative to this URL.
+    fn join(&self, location: &str) -> String {
+        let scheme = match self.secure {
+            true => "https",
+            false => "http",
+        };
+        match location.contains("://") {
+            true => location.to_string(),
+            false if location.starts_with('/') => {
+                format!("{}://{}{}", scheme, self.authority(), location)
+            }
+            false => {
+                let dir = self.path.rsplit_once('/').map_or("", |(dir, _)| dir);
+                format!("{}://{}{}/{}", scheme, self.authority(), dir, location)
+            }
+        }
+    }
+}
+
+/// A response whose body hasn't been read yet.
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl Response {
+    /// Returns the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the length of the body, if the server announced it.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("content-length")?.parse().ok()
+    }
+
+    /// Reads the whole body as text, replacing invalid UTF-8.
+    pub fn text(mut self) -> io::Result<String> {
+        let mut bytes = Vec::new();
+        self.body.read_to_end(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Makes a GET request with extra headers, following redirects. Statuses other than 2xx are errors.
+pub fn get(url: &str, headers: &[(&str, &str)]) -> Result<Response, Box<dyn Error>> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let parsed = Url::parse(&url)?;
+        let response = request(&parsed, headers)?;
+        match response.status {
+            200..=299 => return Ok(response),
+            301 | 302 | 303 | 307 | 308 => match response.header("location") {
+                Some(location) => url = parsed.join(location),
+                None => return Err(format!("HTTP {} without a location", response.status).into()),
+            },
+            status => return Err(format!("HTTP {}", status).into()),
+        }
+    }
+    Err("too many redirects".into())
+}
+
+/// Sends a single GET request and reads the response head.
+fn request(url: &Url, headers: &[(&str, &str)]) -> Result<Response, Box<dyn Error>> {
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("couldn't resolve {}", url.host))?;
+    let tcp = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+    let mut stream: Box<dyn Stream> = match url.secure {
+        true => {
+            let name = ServerName::try_from(url.host.clone())?;
+            let connection = ClientConnection::new(tls_config(), name)?;
+            Box::new(StreamOwned::new(connection, tcp))
+        }
+        false => Box::new(tcp),
+    };
+
+    let mut head = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: rmpr\r\nAccept: */*\r\nAccept-Encoding: identity\r\nConnection: close\r\n",
+        url.path,
+        url.authority()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+
+    let mut reader = BufReader::new(stream);
+    let status_line = read_line(&mut reader)?;
+    // Shoutcast servers answer with "ICY 200 OK" instead of an HTTP status line.
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| format!("malformed status line: {}", status_line))?;
+
+    let mut response_headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            response_headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut response = Response {
+        status,
+        headers: response_headers,
+        body: Box::new(io::empty()),
+    };
+    response.body = match (
+        response
+            .header("transfer-encoding")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked")),
+        response.content_length(),
+    ) {
+        (true, _) => Box::new(Chunked::new(reader)),
+        (false, Some(length)) => Box::new(reader.take(length)),
+        (false, None) => Box::new(reader),
+    };
+    Ok(response)
+}
+
+/// A connection a request can be sent over, plain or encrypted.
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+/// Returns the TLS settings shared by all connections, trusting the Mozilla root certificates.
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth(),
+            )
+        })
+        .clone()
+}
+
+/// Reads a CRLF terminated line, without the terminator.
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed",
+        ));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Decodes a body sent with chunked transfer encoding.
+struct Chunked<R> {
+    inner: R,
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> Chunked<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let line = read_line(&mut self.inner)?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            self.remaining = u64::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad chunk size"))?;
+            if self.remaining == 0 {
+                // Skips the trailers.
+                while !read_line(&mut self.inner)?.is_empty() {}
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.remaining as usize);
+        let read = self.inner.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed in a chunk",
+            ));
+        }
+        self.remaining -= read as u64;
+        if self.remaining == 0 {
+            read_line(&mut self.inner)?;
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    /// Serves each accepted connection with the given handler, which gets the request head.
+    /// Returns the base URL of the server.
+    pub fn serve<F>(handler: F) -> String
+    where
+        F: Fn(&str, &mut TcpStream) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                while let Ok(line) = read_line(&mut reader) {
+                    if line.is_empty() {
+                        break;
+                    }
+                    head.push_str(&line);
+                    head.push('\n');
+                }
+                handler(&head, &mut stream);
+            }
+        });
+        format!("http://127.0.0.1:{}", port)
+    }
+
+    #[test]
+    fn parses_urls() {
+        let url = Url::parse("http://radio.example:8000/live.mp3?x=1#top").unwrap();
+        assert_eq!(url.host, "radio.example");
+        assert_eq!(url.port, 8000);
+        assert_eq!(url.path, "/live.mp3?x=1");
+        assert_eq!(Url::parse("http://example.org").unwrap().path, "/");
+        assert!(Url::parse("/music/song.mp3").is_err());
+
+        let url = Url::parse("https://example.org/feed").unwrap();
+        assert!(url.secure);
+        assert_eq!(url.port, 443);
+        assert_eq!(url.join("/episode.mp3"), "https://example.org/episode.mp3");
+
+        let url = Url::parse("http://[::1]:8000/live").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", 8000));
+        assert_eq!(url.join("next"), "http://[::1]:8000/next");
+        assert_eq!(Url::parse("https://[2001:db8::1]/").unwrap().port, 443);
+        assert!(Url::parse("http://[::1/").is_err());
+        assert!(Url::parse("http://[::1]8000/").is_err());
+    }
+
+    #[test]
+    fn https_requests_start_a_tls_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut record = [0; 3];
+            stream.read_exact(&mut record).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+            record
+        });
+        assert!(get(&format!("https://127.0.0.1:{}/", port), &[]).is_err());
+        // A TLS handshake record of version 3.x, rather than a plain request.
+        assert_eq!(server.join().unwrap()[..2], [0x16, 0x03]);
+    }
+
+    #[test]
+    fn follows_redirects_and_decodes_chunks() {
+        let base = serve(|head, stream| {
+            let response = match head.starts_with("GET /old ") {
+                true => "HTTP/1.1 302 Found\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n"
+                    .to_string(),
+                false => "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"
+                    .to_string(),
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        let response = get(&format!("{}/old", base), &[]).unwrap();
+        assert_eq!(response.text().unwrap(), "hello world");
+
+        let missing = serve(|_, stream| {
+            stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+        assert!(get(&format!("{}/gone", missing), &[]).is_err());
+    }
+}
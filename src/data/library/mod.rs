@@ -0,0 +1,366 @@
+pub mod query;
+
+use crate::data::{
+    cue::{is_cue, is_virtual, resolve, sheet_tracks},
+    files::collect_playable,
+    metadata::audio_probe::{probe_duration, read_tags},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, read_dir},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::UNIX_EPOCH,
+};
+use symphonia::core::meta::StandardTagKey;
+
+/// The tags and length of a file in the library.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct LibraryTrack {
+    /// Modification time of the file when it was indexed, in seconds since the epoch.
+    pub modified: u64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<i32>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    /// In seconds.
+    pub duration: Option<f64>,
+    #[serde(skip_serializing_if = "TrackStats::is_empty")]
+    pub stats: TrackStats,
+}
+
+/// What's known of how a track is listened to.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct TrackStats {
+    pub plays: u32,
+    /// Times the track was skipped before it counted as played.
+    pub skips: u32,
+    /// When the track was last played, in seconds since the epoch.
+    pub last_played: Option<u64>,
+    /// From 1 to 5 stars, 0 when unrated.
+    pub rating: u8,
+}
+
+impl TrackStats {
+    pub fn is_empty(&self) -> bool {
+        *self == TrackStats::default()
+    }
+}
+
+impl LibraryTrack {
+    /// Checks whether two entries are the same recording, by their tags and length, to follow files that moved.
+    fn same_recording(&self, other: &LibraryTrack) -> bool {
+        self.title.is_some()
+            && self.title == other.title
+            && self.artist == other.artist
+            && self.album == other.album
+            && match (self.duration, other.duration) {
+                (Some(a), Some(b)) => (a - b).abs() < 1.0,
+                _ => false,
+            }
+    }
+}
+
+/// Encapsulates the index of the music directory, keyed by file path.
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct Library {
+    pub tracks: BTreeMap<String, LibraryTrack>,
+}
+
+impl Library {
+    /// Returns the path of the library index in the user's data directory.
+    fn file_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rmpr/library.toml"))
+    }
+
+    /// Loads the library index, or an empty one if the file is missing or unreadable.
+    pub fn load() -> Self {
+        Library::file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the library index to the user's data directory.
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Library::file_path().ok_or("no data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Brings the index up to date with the files under the root: new and modified files are read,
+    /// missing ones dropped. Returns how many files were read.
+    /// Files split by a CUE sheet are indexed as the sheet's tracks, like the browser lists them.
+    /// Statistics are kept when a file is read again, and follow a file that moved when its tags and length match.
+    pub fn refresh(&mut self, root: &Path) -> usize {
+        let paths = collect_tracks(root);
+        let mut tracks = BTreeMap::new();
+        let mut added = Vec::new();
+        let mut read = 0;
+        for path in paths {
+            let key = path.to_string_lossy().to_string();
+            let modified = modified(&path);
+            let track = match self.tracks.remove(&key) {
+                Some(track) if track.modified == modified => track,
+                old => {
+                    read += 1;
+                    if old.is_none() {
+                        added.push(key.clone());
+                    }
+                    LibraryTrack {
+                        modified,
+                        stats: old.map(|old| old.stats).unwrap_or_default(),
+                        ..read_track(&path)
+                    }
+                }
+            };
+            tracks.insert(key, track);
+        }
+
+        // What's left are the files that disappeared, some of which may be among the new ones.
+        let mut gone: Vec<LibraryTrack> = self
+            .tracks
+            .values()
+            .filter(|track| !track.stats.is_empty())
+            .cloned()
+            .collect();
+        for key in added {
+            let Some(track) = tracks.get_mut(&key) else {
+                continue;
+            };
+            if let Some(index) = gone.iter().position(|old| old.same_recording(track)) {
+                track.stats = gone.remove(index).stats;
+            }
+        }
+        self.tracks = tracks;
+        read
+    }
+
+    /// Returns the statistics of a track, adding it to the index if it isn't there yet.
+    /// It's read on the next refresh if it's under the music directory, and dropped otherwise.
+    pub fn stats_mut(&mut self, path: &Path) -> &mut TrackStats {
+        &mut self
+            .tracks
+            .entry(path.to_string_lossy().to_string())
+            .or_default()
+            .stats
+    }
+
+    /// Returns the statistics of a track, if it has any.
+    pub fn stats(&self, path: &Path) -> Option<&TrackStats> {
+        self.tracks
+            .get(&*path.to_string_lossy())
+            .map(|track| &track.stats)
+    }
+}
+
+/// Returns the playable files under the root, with the ones covered by a CUE sheet replaced by the sheet's tracks.
+fn collect_tracks(root: &Path) -> Vec<PathBuf> {
+    let mut dirs: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in collect_playable(root) {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        dirs.entry(dir).or_default().push(path);
+    }
+
+    let mut paths = Vec::new();
+    for (dir, mut files) in dirs {
+        let mut sheets: Vec<PathBuf> = read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                is_cue(path)
+                    && !path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        sheets.sort();
+        files.extend(sheets);
+        let (tracks, covered) = sheet_tracks(&files);
+        paths.extend(
+            files
+                .into_iter()
+                .filter(|path| !is_cue(path) && !covered.contains(path)),
+        );
+        paths.extend(tracks);
+    }
+    paths
+}
+
+/// Returns when a file was last modified, in seconds since the epoch. Tracks of a sheet go by the sheet's file.
+fn modified(path: &Path) -> u64 {
+    if is_virtual(path) {
+        return path.parent().map_or(0, modified);
+    }
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_secs())
+}
+
+/// Parses the number a value starts with, like the 3 of "3/12" or the year of "1999-05-01".
+fn leading_number<T: FromStr>(value: &str) -> Option<T> {
+    let digits: String = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Reads the tags of any playable file, and its length from the decoder.
+/// Tracks of a sheet are read from the sheet, and their length is the range they cover.
+fn read_track(path: &Path) -> LibraryTrack {
+    if let Some((sheet, track)) = resolve(path) {
+        return LibraryTrack {
+            title: track.title.clone(),
+            artist: track.performer.clone(),
+            album: sheet.title.clone(),
+            album_artist: sheet.performer.clone(),
+            genre: sheet.genre.clone(),
+            year: sheet.date,
+            track: Some(track.number as u32),
+            duration: track.duration(probe_duration(&track.file)),
+            ..LibraryTrack::default()
+        };
+    }
+
+    let mut track = LibraryTrack::default();
+    for tag in read_tags(path) {
+        // RIFF INFO strings come with their NUL terminator.
+        let value = tag.value.to_string();
+        let value = value.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        let slot = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => &mut track.title,
+            Some(StandardTagKey::Artist) => &mut track.artist,
+            Some(StandardTagKey::Album) => &mut track.album,
+            Some(StandardTagKey::AlbumArtist) => &mut track.album_artist,
+            Some(StandardTagKey::Genre) => &mut track.genre,
+            Some(StandardTagKey::Date) | Some(StandardTagKey::OriginalDate) => {
+                track.year = track.year.or_else(|| leading_number(value));
+                continue;
+            }
+            Some(StandardTagKey::TrackNumber) => {
+                track.track = track.track.or_else(|| leading_number(value));
+                continue;
+            }
+            Some(StandardTagKey::DiscNumber) => {
+                track.disc = track.disc.or_else(|| leading_number(value));
+                continue;
+            }
+            _ => continue,
+        };
+        if slot.is_none() && !value.is_empty() {
+            *slot = Some(value.to_string());
+        }
+    }
+    track.duration = probe_duration(path);
+    track
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::{TempDir, add_wav_title, write_wav};
+
+    #[test]
+    fn refresh_reads_new_files_and_drops_missing_ones() {
+        let dir = TempDir::new("library");
+        fs::create_dir_all(dir.join("album")).unwrap();
+        for name in ["album/one.wav", "two.wav"] {
+            write_wav(&dir.join(name), 1, 8000, [0.0; 16000]);
+        }
+
+        let mut library = Library::default();
+        assert_eq!(library.refresh(dir.path()), 2);
+        let one = dir.join("album/one.wav").to_string_lossy().to_string();
+        assert_eq!(library.tracks[&one].duration, Some(2.0));
+        // Unchanged files aren't read again.
+        assert_eq!(library.refresh(dir.path()), 0);
+
+        // A file that moves keeps its statistics, as long as it has tags to recognise it by.
+        library.stats_mut(&dir.join("album/one.wav")).plays = 3;
+        library.stats_mut(&dir.join("two.wav")).plays = 5;
+        fs::rename(dir.join("album/one.wav"), dir.join("one.wav")).unwrap();
+        fs::remove_file(dir.join("two.wav")).unwrap();
+        add_wav_title(&dir.join("one.wav"), "One");
+        library.tracks.get_mut(&one).unwrap().title = Some("One".to_string());
+        library.refresh(dir.path());
+        let moved = dir.join("one.wav").to_string_lossy().to_string();
+        assert_eq!(library.tracks.keys().collect::<Vec<_>>(), [&moved]);
+        assert_eq!(library.tracks[&moved].title.as_deref(), Some("One"));
+        assert_eq!(library.tracks[&moved].stats.plays, 3);
+    }
+
+    #[test]
+    fn sheets_are_indexed_as_their_tracks() {
+        let dir = TempDir::new("library-cue");
+        write_wav(&dir.join("live.wav"), 1, 8000, [0.0; 48000]);
+        write_wav(&dir.join("single.wav"), 1, 8000, [0.0; 8000]);
+        fs::write(
+            dir.join("live.cue"),
+            "PERFORMER \"Band\"\nTITLE \"Live\"\nFILE \"live.wav\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Opening\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Closing\"\n    INDEX 01 00:02:00\n",
+        )
+        .unwrap();
+
+        let mut library = Library::default();
+        assert_eq!(library.refresh(dir.path()), 3);
+        let key = |path: PathBuf| path.to_string_lossy().to_string();
+        let closing = dir.join("live.cue/track02");
+        assert_eq!(
+            library.tracks.keys().cloned().collect::<Vec<_>>(),
+            [
+                key(dir.join("live.cue/track01")),
+                key(closing.clone()),
+                key(dir.join("single.wav")),
+            ]
+        );
+        let track = &library.tracks[&key(closing.clone())];
+        assert_eq!(track.title.as_deref(), Some("Closing"));
+        assert_eq!(track.album.as_deref(), Some("Live"));
+        assert_eq!(track.artist.as_deref(), Some("Band"));
+        assert_eq!(track.track, Some(2));
+        assert_eq!(track.duration, Some(4.0));
+        assert_eq!(library.refresh(dir.path()), 0);
+
+        let query = query::Query::parse("title:closing").unwrap();
+        assert_eq!(query.run(&library), [closing]);
+    }
+
+    #[test]
+    fn index_round_trips_without_missing_tags() {
+        let mut library = Library::default();
+        library.tracks.insert(
+            "/music/a.flac".to_string(),
+            LibraryTrack {
+                modified: 7,
+                title: Some("A".to_string()),
+                year: Some(1999),
+                duration: Some(61.5),
+                ..LibraryTrack::default()
+            },
+        );
+        library
+            .tracks
+            .insert("/music/b.wav".to_string(), LibraryTrack::default());
+        let loaded: Library = toml::from_str(&toml::to_string(&library).unwrap()).unwrap();
+        assert_eq!(loaded.tracks, library.tracks);
+
+        assert_eq!(leading_number::<u32>(" 3/12"), Some(3));
+        assert_eq!(leading_number::<i32>("1999-05-01"), Some(1999));
+        assert_eq!(leading_number::<u32>("side A"), None);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::data::library::{Library, LibraryTrack};
+use std::{
+    cmp::Ordering,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A property of library tracks that queries can test and sort by.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Key {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    Path,
+    Year,
+    Track,
+    Disc,
+    Duration,
+    Rating,
+    Plays,
+    Skips,
+    /// Days since the track was last played.
+    LastPlayed,
+}
+
+impl Key {
+    /// Finds a key by its name in queries.
+    fn parse(name: &str) -> Option<Key> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "title" => Key::Title,
+            "artist" => Key::Artist,
+            "album" => Key::Album,
+            "albumartist" => Key::AlbumArtist,
+            "genre" => Key::Genre,
+            "path" => Key::Path,
+            "year" => Key::Year,
+            "track" => Key::Track,
+            "disc" => Key::Disc,
+            "duration" => Key::Duration,
+            "rating" => Key::Rating,
+            "plays" => Key::Plays,
+            "skips" => Key::Skips,
+            "lastplayed" => Key::LastPlayed,
+            _ => return None,
+        })
+    }
+
+    /// Numeric keys are compared as numbers, the others as case-insensitive text.
+    fn is_numeric(self) -> bool {
+        !matches!(
+            self,
+            Key::Title | Key::Artist | Key::Album | Key::AlbumArtist | Key::Genre | Key::Path
+        )
+    }
+}
+
+/// A value of a track for a key.
+#[derive(PartialEq, PartialOrd, Debug)]
+enum Value {
+    Text(String),
+    Number(f64),
+}
+
+impl LibraryTrack {
+    /// Returns the value of a key, text lowercased so it compares without case.
+    fn value(&self, key: Key, path: &str) -> Option<Value> {
+        let text =
+            |text: &Option<String>| text.as_ref().map(|text| Value::Text(text.to_lowercase()));
+        match key {
+            Key::Title => text(&self.title),
+            Key::Artist => text(&self.artist),
+            Key::Album => text(&self.album),
+            Key::AlbumArtist => text(&self.album_artist).or_else(|| text(&self.artist)),
+            Key::Genre => text(&self.genre),
+            Key::Path => Some(Value::Text(path.to_lowercase())),
+            Key::Year => self.year.map(|year| Value::Number(year as f64)),
+            Key::Track => self.track.map(|track| Value::Number(track as f64)),
+            Key::Disc => self.disc.map(|disc| Value::Number(disc as f64)),
+            Key::Duration => self.duration.map(Value::Number),
+            Key::Rating => Some(Value::Number(self.stats.rating as f64)),
+            Key::Plays => Some(Value::Number(self.stats.plays as f64)),
+            Key::Skips => Some(Value::Number(self.stats.skips as f64)),
+            Key::LastPlayed => self.stats.last_played.map(|played| {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |now| now.as_secs());
+                Value::Number(now.saturating_sub(played) as f64 / 86400.0)
+            }),
+        }
+    }
+}
+
+/// What a term checks.
+#[derive(PartialEq, Debug)]
+enum Test {
+    /// Any of the title, artist, album and album artist contains the words.
+    Anywhere(String),
+    Contains(Key, String),
+    Equals(Key, String),
+    Compare(Key, Ordering, bool, f64),
+    /// Inclusive on both ends; either may be open.
+    Range(Key, Option<f64>, Option<f64>),
+}
+
+/// A condition every track of the results meets, or fails when negated.
+#[derive(PartialEq, Debug)]
+struct Term {
+    negated: bool,
+    test: Test,
+}
+
+impl Term {
+    fn matches(&self, track: &LibraryTrack, path: &str) -> bool {
+        let number = |key| match track.value(key, path) {
+            Some(Value::Number(number)) => Some(number),
+            _ => None,
+        };
+        let text = |key| match track.value(key, path) {
+            Some(Value::Text(text)) => Some(text),
+            _ => None,
+        };
+        let matched = match &self.test {
+            Test::Anywhere(words) => [Key::Title, Key::Artist, Key::Album, Key::AlbumArtist]
+                .into_iter()
+                .any(|key| text(key).is_some_and(|text| text.contains(words))),
+            Test::Contains(key, words) => text(*key).is_some_and(|text| text.contains(words)),
+            Test::Equals(key, words) => text(*key).is_some_and(|text| text == *words),
+            Test::Compare(key, ordering, or_equal, value) => number(*key).is_some_and(|number| {
+                let order = number.partial_cmp(value);
+                order == Some(*ordering) || (*or_equal && order == Some(Ordering::Equal))
+            }),
+            Test::Range(key, min, max) => number(*key).is_some_and(|number| {
+                min.is_none_or(|min| number >= min) && max.is_none_or(|max| number <= max)
+            }),
+        };
+        matched != self.negated
+    }
+}
+
+/// A parsed query: the terms tracks must match, how the results are ordered and how many are kept.
+#[derive(PartialEq, Debug)]
+pub struct Query {
+    terms: Vec<Term>,
+    /// Keys to sort by, descending when true. Results are in path order otherwise.
+    sort: Vec<(Key, bool)>,
+    limit: Option<usize>,
+}
+
+impl Query {
+    /// Parses a query like `genre:jazz year:1955..1965 -artist:"Miles Davis" sort:-year limit:50`.
+    ///
+    /// - `key:words` matches tracks whose key contains the words, `key=words` the exact value.
+    /// - Numeric keys take `key:n`, ranges `key:a..b` (either end open) and `key>n`, `>=`, `<`, `<=`.
+    /// - Words without a key are looked for in the title, artist, album and album artist.
+    /// - A leading `-` negates a term; values with spaces go in double quotes.
+    /// - Text is compared without case; durations are in seconds or `m:ss`, `lastplayed` in days ago.
+    pub fn parse(text: &str) -> Result<Query, String> {
+        let mut query = Query {
+            terms: Vec::new(),
+            sort: Vec::new(),
+            limit: None,
+        };
+        for word in split_words(text)? {
+            let (negated, word) = match word.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, word.as_str()),
+            };
+            let name_end = word
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(word.len());
+            let (name, rest) = word.split_at(name_end);
+            let operator = [">=", "<=", ":", "=", ">", "<"]
+                .into_iter()
+                .find(|operator| rest.starts_with(operator));
+
+            match (name.to_ascii_lowercase().as_str(), operator) {
+                ("sort", Some(":")) if !negated => {
+                    let value = &rest[1..];
+                    let (descending, name) = match value.strip_prefix('-') {
+                        Some(name) => (true, name),
+                        None => (false, value),
+                    };
+                    let key = Key::parse(name).ok_or_else(|| format!("can't sort by {}", name))?;
+                    query.sort.push((key, descending));
+                }
+                ("limit", Some(":")) if !negated => {
+                    let value = &rest[1..];
+                    query.limit = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("limit must be a number, not {}", value))?,
+                    );
+                }
+                (_, Some(operator)) if !name.is_empty() => {
+                    let key = Key::parse(name).ok_or_else(|| format!("unknown key {}", name))?;
+                    let value = unquote(&rest[operator.len()..]);
+                    query.terms.push(Term {
+                        negated,
+                        test: test(key, operator, &value)?,
+                    });
+                }
+                _ => query.terms.push(Term {
+                    negated,
+                    test: Test::Anywhere(unquote(word).to_lowercase()),
+                }),
+            }
+        }
+        Ok(query)
+    }
+
+    /// Returns the paths of the library tracks that match, sorted and limited.
+    pub fn run(&self, library: &Library) -> Vec<PathBuf> {
+        let mut results: Vec<(&String, &LibraryTrack)> = library
+            .tracks
+            .iter()
+            .filter(|(path, track)| self.terms.iter().all(|term| term.matches(track, path)))
+            .collect();
+        results.sort_by(|(a_path, a), (b_path, b)| {
+            self.sort
+                .iter()
+                .map(|(key, descending)| {
+                    let order = match (a.value(*key, a_path), b.value(*key, b_path)) {
+                        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                        // Tracks without the value go last either way.
+                        (Some(_), None) => return Ordering::Less,
+                        (None, Some(_)) => return Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    };
+                    match descending {
+                        true => order.reverse(),
+                        false => order,
+                    }
+                })
+                .find(|order| *order != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        results
+            .into_iter()
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|(path, _)| PathBuf::from(path))
+            .collect()
+    }
+}
+
+/// Builds the test of a term with a key.
+fn test(key: Key, operator: &str, value: &str) -> Result<Test, String> {
+    if !key.is_numeric() {
+        return match operator {
+            ":" => Ok(Test::Contains(key, value.to_lowercase())),
+            "=" => Ok(Test::Equals(key, value.to_lowercase())),
+            _ => Err(format!("can't compare text with {}", operator)),
+        };
+    }
+    let number =
+        |value: &str| parse_number(value).ok_or_else(|| format!("{} isn't a number", value));
+    Ok(match (operator, value.split_once("..")) {
+        (":", Some((min, max))) => Test::Range(
+            key,
+            (!min.is_empty()).then(|| number(min)).transpose()?,
+            (!max.is_empty()).then(|| number(max)).transpose()?,
+        ),
+        (":" | "=", None) => Test::Range(key, Some(number(value)?), Some(number(value)?)),
+        (">", _) => Test::Compare(key, Ordering::Greater, false, number(value)?),
+        (">=", _) => Test::Compare(key, Ordering::Greater, true, number(value)?),
+        ("<", _) => Test::Compare(key, Ordering::Less, false, number(value)?),
+        ("<=", _) => Test::Compare(key, Ordering::Less, true, number(value)?),
+        _ => return Err(format!("can't use {} with a range", operator)),
+    })
+}
+
+/// Parses a number, or minutes and seconds as `m:ss`.
+fn parse_number(value: &str) -> Option<f64> {
+    match value.split_once(':') {
+        Some((minutes, seconds)) => {
+            Some(minutes.parse::<f64>().ok()? * 60.0 + seconds.parse::<f64>().ok()?)
+        }
+        None => value.parse().ok(),
+    }
+}
+
+/// Splits a query on spaces outside double quotes, keeping the quotes.
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Removes the double quotes around a value, or inside it.
+fn unquote(value: &str) -> String {
+    value.replace('"', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn library() -> Library {
+        let track = |artist: &str, album: &str, genre: &str, year: i32, track: u32| LibraryTrack {
+            title: Some(format!("{} {}", album, track)),
+            artist: Some(artist.to_string()),
+            album: Some(album.to_string()),
+            genre: Some(genre.to_string()),
+            year: Some(year),
+            track: Some(track),
+            duration: Some(200.0 + track as f64),
+            ..LibraryTrack::default()
+        };
+        let mut library = Library::default();
+        for (path, track) in [
+            (
+                "/m/a.flac",
+                track("Miles Davis", "Kind of Blue", "Jazz", 1959, 1),
+            ),
+            (
+                "/m/b.flac",
+                track("Miles Davis", "Kind of Blue", "Jazz", 1959, 2),
+            ),
+            (
+                "/m/c.mp3",
+                track("John Coltrane", "Giant Steps", "Jazz", 1960, 1),
+            ),
+            (
+                "/m/d.mp3",
+                track("Bill Evans", "Sunday at the Village", "Modal Jazz", 1961, 3),
+            ),
+            ("/m/e.ogg", track("Queen", "Jazz", "Rock", 1978, 1)),
+        ] {
+            library.tracks.insert(path.to_string(), track);
+        }
+        library
+            .tracks
+            .insert("/m/f.wav".to_string(), LibraryTrack::default());
+        library
+    }
+
+    fn run(query: &str) -> Vec<String> {
+        Query::parse(query)
+            .unwrap()
+            .run(&library())
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn terms_filter_tracks() {
+        assert_eq!(
+            run(r#"genre:jazz year:1955..1965 -artist:"Miles Davis""#),
+            ["c.mp3", "d.mp3"]
+        );
+        assert_eq!(run("genre=jazz"), ["a.flac", "b.flac", "c.mp3"]);
+        assert_eq!(run("jazz"), ["e.ogg"]);
+        assert_eq!(run("year>=1961"), ["d.mp3", "e.ogg"]);
+        assert_eq!(run("year:..1959 track>1"), ["b.flac"]);
+        assert_eq!(run("duration<=3:22 path:.flac"), ["a.flac", "b.flac"]);
+        // Tracks without a year don't match the term, but do match it negated.
+        assert_eq!(run("-year:1900..2000"), ["f.wav"]);
+    }
+
+    #[test]
+    fn statistics_can_be_queried() {
+        let mut library = library();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let stats = library.stats_mut(Path::new("/m/c.mp3"));
+        stats.rating = 5;
+        stats.plays = 12;
+        stats.last_played = Some(now - 3 * 86400);
+        let stats = library.stats_mut(Path::new("/m/a.flac"));
+        stats.rating = 4;
+        stats.last_played = Some(now - 40 * 86400);
+        library.stats_mut(Path::new("/m/b.flac")).skips = 3;
+
+        let run = |query: &str| Query::parse(query).unwrap().run(&library);
+        assert_eq!(
+            run("genre:jazz rating>=4 sort:-rating"),
+            [PathBuf::from("/m/c.mp3"), PathBuf::from("/m/a.flac")]
+        );
+        assert_eq!(run("lastplayed<7"), [PathBuf::from("/m/c.mp3")]);
+        assert_eq!(run("skips>0 plays:0"), [PathBuf::from("/m/b.flac")]);
+        assert_eq!(run("rating:0").len(), 4);
+    }
+
+    #[test]
+    fn results_are_sorted_and_limited() {
+        assert_eq!(
+            run("sort:-year sort:track limit:4"),
+            ["e.ogg", "d.mp3", "c.mp3", "a.flac"]
+        );
+        assert_eq!(run("sort:albumartist limit:2"), ["d.mp3", "c.mp3"]);
+    }
+
+    #[test]
+    fn bad_queries_are_reported() {
+        assert!(Query::parse("mood>=4").is_err());
+        assert!(Query::parse("artist>b").is_err());
+        assert!(Query::parse("year:soon").is_err());
+        assert!(Query::parse("limit:all").is_err());
+        assert!(Query::parse(r#"title:"open"#).is_err());
+        assert!(Query::parse("year>1950..1960").is_err());
+    }
+}
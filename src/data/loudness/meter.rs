@@ -0,0 +1,328 @@
+use crate::handlers::dsp::biquad::Biquad;
+use std::{collections::VecDeque, f64::consts::PI};
+use symphonia::core::audio::Channels;
+
+/// Integrated loudness below which blocks are ignored, in LUFS.
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Offset of the relative gate from the absolute-gated loudness, in LU.
+const RELATIVE_GATE: f64 = -10.0;
+/// Taps per phase of the true peak interpolator.
+const PEAK_TAPS: usize = 12;
+/// Oversampling factor of the true peak interpolator.
+const PEAK_OVERSAMPLING: usize = 4;
+
+/// High shelf of the BS.1770 K-weighting filter, derived for any sample rate.
+fn k_shelf(sample_rate: f64) -> Biquad {
+    let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    Biquad::new(
+        (vh + vb * k / q + k * k) / a0,
+        2.0 * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    )
+}
+
+/// High pass of the BS.1770 K-weighting filter, derived for any sample rate.
+fn k_highpass(sample_rate: f64) -> Biquad {
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    Biquad::new(
+        1.0,
+        -2.0,
+        1.0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    )
+}
+
+/// Per channel filter and interpolation state.
+#[derive(Clone)]
+struct ChannelState {
+    shelf: Biquad,
+    highpass: Biquad,
+    weight: f64,
+    history: [f64; PEAK_TAPS],
+}
+
+/// Measures integrated loudness (EBU R128 / ITU BS.1770) and true peak of interleaved samples.
+pub struct LoudnessMeter {
+    channels: Vec<ChannelState>,
+    peak_phases: [[f64; PEAK_TAPS]; PEAK_OVERSAMPLING],
+    current_channel: usize,
+    frame_energy: f64,
+    sub_block_energy: f64,
+    sub_block_frames: usize,
+    sub_block_len: usize,
+    sub_blocks: VecDeque<f64>,
+    /// Mean square energy of every 400ms block, overlapping by 75%.
+    pub blocks: Vec<f64>,
+    /// Highest absolute value of the 4x oversampled signal.
+    pub true_peak: f64,
+}
+
+/// The usual layout of a file that only tells how many channels it has, in WAVE order.
+fn default_layout(count: u16) -> Option<Channels> {
+    let front = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+    let rear = Channels::REAR_LEFT | Channels::REAR_RIGHT;
+    let side = Channels::SIDE_LEFT | Channels::SIDE_RIGHT;
+    let centre = Channels::FRONT_CENTRE;
+    let lfe = Channels::LFE1;
+    match count {
+        1 => Some(centre),
+        2 => Some(front),
+        3 => Some(front | centre),
+        4 => Some(front | rear),
+        5 => Some(front | centre | rear),
+        6 => Some(front | centre | lfe | rear),
+        7 => Some(front | centre | lfe | Channels::REAR_CENTRE | side),
+        8 => Some(front | centre | lfe | rear | side),
+        _ => None,
+    }
+}
+
+/// BS.1770 weight of a channel: LFEs are excluded, surrounds get +1.5 dB, the rest are unweighted.
+fn channel_weight(channel: Channels) -> f64 {
+    let lfe = Channels::LFE1 | Channels::LFE2;
+    let surrounds = Channels::REAR_LEFT
+        | Channels::REAR_RIGHT
+        | Channels::REAR_CENTRE
+        | Channels::SIDE_LEFT
+        | Channels::SIDE_RIGHT
+        | Channels::REAR_LEFT_CENTRE
+        | Channels::REAR_RIGHT_CENTRE;
+    match (lfe.contains(channel), surrounds.contains(channel)) {
+        (true, _) => 0.0,
+        (false, true) => 1.41,
+        (false, false) => 1.0,
+    }
+}
+
+/// Returns the weight of each of the interleaved channels of a file.
+/// Without a layout matching the channel count, the usual one for the count is assumed, and past 8 channels none is weighted.
+pub fn channel_weights(layout: Option<Channels>, count: u16) -> Vec<f64> {
+    match layout
+        .filter(|layout| layout.count() == count as usize)
+        .or_else(|| default_layout(count))
+    {
+        Some(layout) => layout.iter().map(channel_weight).collect(),
+        None => vec![1.0; count as usize],
+    }
+}
+
+impl LoudnessMeter {
+    /// Creates a meter for interleaved samples with a weight per channel, see `channel_weights`.
+    pub fn new(weights: &[f64], sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+        let channels = weights
+            .iter()
+            .map(|weight| ChannelState {
+                shelf: k_shelf(rate),
+                highpass: k_highpass(rate),
+                weight: *weight,
+                history: [0.0; PEAK_TAPS],
+            })
+            .collect();
+
+        Self {
+            channels,
+            peak_phases: LoudnessMeter::interpolation_phases(),
+            current_channel: 0,
+            frame_energy: 0.0,
+            sub_block_energy: 0.0,
+            sub_block_frames: 0,
+            sub_block_len: (sample_rate as usize / 10).max(1),
+            sub_blocks: VecDeque::with_capacity(4),
+            blocks: Vec::new(),
+            true_peak: 0.0,
+        }
+    }
+
+    /// Windowed sinc low pass split into the polyphase branches of a 4x interpolator.
+    fn interpolation_phases() -> [[f64; PEAK_TAPS]; PEAK_OVERSAMPLING] {
+        let len = PEAK_TAPS * PEAK_OVERSAMPLING;
+        let center = (len - 1) as f64 / 2.0;
+        let mut phases = [[0.0; PEAK_TAPS]; PEAK_OVERSAMPLING];
+        for n in 0..len {
+            let x = (n as f64 - center) / PEAK_OVERSAMPLING as f64;
+            let sinc = match x == 0.0 {
+                true => 1.0,
+                false => (PI * x).sin() / (PI * x),
+            };
+            let window = 0.5 - 0.5 * (2.0 * PI * (n as f64 + 0.5) / len as f64).cos();
+            phases[n % PEAK_OVERSAMPLING][n / PEAK_OVERSAMPLING] = sinc * window;
+        }
+        phases
+    }
+
+    /// Feeds interleaved samples to the meter.
+    pub fn push(&mut self, samples: impl IntoIterator<Item = f32>) {
+        if self.channels.is_empty() {
+            return;
+        }
+        for sample in samples {
+            let x = sample as f64;
+            let phases = &self.peak_phases;
+            let channel = &mut self.channels[self.current_channel];
+
+            channel.history.copy_within(0..PEAK_TAPS - 1, 1);
+            channel.history[0] = x;
+            for phase in phases {
+                let interpolated: f64 = phase
+                    .iter()
+                    .zip(channel.history.iter())
+                    .map(|(tap, sample)| tap * sample)
+                    .sum();
+                self.true_peak = self.true_peak.max(interpolated.abs());
+            }
+            self.true_peak = self.true_peak.max(x.abs());
+
+            let weighted = channel.highpass.process(channel.shelf.process(x));
+            self.frame_energy += channel.weight * weighted * weighted;
+
+            self.current_channel += 1;
+            if self.current_channel == self.channels.len() {
+                self.current_channel = 0;
+                self.end_frame();
+            }
+        }
+    }
+
+    /// Accumulates a complete frame into the current 100ms sub block.
+    fn end_frame(&mut self) {
+        self.sub_block_energy += self.frame_energy;
+        self.frame_energy = 0.0;
+        self.sub_block_frames += 1;
+        if self.sub_block_frames < self.sub_block_len {
+            return;
+        }
+
+        if self.sub_blocks.len() == 4 {
+            self.sub_blocks.pop_front();
+        }
+        self.sub_blocks
+            .push_back(self.sub_block_energy / self.sub_block_len as f64);
+        self.sub_block_energy = 0.0;
+        self.sub_block_frames = 0;
+
+        if self.sub_blocks.len() == 4 {
+            self.blocks.push(self.sub_blocks.iter().sum::<f64>() / 4.0);
+        }
+    }
+}
+
+/// Converts a mean square energy to LUFS.
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Computes the gated integrated loudness of a set of blocks, or None if everything is below the absolute gate.
+pub fn integrated_loudness(blocks: &[f64]) -> Option<f64> {
+    let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+
+    let above_absolute: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|energy| energy_to_lufs(*energy) > ABSOLUTE_GATE)
+        .collect();
+    if above_absolute.is_empty() {
+        return None;
+    }
+
+    let relative_gate = energy_to_lufs(mean(&above_absolute)) + RELATIVE_GATE;
+    let above_relative: Vec<f64> = above_absolute
+        .into_iter()
+        .filter(|energy| energy_to_lufs(*energy) > relative_gate)
+        .collect();
+
+    Some(energy_to_lufs(mean(&above_relative)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleaved samples of a sine on every channel, with its peak at a level in dBFS.
+    fn sine(freq: f64, dbfs: f64, phase: f64, secs: f64, channels: usize, rate: u32) -> Vec<f32> {
+        let amplitude = 10f64.powf(dbfs / 20.0);
+        (0..(secs * rate as f64) as usize)
+            .flat_map(|n| {
+                let x = amplitude * (2.0 * PI * freq * n as f64 / rate as f64 + phase).sin();
+                std::iter::repeat_n(x as f32, channels)
+            })
+            .collect()
+    }
+
+    fn loudness(samples: Vec<f32>, weights: &[f64]) -> f64 {
+        let mut meter = LoudnessMeter::new(weights, 48000);
+        meter.push(samples);
+        integrated_loudness(&meter.blocks).unwrap()
+    }
+
+    #[test]
+    fn reference_sines_read_their_level() {
+        let stereo = channel_weights(None, 2);
+        // EBU Tech 3341 case 1: a stereo 1 kHz sine at -23 dBFS reads -23 LUFS.
+        let measured = loudness(sine(1000.0, -23.0, 0.0, 4.0, 2, 48000), &stereo);
+        assert!((measured + 23.0).abs() < 0.1, "{}", measured);
+        let measured = loudness(sine(1000.0, -20.0, 0.0, 4.0, 2, 48000), &stereo);
+        assert!((measured + 20.0).abs() < 0.1, "{}", measured);
+        // A single channel carries half the energy.
+        let measured = loudness(sine(1000.0, -20.0, 0.0, 4.0, 1, 48000), &[1.0]);
+        assert!((measured + 23.0).abs() < 0.1, "{}", measured);
+    }
+
+    #[test]
+    fn quiet_parts_are_gated_out() {
+        let stereo = channel_weights(None, 2);
+        let mut samples = sine(1000.0, -20.0, 0.0, 12.0, 2, 48000);
+        // Below the absolute gate.
+        samples.extend(sine(1000.0, -80.0, 0.0, 4.0, 2, 48000));
+        // Above it, but more than 10 LU under the rest.
+        samples.extend(sine(1000.0, -40.0, 0.0, 4.0, 2, 48000));
+        let measured = loudness(samples, &stereo);
+        assert!((measured + 20.0).abs() < 0.1, "{}", measured);
+
+        let silence = vec![0.0; 48000 * 2 * 5];
+        let mut meter = LoudnessMeter::new(&stereo, 48000);
+        meter.push(silence);
+        assert_eq!(integrated_loudness(&meter.blocks), None);
+    }
+
+    #[test]
+    fn true_peak_finds_peaks_between_samples() {
+        // A quarter of the sample rate, sampled 45° off its peaks: every sample is at 0.707 of the peak.
+        let samples = sine(12000.0, -6.0, PI / 4.0, 1.0, 1, 48000);
+        let sample_peak = samples
+            .iter()
+            .fold(0f64, |peak, x| peak.max(x.abs() as f64));
+        let mut meter = LoudnessMeter::new(&[1.0], 48000);
+        meter.push(samples);
+        let peak = 10f64.powf(-6.0 / 20.0);
+        assert!((sample_peak / peak - 0.707).abs() < 0.01);
+        assert!(
+            (20.0 * (meter.true_peak / peak).log10()).abs() < 0.5,
+            "{}",
+            meter.true_peak
+        );
+    }
+
+    #[test]
+    fn weights_follow_the_channel_layout() {
+        let quad = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::REAR_LEFT
+            | Channels::REAR_RIGHT;
+        assert_eq!(channel_weights(Some(quad), 4), [1.0, 1.0, 1.41, 1.41]);
+        assert_eq!(channel_weights(None, 4), [1.0, 1.0, 1.41, 1.41]);
+        assert_eq!(channel_weights(None, 6), [1.0, 1.0, 1.0, 0.0, 1.41, 1.41]);
+        // A layout that doesn't match the decoded channels is ignored.
+        assert_eq!(channel_weights(Some(quad), 2), [1.0, 1.0]);
+        assert_eq!(channel_weights(None, 10), [1.0; 10]);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::data::{
+    loudness::meter::{LoudnessMeter, channel_weights, integrated_loudness},
+    metadata::{audio_probe::open_format, replay_gain::ReplayGain, tag_writer::write_replay_gain},
+};
+use rodio::{Decoder, Source};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// Loudness targeted by ReplayGain 2.0, in LUFS.
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// Encapsulates the measurement of a single track.
+pub struct TrackLoudness {
+    pub path: PathBuf,
+    pub blocks: Vec<f64>,
+    pub true_peak: f64,
+}
+
+/// Progress of a scan, reported once per step.
+pub enum ScanEvent {
+    /// A track was decoded and measured.
+    Measured {
+        done: usize,
+        total: usize,
+        path: PathBuf,
+    },
+    /// The gains of a track were computed, and written if requested.
+    Tagged {
+        path: PathBuf,
+        gain: ReplayGain,
+        written: Option<Result<(), String>>,
+    },
+    /// A track could not be decoded.
+    Failed { path: PathBuf, error: String },
+    /// Every track was processed.
+    Finished,
+}
+
+/// Decodes a file with rodio's decoder and measures its loudness.
+pub fn measure_track(path: &Path) -> Result<TrackLoudness, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let decoder = Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+
+    // The decoder only tells how many channels there are; the container knows which they are.
+    let layout = open_format(path).and_then(|format| {
+        format
+            .default_track()
+            .and_then(|track| track.codec_params.channels)
+    });
+    let weights = channel_weights(layout, decoder.channels());
+    let mut meter = LoudnessMeter::new(&weights, decoder.sample_rate());
+    meter.push(decoder.convert_samples::<f32>());
+
+    Ok(TrackLoudness {
+        path: path.to_path_buf(),
+        blocks: meter.blocks,
+        true_peak: meter.true_peak,
+    })
+}
+
+/// Groups paths into albums by their parent directory, keeping the original order.
+pub fn group_by_album(paths: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+    let mut albums: Vec<Vec<PathBuf>> = Vec::new();
+    for path in paths {
+        match albums
+            .iter_mut()
+            .find(|album| album[0].parent() == path.parent())
+        {
+            Some(album) => album.push(path.clone()),
+            None => albums.push(vec![path.clone()]),
+        }
+    }
+    albums
+}
+
+/// Measures every path, computes track and album gains, and optionally writes them as tags.
+pub fn scan(paths: &[PathBuf], write: bool, mut report: impl FnMut(ScanEvent)) {
+    let total = paths.len();
+    let mut done = 0;
+
+    for album in group_by_album(paths) {
+        let mut tracks = Vec::new();
+        for path in &album {
+            match measure_track(path) {
+                Ok(track) => tracks.push(track),
+                Err(error) => report(ScanEvent::Failed {
+                    path: path.clone(),
+                    error,
+                }),
+            }
+            done += 1;
+            report(ScanEvent::Measured {
+                done,
+                total,
+                path: path.clone(),
+            });
+        }
+
+        let album_blocks: Vec<f64> = tracks
+            .iter()
+            .flat_map(|track| track.blocks.iter().copied())
+            .collect();
+        let album_gain = integrated_loudness(&album_blocks).map(|lufs| REFERENCE_LOUDNESS - lufs);
+        let album_peak = tracks
+            .iter()
+            .map(|track| track.true_peak)
+            .fold(0.0, f64::max);
+
+        for track in tracks {
+            let gain = ReplayGain {
+                track_gain: integrated_loudness(&track.blocks)
+                    .map(|lufs| (REFERENCE_LOUDNESS - lufs) as f32),
+                track_peak: Some(track.true_peak as f32),
+                album_gain: album_gain.map(|gain| gain as f32),
+                album_peak: Some(album_peak as f32),
+            };
+            let written =
+                write.then(|| write_replay_gain(&track.path, &gain).map_err(|e| e.to_string()));
+            report(ScanEvent::Tagged {
+                path: track.path,
+                gain,
+                written,
+            });
+        }
+    }
+
+    report(ScanEvent::Finished);
+}
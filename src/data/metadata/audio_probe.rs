@@ -0,0 +1,177 @@
+use std::{fs::File, path::Path};
+use symphonia::core::{
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::{MetadataOptions, StandardVisualKey, Tag, Visual},
+    probe::{Hint, ProbeResult},
+    units::TimeBase,
+};
+
+/// Runs symphonia's probe over a file.
+fn probe(path: &Path) -> Option<ProbeResult> {
+    let file = File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension() {
+        hint.with_extension(&ext.to_string_lossy());
+    }
+
+    symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()
+}
+
+/// Opens a file with symphonia's probe and returns its format reader.
+pub fn open_format(path: &Path) -> Option<Box<dyn FormatReader>> {
+    probe(path).map(|probed| probed.format)
+}
+
+/// Returns the tags found in a file, both outside the container (e.g. ID3v2) and inside it.
+pub fn read_tags(path: &Path) -> Vec<Tag> {
+    let Some(mut probed) = probe(path) else {
+        return Vec::new();
+    };
+
+    let mut tags = Vec::new();
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current()
+    {
+        tags.extend_from_slice(revision.tags());
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        tags.extend_from_slice(revision.tags());
+    }
+    tags
+}
+
+/// Returns the pictures embedded in a file (ID3v2 APIC frames, FLAC and Vorbis comment pictures, MP4 covr atoms),
+/// front covers first.
+pub fn read_visuals(path: &Path) -> Vec<Visual> {
+    let Some(mut probed) = probe(path) else {
+        return Vec::new();
+    };
+
+    let mut visuals = Vec::new();
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current()
+    {
+        visuals.extend_from_slice(revision.visuals());
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        visuals.extend_from_slice(revision.visuals());
+    }
+    visuals.sort_by_key(|visual| visual.usage != Some(StandardVisualKey::FrontCover));
+    visuals
+}
+
+/// Formats whose container may have no frame count, so their packets are walked to find the length.
+const WALKED_EXTS: [&str; 2] = ["mp3", "aac"];
+
+/// Computes the duration of a file in seconds from its container.
+///
+/// Uses the frame count when the container provides one (FLAC STREAMINFO, WAV headers, MP4 atoms, Xing/VBRI headers in MP3s).
+/// Otherwise every packet of the default track of an MP3 or raw AAC file is walked, which is slower but exact;
+/// other formats always carry a count, so a missing one means the length is unknown.
+pub fn probe_duration(path: &Path) -> Option<f64> {
+    let mut format = open_format(path)?;
+    let track = format.default_track()?;
+    let track_id = track.id;
+    let params = &track.codec_params;
+    let time_base = params
+        .time_base
+        .or_else(|| params.sample_rate.map(|rate| TimeBase::new(1, rate)))?;
+
+    let walk = path.extension().is_some_and(|ext| {
+        WALKED_EXTS.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str())
+    });
+    let n_frames = match params.n_frames {
+        Some(n_frames) => n_frames,
+        None if !walk => return None,
+        None => {
+            let mut end = 0;
+            while let Ok(packet) = format.next_packet() {
+                if packet.track_id() == track_id {
+                    end = end.max(packet.ts() + packet.dur());
+                }
+            }
+            end
+        }
+    };
+
+    let time = time_base.calc_time(n_frames);
+    match time.seconds == 0 && time.frac == 0.0 {
+        true => None,
+        false => Some(time.seconds as f64 + time.frac),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::{TempDir, add_wav_title, write_tone};
+    use std::fs;
+    use symphonia::core::meta::StandardTagKey;
+
+    /// Writes silent MPEG-1 layer III frames, mono at 128 kbps and 44.1 kHz, with no Xing header to count them.
+    /// Under 17 frames are too few for symphonia to estimate a length from the bitrate.
+    fn write_silent_mp3(path: &Path, frames: usize) {
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0xc0]);
+        fs::write(path, frame.repeat(frames)).unwrap();
+    }
+
+    #[test]
+    fn durations_come_from_the_frame_count() {
+        let dir = TempDir::new("probe-duration");
+        let path = dir.join("tone.wav");
+        write_tone(&path, 1.5, 0.5);
+        let duration = probe_duration(&path).unwrap();
+        assert!((duration - 1.5).abs() < 0.001, "{}", duration);
+    }
+
+    #[test]
+    fn mp3s_without_a_frame_count_are_walked() {
+        let dir = TempDir::new("probe-walk");
+        let path = dir.join("silence.mp3");
+        write_silent_mp3(&path, 10);
+        let duration = probe_duration(&path).unwrap();
+        // 10 frames of 1152 samples.
+        assert!((duration - 11520.0 / 44100.0).abs() < 0.001, "{}", duration);
+
+        // Other extensions aren't walked, so the length stays unknown.
+        let other = dir.join("silence.mp2");
+        fs::copy(&path, &other).unwrap();
+        assert_eq!(probe_duration(&other), None);
+    }
+
+    #[test]
+    fn unreadable_files_have_no_duration_or_tags() {
+        let dir = TempDir::new("probe-garbage");
+        let path = dir.join("noise.wav");
+        fs::write(&path, b"not audio at all").unwrap();
+        assert_eq!(probe_duration(&path), None);
+        assert!(read_tags(&path).is_empty());
+        assert_eq!(probe_duration(&dir.join("missing.flac")), None);
+    }
+
+    #[test]
+    fn tags_are_read_from_the_container() {
+        let dir = TempDir::new("probe-tags");
+        let path = dir.join("titled.wav");
+        write_tone(&path, 0.1, 0.5);
+        add_wav_title(&path, "Test Tone");
+        let tags = read_tags(&path);
+        let title = tags
+            .iter()
+            .find(|tag| tag.std_key == Some(StandardTagKey::TrackTitle))
+            .unwrap();
+        // RIFF INFO strings come with their NUL terminator.
+        assert_eq!(title.value.to_string().trim_end_matches('\0'), "Test Tone");
+    }
+}
@@ -0,0 +1,351 @@
+use crate::data::metadata::audio_probe::read_tags;
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+/// Encapsulates a chapter marker.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+}
+
+/// Reads the chapter markers of a file, sorted by start.
+///
+/// MP4 files (M4A/M4B) are read from their QuickTime chapter track or Nero `chpl` atom, other files from Vorbis CHAPTERxxx comments.
+pub fn read_chapters(path: &Path) -> Vec<Chapter> {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let mut chapters = match ext.as_deref() {
+        Some("m4a" | "m4b" | "mp4") => mp4_chapters(path).unwrap_or_default(),
+        _ => vorbis_chapters(
+            read_tags(path)
+                .iter()
+                .map(|tag| (tag.key.as_str(), tag.value.to_string())),
+        ),
+    };
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+/// Returns the index of the chapter playing at the given position.
+pub fn chapter_at(chapters: &[Chapter], pos: Duration) -> Option<usize> {
+    chapters.iter().rposition(|chapter| chapter.start <= pos)
+}
+
+/// Collects CHAPTERxxx=HH:MM:SS.mmm and CHAPTERxxxNAME=title comment pairs.
+fn vorbis_chapters<'a>(comments: impl Iterator<Item = (&'a str, String)>) -> Vec<Chapter> {
+    let mut starts = Vec::new();
+    let mut names = Vec::new();
+    for (key, value) in comments {
+        let key = key.to_ascii_uppercase();
+        let Some(rest) = key.strip_prefix("CHAPTER") else {
+            continue;
+        };
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        let Ok(number) = digits.parse::<u32>() else {
+            continue;
+        };
+        match &rest[digits.len()..] {
+            "" => {
+                if let Some(start) = parse_timestamp(&value) {
+                    starts.push((number, start));
+                }
+            }
+            "NAME" => names.push((number, value)),
+            _ => {}
+        }
+    }
+    starts
+        .into_iter()
+        .map(|(number, start)| Chapter {
+            start,
+            title: names
+                .iter()
+                .find(|(name_number, _)| *name_number == number)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| format!("Chapter {}", number)),
+        })
+        .collect()
+}
+
+/// Parses HH:MM:SS.mmm (hours and fraction optional).
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut secs = 0.0;
+    for part in value.trim().split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    (secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// Splits a buffer into its MP4 atoms, as (type, body) pairs.
+fn atoms(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut atoms = Vec::new();
+    while data.len() >= 8 {
+        let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = data[4..8].try_into().unwrap();
+        let (header, size) = match size {
+            0 => (8, data.len()),
+            1 if data.len() >= 16 => (
+                16,
+                u64::from_be_bytes(data[8..16].try_into().unwrap()) as usize,
+            ),
+            _ => (8, size),
+        };
+        if size < header || size > data.len() {
+            break;
+        }
+        atoms.push((kind, &data[header..size]));
+        data = &data[size..];
+    }
+    atoms
+}
+
+/// Returns the body of the first child atom of the given type.
+fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    atoms(data)
+        .into_iter()
+        .find(|(atom, _)| atom == kind)
+        .map(|(_, body)| body)
+}
+
+/// Follows a path of nested atoms.
+fn descend<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, kind| child(data, kind))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+/// Reads the top-level `moov` atom, seeking over the others (the media data can be gigabytes).
+fn read_moov(file: &mut File) -> Option<Vec<u8>> {
+    let len = file.metadata().ok()?.len();
+    let mut pos = 0;
+    while pos + 8 <= len {
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let size = be_u32(&header, 0)? as u64;
+        let (header_len, size) = match size {
+            0 => (8, len - pos),
+            1 => {
+                file.read_exact(&mut header[8..]).ok()?;
+                (16, be_u64(&header, 8)?)
+            }
+            _ => (8, size),
+        };
+        if size < header_len {
+            return None;
+        }
+        if &header[4..8] == b"moov" {
+            let mut moov = vec![0; (size - header_len) as usize];
+            file.read_exact(&mut moov).ok()?;
+            return Some(moov);
+        }
+        pos += size;
+    }
+    None
+}
+
+fn mp4_chapters(path: &Path) -> Option<Vec<Chapter>> {
+    let mut file = File::open(path).ok()?;
+    let moov = read_moov(&mut file)?;
+    match quicktime_chapters(&moov, &mut file) {
+        Some(chapters) if !chapters.is_empty() => Some(chapters),
+        _ => nero_chapters(&moov),
+    }
+}
+
+/// Reads a Nero `moov/udta/chpl` atom: start times in 100 ns units and Pascal-string titles.
+fn nero_chapters(moov: &[u8]) -> Option<Vec<Chapter>> {
+    let chpl = descend(moov, &[b"udta", b"chpl"])?;
+    let version = *chpl.first()?;
+    let mut pos = match version {
+        0 => 4,
+        _ => 8,
+    };
+    let count = *chpl.get(pos)?;
+    pos += 1;
+    let mut chapters = Vec::new();
+    for _ in 0..count {
+        let start = be_u64(chpl, pos)?;
+        let len = *chpl.get(pos + 8)? as usize;
+        let title = chpl.get(pos + 9..pos + 9 + len)?;
+        chapters.push(Chapter {
+            start: Duration::from_nanos(start * 100),
+            title: String::from_utf8_lossy(title).to_string(),
+        });
+        pos += 9 + len;
+    }
+    Some(chapters)
+}
+
+/// Returns a track's ID from its `tkhd` atom.
+fn track_id(trak: &[u8]) -> Option<u32> {
+    let tkhd = child(trak, b"tkhd")?;
+    match tkhd.first()? {
+        1 => be_u32(tkhd, 20),
+        _ => be_u32(tkhd, 12),
+    }
+}
+
+/// Reads the text track referenced by a `tref/chap` atom, whose samples are the chapter titles.
+fn quicktime_chapters(moov: &[u8], file: &mut File) -> Option<Vec<Chapter>> {
+    let traks: Vec<&[u8]> = atoms(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .map(|(_, body)| body)
+        .collect();
+    let chap_id = traks
+        .iter()
+        .find_map(|trak| descend(trak, &[b"tref", b"chap"]))
+        .and_then(|chap| be_u32(chap, 0))?;
+    let trak = traks.iter().find(|trak| track_id(trak) == Some(chap_id))?;
+
+    let mdia = child(trak, b"mdia")?;
+    let mdhd = child(mdia, b"mdhd")?;
+    let timescale = match mdhd.first()? {
+        1 => be_u32(mdhd, 20)?,
+        _ => be_u32(mdhd, 12)?,
+    }
+    .max(1);
+    let stbl = descend(mdia, &[b"minf", b"stbl"])?;
+
+    // Sample start times, from the time-to-sample table.
+    let stts = child(stbl, b"stts")?;
+    let mut starts = Vec::new();
+    let mut time = 0u64;
+    for entry in 0..be_u32(stts, 4)? as usize {
+        let count = be_u32(stts, 8 + entry * 8)?;
+        let delta = be_u32(stts, 12 + entry * 8)? as u64;
+        for _ in 0..count {
+            starts.push(time);
+            time += delta;
+        }
+    }
+
+    // Sample sizes.
+    let stsz = child(stbl, b"stsz")?;
+    let fixed_size = be_u32(stsz, 4)?;
+    let sample_count = be_u32(stsz, 8)? as usize;
+    let sizes: Vec<u32> = (0..sample_count)
+        .map(|n| match fixed_size {
+            0 => be_u32(stsz, 12 + n * 4),
+            size => Some(size),
+        })
+        .collect::<Option<_>>()?;
+
+    // Chunk offsets, and how many samples each chunk holds.
+    let offsets: Vec<u64> = match (child(stbl, b"stco"), child(stbl, b"co64")) {
+        (Some(stco), _) => (0..be_u32(stco, 4)? as usize)
+            .map(|n| be_u32(stco, 8 + n * 4).map(u64::from))
+            .collect::<Option<_>>()?,
+        (None, Some(co64)) => (0..be_u32(co64, 4)? as usize)
+            .map(|n| be_u64(co64, 8 + n * 8))
+            .collect::<Option<_>>()?,
+        (None, None) => return None,
+    };
+    let stsc = child(stbl, b"stsc")?;
+    let stsc_entries: Vec<(u32, u32)> = (0..be_u32(stsc, 4)? as usize)
+        .map(|n| Some((be_u32(stsc, 8 + n * 12)?, be_u32(stsc, 12 + n * 12)?)))
+        .collect::<Option<_>>()?;
+
+    let mut sample_offsets = Vec::with_capacity(sample_count);
+    for (chunk, offset) in offsets.iter().enumerate() {
+        let chunk_number = chunk as u32 + 1;
+        let per_chunk = stsc_entries
+            .iter()
+            .rev()
+            .find(|(first, _)| *first <= chunk_number)
+            .map_or(1, |(_, per_chunk)| *per_chunk);
+        let mut offset = *offset;
+        for _ in 0..per_chunk {
+            let Some(size) = sizes.get(sample_offsets.len()) else {
+                break;
+            };
+            sample_offsets.push(offset);
+            offset += *size as u64;
+        }
+    }
+
+    let mut chapters = Vec::new();
+    for ((start, offset), size) in starts.iter().zip(&sample_offsets).zip(&sizes) {
+        // Each sample is a 16-bit length followed by the title.
+        let mut sample = vec![0; *size as usize];
+        file.seek(SeekFrom::Start(*offset)).ok()?;
+        file.read_exact(&mut sample).ok()?;
+        let len = u16::from_be_bytes(sample.get(..2)?.try_into().ok()?) as usize;
+        let title = sample.get(2..2 + len)?;
+        chapters.push(Chapter {
+            start: Duration::from_secs_f64(*start as f64 / timescale as f64),
+            title: String::from_utf8_lossy(title).to_string(),
+        });
+    }
+    Some(chapters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vorbis_comments_pair_times_and_names() {
+        let comments = [
+            ("CHAPTER002", "00:10:30.500".to_string()),
+            ("CHAPTER001", "00:00:00.000".to_string()),
+            ("CHAPTER001NAME", "Opening".to_string()),
+            ("chapter002name", "The Storm".to_string()),
+            ("CHAPTER003", "1:02:03".to_string()),
+        ];
+        let mut chapters = vorbis_chapters(comments.iter().map(|(k, v)| (*k, v.clone())));
+        chapters.sort_by_key(|chapter| chapter.start);
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    start: Duration::ZERO,
+                    title: "Opening".to_string()
+                },
+                Chapter {
+                    start: Duration::from_millis(630_500),
+                    title: "The Storm".to_string()
+                },
+                Chapter {
+                    start: Duration::from_secs(3723),
+                    title: "Chapter 3".to_string()
+                },
+            ]
+        );
+        assert_eq!(chapter_at(&chapters, Duration::from_secs(700)), Some(1));
+    }
+
+    #[test]
+    fn nero_chapter_atom() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "One"), (90 * 10_000_000, "Two")] {
+            chpl.extend(start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend(title.as_bytes());
+        }
+        let atom = |kind: &[u8; 4], body: &[u8]| {
+            let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+            atom.extend(kind);
+            atom.extend(body);
+            atom
+        };
+        let moov = atom(b"udta", &atom(b"chpl", &chpl));
+        let chapters = nero_chapters(&moov).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].start, Duration::from_secs(90));
+        assert_eq!(chapters[1].title, "Two");
+    }
+}
@@ -1,5 +1,10 @@
+use crate::data::{
+    cue::{CueSheet, CueTrack, resolve},
+    files::is_stream,
+    metadata::{audio_probe::probe_duration, replay_gain::ReplayGain},
+};
 use audiotags::Tag;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Encapsulates file data information.
 #[derive(Clone)]
@@ -12,6 +17,7 @@ pub struct FileMetadata {
     pub duration_display: Option<(f64, f64)>,
     pub duration_as_secs: Option<f64>,
     pub track_number: Option<u16>,
+    pub replay_gain: ReplayGain,
 }
 
 impl FileMetadata {
@@ -25,41 +31,89 @@ impl FileMetadata {
             duration_display: None,
             duration_as_secs: None,
             track_number: None,
+            replay_gain: ReplayGain::default(),
         }
     }
 
     /// Sets FileMetadata with the respective values from the file.
     pub fn get_file_data(&mut self, path: &PathBuf) {
-        let valid_exts = ["flac", "mp3", "m4a", "mp4"];
-
-        match path.extension() {
-            Some(ext) => {
-                let file_ext = ext.to_string_lossy().to_ascii_lowercase();
-                match valid_exts.contains(&file_ext.as_str()) {
-                    true => {
-                        let tags = Tag::default().read_from_path(path).unwrap();
-                        self.raw_file = path.file_name().map(|n| n.to_string_lossy().to_string());
-                        self.album = tags.album_title().map(|n| n.to_string());
-                        self.artist = tags.artist().map(|n| n.to_string());
-                        self.title = tags.title().map(|n| n.to_string());
-                        self.year = tags.year();
-                        self.duration_display = tags.duration().map(FileMetadata::sec_to_min_sec);
-                        self.duration_as_secs = tags.duration();
-                        self.track_number = tags.track_number();
-                    }
-                    false => {
-                        self.raw_file = path.file_name().map(|n| n.to_string_lossy().to_string())
-                    }
-                }
+        let valid_exts = ["flac", "mp3", "m4a", "m4b", "mp4"];
+
+        if is_stream(path) {
+            self.raw_file = Some(path.to_string_lossy().to_string());
+            return;
+        }
+        self.raw_file = path.file_name().map(|n| n.to_string_lossy().to_string());
+
+        if let Some((sheet, track)) = resolve(path) {
+            self.fill_from_cue(&sheet, &track);
+            return;
+        }
+
+        if let Some(ext) = path.extension() {
+            let file_ext = ext.to_string_lossy().to_ascii_lowercase();
+            if valid_exts.contains(&file_ext.as_str())
+                && let Ok(tags) = Tag::default().read_from_path(path)
+            {
+                self.album = tags.album_title().map(|n| n.to_string());
+                self.artist = tags.artist().map(|n| n.to_string());
+                self.title = tags.title().map(|n| n.to_string());
+                self.year = tags.year();
+                self.duration_display = tags.duration().map(FileMetadata::sec_to_min_sec);
+                self.duration_as_secs = tags.duration();
+                self.track_number = tags.track_number();
             }
-            None => {}
         }
     }
 
+    /// Sets the metadata of a CUE sheet track.
+    fn fill_from_cue(&mut self, sheet: &CueSheet, track: &CueTrack) {
+        self.album = sheet.title.clone();
+        self.artist = track.performer.clone();
+        self.title = track.title.clone();
+        self.year = sheet.date;
+        self.track_number = Some(track.number);
+    }
+
+    /// Computes the duration from the decoder when the tags don't provide one.
+    /// Tracks of CUE sheets get the length of their range.
+    /// Streams have no duration.
+    pub fn fill_duration(&mut self, path: &Path) {
+        if is_stream(path) || self.duration_as_secs.is_some_and(|duration| duration > 0.0) {
+            return;
+        }
+        self.duration_as_secs = match resolve(path) {
+            Some((_, track)) => track.duration(probe_duration(&track.file)),
+            None => probe_duration(path),
+        };
+        self.duration_display = self.duration_as_secs.map(FileMetadata::sec_to_min_sec);
+    }
+
+    /// Reads the ReplayGain information used during playback.
+    /// Tracks of CUE sheets use the sheet's REM REPLAYGAIN_* values, falling back to the underlying file's tags.
+    pub fn fill_replay_gain(&mut self, path: &Path) {
+        if is_stream(path) {
+            return;
+        }
+        self.replay_gain = match resolve(path) {
+            Some((_, track)) => {
+                let file = ReplayGain::read(&track.file);
+                let cue = track.replay_gain;
+                ReplayGain {
+                    track_gain: cue.track_gain.or(file.track_gain),
+                    track_peak: cue.track_peak.or(file.track_peak),
+                    album_gain: cue.album_gain.or(file.album_gain),
+                    album_peak: cue.album_peak.or(file.album_peak),
+                }
+            }
+            None => ReplayGain::read(path),
+        };
+    }
+
     /// Display album or nothing.
     pub fn display_album(&self) -> String {
         match self.album.as_ref() {
-            Some(display) => format!("{}", display),
+            Some(display) => display.to_string(),
             None => "".to_string(),
         }
     }
@@ -67,7 +121,7 @@ impl FileMetadata {
     /// Display artists or nothing.
     pub fn display_artist(&self) -> String {
         match self.artist.as_ref() {
-            Some(artist) => format!("{}", artist),
+            Some(artist) => artist.to_string(),
             None => "".to_string(),
         }
     }
@@ -75,9 +129,9 @@ impl FileMetadata {
     /// Display title, or raw file, or nothing if neither is found.
     pub fn display_title(&self) -> String {
         match self.title.as_ref() {
-            Some(title) => format!("{}", title),
+            Some(title) => title.to_string(),
             None => match &self.raw_file {
-                Some(raw_file) => format!("{}", raw_file),
+                Some(raw_file) => raw_file.to_string(),
                 None => "".to_string(),
             },
         }
@@ -100,17 +154,17 @@ impl FileMetadata {
     }
 
     /// Converts seconds to seconds and minutes.
-    fn sec_to_min_sec(duration: f64) -> (f64, f64) {
+    pub fn sec_to_min_sec(duration: f64) -> (f64, f64) {
         let min = (duration / 60.0).floor();
         let sec = (duration % 60.0).floor();
         (min, sec)
     }
 
-    /// Display duration_display or nothing.
+    /// Display duration_display or a placeholder when the duration is unknown.
     pub fn display_duration_display(&self) -> String {
         match self.duration_display {
             Some((min, sec)) => format!("{:.0}:{:02.0}", min, sec),
-            None => "".to_string(),
+            None => "--:--".to_string(),
         }
     }
 }
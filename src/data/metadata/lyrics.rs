@@ -0,0 +1,193 @@
+use crate::data::metadata::audio_probe::read_tags;
+use std::{fs, path::Path, time::Duration};
+use symphonia::core::meta::StandardTagKey;
+
+/// Keys of the LRC tags that describe the file rather than a line.
+const ID_TAGS: [&str; 10] = [
+    "ar", "al", "ti", "au", "by", "length", "offset", "re", "ve", "tool",
+];
+
+/// A line of lyrics, with the time it's sung at when they're synced.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LyricLine {
+    pub time: Option<Duration>,
+    pub text: String,
+}
+
+/// Reads the lyrics of a file from a sidecar .lrc file next to it, or else from its USLT/LYRICS tag.
+/// Lyrics with LRC timestamps come back synced and sorted by time, others as plain lines.
+pub fn read_lyrics(path: &Path) -> Vec<LyricLine> {
+    let sidecar = fs::read(path.with_extension("lrc"))
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    let text = sidecar.or_else(|| {
+        read_tags(path)
+            .into_iter()
+            .find(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+            .map(|tag| tag.value.to_string())
+    });
+    text.map(|text| parse_lrc(&text)).unwrap_or_default()
+}
+
+/// Returns the index of the synced line being sung at the given position.
+pub fn line_at(lines: &[LyricLine], pos: Duration) -> Option<usize> {
+    lines
+        .iter()
+        .rposition(|line| line.time.is_some_and(|time| time <= pos))
+}
+
+/// Parses LRC lyrics: `[mm:ss.xx]` timestamps, several per line for repeated lines, and an `[offset:±ms]` tag.
+/// ID tags such as `[ar:...]` and word timestamps of enhanced LRC are dropped. Text without timestamps is kept as plain lines.
+pub fn parse_lrc(text: &str) -> Vec<LyricLine> {
+    let mut offset = 0i64;
+    let mut synced = Vec::new();
+    let mut plain = Vec::new();
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        let mut id_tag = false;
+        while let Some(tag) = rest.strip_prefix('[')
+            && let Some((tag, after)) = tag.split_once(']')
+        {
+            match parse_time(tag) {
+                Some(time) => times.push(time),
+                None => {
+                    // Anything else in brackets, like [Chorus], is part of the text.
+                    if times.is_empty()
+                        && let Some((key, value)) = tag.split_once(':')
+                        && ID_TAGS.contains(&key.trim().to_ascii_lowercase().as_str())
+                    {
+                        if key.trim().eq_ignore_ascii_case("offset")
+                            && let Ok(value) = value.trim().parse()
+                        {
+                            offset = value;
+                        }
+                        id_tag = true;
+                    }
+                    break;
+                }
+            }
+            rest = after;
+        }
+        let lyric = strip_word_times(rest.trim());
+        match (id_tag, times.is_empty()) {
+            (true, _) => {}
+            (false, true) => plain.push(lyric),
+            (false, false) => synced.extend(times.into_iter().map(|time| (time, lyric.clone()))),
+        }
+    }
+
+    if synced.is_empty() {
+        // Blank lines at either end are dropped, the ones between verses kept.
+        let start = plain
+            .iter()
+            .position(|line| !line.is_empty())
+            .unwrap_or(plain.len());
+        let end = plain
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(start, |end| end + 1);
+        return plain[start..end]
+            .iter()
+            .map(|text| LyricLine {
+                time: None,
+                text: text.clone(),
+            })
+            .collect();
+    }
+    synced.sort_by_key(|(time, _)| *time);
+    synced
+        .into_iter()
+        .map(|(time, text)| LyricLine {
+            // A positive offset makes the lyrics appear sooner.
+            time: Some(Duration::from_millis((time as i64 - offset).max(0) as u64)),
+            text,
+        })
+        .collect()
+}
+
+/// Parses mm:ss, mm:ss.xx or mm:ss:xx into milliseconds.
+fn parse_time(tag: &str) -> Option<u64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds = match seconds.split_once(':') {
+        Some((seconds, hundredths)) => format!("{}.{}", seconds, hundredths),
+        None => seconds.to_string(),
+    };
+    if !seconds.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let seconds: f64 = seconds.parse().ok()?;
+    Some(minutes * 60_000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Removes the `<mm:ss.xx>` word timestamps of enhanced LRC.
+fn strip_word_times(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) if parse_time(&rest[start + 1..start + end]).is_some() => {
+                out.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced(lines: &[LyricLine]) -> Vec<(u64, &str)> {
+        lines
+            .iter()
+            .map(|line| (line.time.unwrap().as_millis() as u64, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_timestamps_repeats_and_offsets() {
+        let lrc = "[ar:Someone]\n\
+                   [ti:Something]\n\
+                   [offset:+500]\n\
+                   [00:12.00]First line\n\
+                   [00:20.50][01:02.25]Chorus <00:21.00>again\n\
+                   [00:30:10]\n\
+                   [1:05.5]Last";
+        let lines = parse_lrc(lrc);
+        assert_eq!(
+            synced(&lines),
+            vec![
+                (11_500, "First line"),
+                (20_000, "Chorus again"),
+                (29_600, ""),
+                (61_750, "Chorus again"),
+                (65_000, "Last"),
+            ]
+        );
+        assert_eq!(line_at(&lines, Duration::from_secs(5)), None);
+        assert_eq!(line_at(&lines, Duration::from_secs(25)), Some(1));
+        assert_eq!(line_at(&lines, Duration::from_secs(90)), Some(4));
+
+        let lines = parse_lrc("[offset:-1000]\n[00:01.00]a");
+        assert_eq!(synced(&lines), vec![(2_000, "a")]);
+    }
+
+    #[test]
+    fn unsynced_lyrics_keep_their_lines() {
+        let lines = parse_lrc("\r\n[Chorus]\r\nstill the chorus\r\n\r\nVerse [two]\r\n\r\n");
+        let texts: Vec<_> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["[Chorus]", "still the chorus", "", "Verse [two]"]
+        );
+        assert!(lines.iter().all(|line| line.time.is_none()));
+    }
+}
@@ -19,6 +19,8 @@ impl MetadataQueue {
     /// Updates the current metadata.
     pub fn update_current(&mut self, mut data: FileMetadata, path: &PathBuf, clear: bool) {
         data.get_file_data(path);
+        data.fill_duration(path);
+        data.fill_replay_gain(path);
         if clear {
             self.queue.clear();
         }
@@ -29,9 +31,19 @@ impl MetadataQueue {
     /// Appends metadata for a queued song.
     pub fn queue_metadata(&mut self, mut data: FileMetadata, path: &PathBuf) {
         data.get_file_data(path);
+        data.fill_duration(path);
+        data.fill_replay_gain(path);
         self.queue.push(data);
     }
 
+    /// Inserts metadata for a song queued at the given position.
+    pub fn insert_metadata(&mut self, mut data: FileMetadata, path: &PathBuf, index: usize) {
+        data.get_file_data(path);
+        data.fill_duration(path);
+        data.fill_replay_gain(path);
+        self.queue.insert(index.min(self.queue.len()), data);
+    }
+
     /// When skipping, remove the current metadata (index 0), set it to the next in the vec, then update current.
     pub fn pop_next(&mut self) -> Option<FileMetadata> {
         if !self.queue.is_empty() {
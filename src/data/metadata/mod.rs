@@ -1,2 +1,8 @@
+pub mod audio_probe;
+pub mod chapters;
 pub mod file_metadata;
+pub mod lyrics;
 pub mod metadata_queue;
+pub mod replay_gain;
+pub mod tag_fields;
+pub mod tag_writer;
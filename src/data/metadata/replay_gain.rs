@@ -0,0 +1,159 @@
+use crate::data::metadata::audio_probe::read_tags;
+use std::path::Path;
+use symphonia::core::meta::{StandardTagKey, Tag};
+
+/// Encapsulates a track's ReplayGain information. Gains are in dB, peaks are linear amplitudes.
+#[derive(Clone, Copy, Default)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Reads the ReplayGain tags of a file.
+    pub fn read(path: &Path) -> Self {
+        ReplayGain::from_tags(&read_tags(path))
+    }
+
+    /// Collects the REPLAYGAIN_* values from symphonia's tags.
+    fn from_tags(tags: &[Tag]) -> Self {
+        let mut gain = ReplayGain::default();
+        for tag in tags {
+            let value = parse_db(&tag.value.to_string());
+            // MP4 freeform atoms aren't mapped to standard keys, so fall back to the raw key.
+            let key = tag.key.to_ascii_lowercase();
+            match tag.std_key {
+                Some(StandardTagKey::ReplayGainTrackGain) => gain.track_gain = value,
+                Some(StandardTagKey::ReplayGainTrackPeak) => gain.track_peak = value,
+                Some(StandardTagKey::ReplayGainAlbumGain) => gain.album_gain = value,
+                Some(StandardTagKey::ReplayGainAlbumPeak) => gain.album_peak = value,
+                _ if key.ends_with("replaygain_track_gain") => gain.track_gain = value,
+                _ if key.ends_with("replaygain_track_peak") => gain.track_peak = value,
+                _ if key.ends_with("replaygain_album_gain") => gain.album_gain = value,
+                _ if key.ends_with("replaygain_album_peak") => gain.album_peak = value,
+                _ => {}
+            }
+        }
+        gain
+    }
+
+    /// Returns the linear gain factor for playback.
+    ///
+    /// Falls back to the other gain when the requested one is missing, and returns 1.0 for untagged files.
+    /// With clipping prevention the factor is capped so that the peak stays at or below full scale.
+    pub fn factor(&self, album: bool, preamp: f32, prevent_clipping: bool) -> f32 {
+        let (gain, peak) = match album {
+            true => (
+                self.album_gain.or(self.track_gain),
+                self.album_peak.or(self.track_peak),
+            ),
+            false => (
+                self.track_gain.or(self.album_gain),
+                self.track_peak.or(self.album_peak),
+            ),
+        };
+
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+        let factor = 10f32.powf((gain + preamp) / 20.0);
+        match (prevent_clipping, peak) {
+            (true, Some(peak)) if peak > 0.0 => factor.min(1.0 / peak),
+            _ => factor,
+        }
+    }
+}
+
+/// Parses values like "-6.54 dB" or "0.988525".
+pub fn parse_db(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic() || c.is_whitespace())
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gain(track: Option<f32>, album: Option<f32>) -> ReplayGain {
+        ReplayGain {
+            track_gain: track,
+            album_gain: album,
+            ..ReplayGain::default()
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn missing_gains_fall_back_to_the_other_one() {
+        let both = gain(Some(-6.0), Some(-20.0 * 2f32.log10()));
+        assert!(close(
+            both.factor(false, 0.0, false),
+            10f32.powf(-6.0 / 20.0)
+        ));
+        assert!(close(both.factor(true, 0.0, false), 0.5));
+
+        assert!(close(
+            gain(Some(-6.0), None).factor(true, 0.0, false),
+            10f32.powf(-0.3)
+        ));
+        assert!(close(
+            gain(None, Some(6.0)).factor(false, 0.0, false),
+            10f32.powf(0.3)
+        ));
+        assert_eq!(ReplayGain::default().factor(false, 6.0, true), 1.0);
+    }
+
+    #[test]
+    fn the_preamp_adds_to_the_tagged_gain() {
+        let track = gain(Some(-6.0), None);
+        assert!(close(track.factor(false, 6.0, false), 1.0));
+        assert!(close(track.factor(false, -14.0, false), 0.1));
+    }
+
+    #[test]
+    fn clipping_prevention_caps_the_gain_at_the_peak() {
+        let loud = ReplayGain {
+            track_gain: Some(6.0),
+            track_peak: Some(0.8),
+            ..ReplayGain::default()
+        };
+        assert!(close(loud.factor(false, 0.0, true), 1.25));
+        assert!(close(loud.factor(false, 0.0, false), 10f32.powf(0.3)));
+
+        // Quieter gains and missing or zero peaks are left alone.
+        let quiet = ReplayGain {
+            track_gain: Some(-6.0),
+            ..loud
+        };
+        assert!(close(quiet.factor(false, 0.0, true), 10f32.powf(-0.3)));
+        let unpeaked = ReplayGain {
+            track_peak: None,
+            ..loud
+        };
+        assert!(close(unpeaked.factor(false, 0.0, true), 10f32.powf(0.3)));
+        let zero = ReplayGain {
+            track_peak: Some(0.0),
+            ..loud
+        };
+        assert!(close(zero.factor(false, 0.0, true), 10f32.powf(0.3)));
+    }
+
+    #[test]
+    fn gain_values_parse_with_or_without_units() {
+        assert_eq!(parse_db("-3.2 dB"), Some(-3.2));
+        assert_eq!(parse_db(" +1.50 dB "), Some(1.5));
+        assert_eq!(parse_db("0.988525"), Some(0.988525));
+        assert_eq!(parse_db("-7dB"), Some(-7.0));
+        assert_eq!(parse_db("abc"), None);
+        assert_eq!(parse_db(""), None);
+        assert_eq!(parse_db("1.2.3 dB"), None);
+    }
+}
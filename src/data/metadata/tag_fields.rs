@@ -0,0 +1,146 @@
+use audiotags::Tag;
+use std::path::Path;
+
+/// Extensions of the files whose tags can be edited.
+const EDITABLE_EXTS: [&str; 5] = ["flac", "mp3", "m4a", "m4b", "mp4"];
+
+/// A tag the tag editor can change.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Year,
+    Track,
+    Disc,
+    Genre,
+}
+
+impl TagField {
+    pub const ALL: [TagField; 8] = [
+        TagField::Title,
+        TagField::Artist,
+        TagField::Album,
+        TagField::AlbumArtist,
+        TagField::Year,
+        TagField::Track,
+        TagField::Disc,
+        TagField::Genre,
+    ];
+
+    /// The label shown in the tag editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            TagField::Title => "title",
+            TagField::Artist => "artist",
+            TagField::Album => "album",
+            TagField::AlbumArtist => "album artist",
+            TagField::Year => "year",
+            TagField::Track => "track",
+            TagField::Disc => "disc",
+            TagField::Genre => "genre",
+        }
+    }
+
+    /// The name of the field in file name patterns, e.g. `{albumartist}`.
+    pub fn key(self) -> &'static str {
+        match self {
+            TagField::AlbumArtist => "albumartist",
+            field => field.name(),
+        }
+    }
+
+    /// Finds a field by its name in file name patterns.
+    pub fn from_key(key: &str) -> Option<TagField> {
+        TagField::ALL
+            .into_iter()
+            .find(|field| field.key().eq_ignore_ascii_case(key))
+    }
+
+    /// The Vorbis comment holding the field.
+    pub fn vorbis_key(self) -> &'static str {
+        match self {
+            TagField::Title => "TITLE",
+            TagField::Artist => "ARTIST",
+            TagField::Album => "ALBUM",
+            TagField::AlbumArtist => "ALBUMARTIST",
+            TagField::Year => "DATE",
+            TagField::Track => "TRACKNUMBER",
+            TagField::Disc => "DISCNUMBER",
+            TagField::Genre => "GENRE",
+        }
+    }
+
+    /// Parses the value of a numeric field, which an empty value removes.
+    /// Track and disc numbers may be written as `3/12`, of which only the number is kept.
+    pub fn number(self, value: &str) -> Result<Option<u32>, String> {
+        let value = value.trim();
+        let value = match self {
+            TagField::Track | TagField::Disc => value.split('/').next().unwrap_or_default(),
+            _ => value,
+        };
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match value.parse() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(format!("{} must be a number", self.name())),
+        }
+    }
+
+    /// Checks a value can be written to the field.
+    pub fn validate(self, value: &str) -> Result<(), String> {
+        match self {
+            TagField::Year | TagField::Track | TagField::Disc => self.number(value).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Checks whether the tags of a file can be edited.
+pub fn is_editable(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        EDITABLE_EXTS.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str())
+    })
+}
+
+/// Reads the editable fields of a file, in the order of `TagField::ALL`, with missing ones empty.
+pub fn read_fields(path: &Path) -> Vec<String> {
+    let Ok(tags) = Tag::default().read_from_path(path) else {
+        return vec![String::new(); TagField::ALL.len()];
+    };
+    TagField::ALL
+        .iter()
+        .map(|field| {
+            let value = match field {
+                TagField::Title => tags.title().map(str::to_string),
+                TagField::Artist => tags.artist().map(str::to_string),
+                TagField::Album => tags.album_title().map(str::to_string),
+                TagField::AlbumArtist => tags.album_artist().map(str::to_string),
+                TagField::Year => tags.year().map(|year| year.to_string()),
+                TagField::Track => tags.track_number().map(|track| track.to_string()),
+                TagField::Disc => tags.disc_number().map(|disc| disc.to_string()),
+                TagField::Genre => tags.genre().map(str::to_string),
+            };
+            value.unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_fields_are_checked() {
+        assert_eq!(TagField::Track.number("3/12"), Ok(Some(3)));
+        assert_eq!(TagField::Disc.number(" 2 "), Ok(Some(2)));
+        assert_eq!(TagField::Year.number(""), Ok(None));
+        assert!(TagField::Year.validate("1999/2000").is_err());
+        assert!(TagField::Track.validate("three").is_err());
+        assert!(TagField::Title.validate("3/12").is_ok());
+        assert!(is_editable(Path::new("a/b.FLAC")));
+        assert!(!is_editable(Path::new("a/b.cue")));
+    }
+}
@@ -0,0 +1,312 @@
+use crate::data::metadata::{replay_gain::ReplayGain, tag_fields::TagField};
+use id3::{
+    TagLike, Timestamp, Version,
+    frame::{ExtendedText, Popularimeter},
+};
+use mp4ameta::{Data, FreeformIdent};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Formats the REPLAYGAIN_* tag values of a track.
+fn replay_gain_fields(gain: &ReplayGain) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+    if let Some(track_gain) = gain.track_gain {
+        fields.push(("REPLAYGAIN_TRACK_GAIN", format!("{:.2} dB", track_gain)));
+    }
+    if let Some(track_peak) = gain.track_peak {
+        fields.push(("REPLAYGAIN_TRACK_PEAK", format!("{:.6}", track_peak)));
+    }
+    if let Some(album_gain) = gain.album_gain {
+        fields.push(("REPLAYGAIN_ALBUM_GAIN", format!("{:.2} dB", album_gain)));
+    }
+    if let Some(album_peak) = gain.album_peak {
+        fields.push(("REPLAYGAIN_ALBUM_PEAK", format!("{:.6}", album_peak)));
+    }
+    fields
+}
+
+/// Writes ReplayGain values as Vorbis comments (FLAC), TXXX frames (MP3) or freeform atoms (MP4).
+pub fn write_replay_gain(path: &Path, gain: &ReplayGain) -> Result<(), Box<dyn Error>> {
+    let fields = replay_gain_fields(gain);
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("flac") => {
+            let mut tag = metaflac::Tag::read_from_path(path)?;
+            let comments = tag.vorbis_comments_mut();
+            for (key, value) in fields {
+                comments.set(key, vec![value]);
+            }
+            tag.save()?;
+        }
+        Some("mp3") => {
+            let mut tag = match id3::Tag::read_from_path(path) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => id3::Tag::new(),
+                Err(e) => return Err(e.into()),
+            };
+            for (key, value) in fields {
+                tag.remove_extended_text(Some(key), None);
+                tag.add_frame(ExtendedText {
+                    description: key.to_string(),
+                    value,
+                });
+            }
+            tag.write_to_path(path, Version::Id3v24)?;
+        }
+        Some("m4a") | Some("m4b") | Some("mp4") => {
+            let mut tag = mp4ameta::Tag::read_from_path(path)?;
+            for (key, value) in fields {
+                let name = key.to_ascii_lowercase();
+                tag.set_data(
+                    FreeformIdent::new("com.apple.iTunes", &name),
+                    Data::Utf8(value),
+                );
+            }
+            tag.write_to_path(path)?;
+        }
+        _ => return Err(format!("can't write tags to {}", path.display()).into()),
+    }
+    Ok(())
+}
+
+/// Writes edited tags as Vorbis comments (FLAC), ID3v2.4 frames (MP3) or iTunes atoms (MP4).
+/// An empty value removes the field.
+pub fn write_tags(path: &Path, edits: &[(TagField, String)]) -> Result<(), Box<dyn Error>> {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("flac") => {
+            let mut tag = metaflac::Tag::read_from_path(path)?;
+            for (field, value) in edits {
+                let value = match field {
+                    TagField::Year | TagField::Track | TagField::Disc => {
+                        field.number(value)?.map(|number| number.to_string())
+                    }
+                    _ => Some(value.clone()).filter(|value| !value.is_empty()),
+                };
+                if *field == TagField::Year {
+                    // YEAR takes precedence over DATE when reading, so it would hide the new value.
+                    tag.remove_vorbis("YEAR");
+                }
+                match value {
+                    Some(value) => tag.set_vorbis(field.vorbis_key(), vec![value]),
+                    None => tag.remove_vorbis(field.vorbis_key()),
+                }
+            }
+            tag.save()?;
+        }
+        Some("mp3") => {
+            let mut tag = match id3::Tag::read_from_path(path) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => id3::Tag::new(),
+                Err(e) => return Err(e.into()),
+            };
+            for (field, value) in edits {
+                match (field, value.is_empty()) {
+                    (TagField::Title, false) => tag.set_title(value),
+                    (TagField::Title, true) => tag.remove_title(),
+                    (TagField::Artist, false) => tag.set_artist(value),
+                    (TagField::Artist, true) => tag.remove_artist(),
+                    (TagField::Album, false) => tag.set_album(value),
+                    (TagField::Album, true) => tag.remove_album(),
+                    (TagField::AlbumArtist, false) => tag.set_album_artist(value),
+                    (TagField::AlbumArtist, true) => tag.remove_album_artist(),
+                    (TagField::Genre, false) => tag.set_genre(value),
+                    (TagField::Genre, true) => tag.remove_genre(),
+                    (TagField::Year, _) => match field.number(value)? {
+                        Some(year) => {
+                            tag.set_year(year as i32);
+                            tag.set_date_recorded(Timestamp {
+                                year: year as i32,
+                                month: None,
+                                day: None,
+                                hour: None,
+                                minute: None,
+                                second: None,
+                            });
+                        }
+                        None => {
+                            tag.remove_year();
+                            tag.remove_date_recorded();
+                        }
+                    },
+                    (TagField::Track, _) => match field.number(value)? {
+                        Some(track) => tag.set_track(track),
+                        None => tag.remove_track(),
+                    },
+                    (TagField::Disc, _) => match field.number(value)? {
+                        Some(disc) => tag.set_disc(disc),
+                        None => tag.remove_disc(),
+                    },
+                }
+            }
+            tag.write_to_path(path, Version::Id3v24)?;
+        }
+        Some("m4a") | Some("m4b") | Some("mp4") => {
+            let mut tag = mp4ameta::Tag::read_from_path(path)?;
+            for (field, value) in edits {
+                match (field, value.is_empty()) {
+                    (TagField::Title, false) => tag.set_title(value),
+                    (TagField::Title, true) => tag.remove_title(),
+                    (TagField::Artist, false) => tag.set_artist(value),
+                    (TagField::Artist, true) => tag.remove_artists(),
+                    (TagField::Album, false) => tag.set_album(value),
+                    (TagField::Album, true) => tag.remove_album(),
+                    (TagField::AlbumArtist, false) => tag.set_album_artist(value),
+                    (TagField::AlbumArtist, true) => tag.remove_album_artists(),
+                    (TagField::Genre, false) => tag.set_genre(value),
+                    (TagField::Genre, true) => tag.remove_genres(),
+                    (TagField::Year, _) => match field.number(value)? {
+                        Some(year) => tag.set_year(year.to_string()),
+                        None => tag.remove_year(),
+                    },
+                    (TagField::Track, _) => match field.number(value)? {
+                        Some(track) => tag.set_track_number(track as u16),
+                        None => tag.remove_track_number(),
+                    },
+                    (TagField::Disc, _) => match field.number(value)? {
+                        Some(disc) => tag.set_disc_number(disc as u16),
+                        None => tag.remove_disc_number(),
+                    },
+                }
+            }
+            tag.write_to_path(path)?;
+        }
+        _ => return Err(format!("can't write tags to {}", path.display()).into()),
+    }
+    Ok(())
+}
+
+/// Writes a rating of 1 to 5 stars as a POPM frame (MP3) or FMPS_RATING between 0 and 1 (FLAC, MP4).
+/// A rating of 0 removes them.
+pub fn write_rating(path: &Path, rating: u8) -> Result<(), Box<dyn Error>> {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let fmps = (rating > 0).then(|| format!("{}", rating.min(5) as f32 / 5.0));
+
+    match ext.as_deref() {
+        Some("flac") => {
+            let mut tag = metaflac::Tag::read_from_path(path)?;
+            match fmps {
+                Some(value) => tag.set_vorbis("FMPS_RATING", vec![value]),
+                None => tag.remove_vorbis("FMPS_RATING"),
+            }
+            tag.save()?;
+        }
+        Some("mp3") => {
+            let mut tag = match id3::Tag::read_from_path(path) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => id3::Tag::new(),
+                Err(e) => return Err(e.into()),
+            };
+            tag.remove("POPM");
+            if rating > 0 {
+                // The steps most players read as 1 to 5 stars.
+                let steps = [1, 64, 128, 196, 255];
+                tag.add_frame(Popularimeter {
+                    user: "rmpr".to_string(),
+                    rating: steps[rating.min(5) as usize - 1],
+                    counter: 0,
+                });
+            }
+            tag.write_to_path(path, Version::Id3v24)?;
+        }
+        Some("m4a") | Some("m4b") | Some("mp4") => {
+            let mut tag = mp4ameta::Tag::read_from_path(path)?;
+            let ident = FreeformIdent::new("com.apple.iTunes", "FMPS_Rating");
+            match fmps {
+                Some(value) => tag.set_data(ident, Data::Utf8(value)),
+                None => tag.remove_data_of(&ident),
+            }
+            tag.write_to_path(path)?;
+        }
+        _ => return Err(format!("can't write tags to {}", path.display()).into()),
+    }
+    Ok(())
+}
+
+/// Mirrors ratings into the tags of files on a thread of its own, one batch at a time so the last rating wins.
+pub struct RatingWriter {
+    jobs: Option<Sender<(Vec<PathBuf>, u8)>>,
+    failed: Receiver<usize>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RatingWriter {
+    pub fn spawn() -> Self {
+        let (jobs, job_rx) = mpsc::channel::<(Vec<PathBuf>, u8)>();
+        let (failed_tx, failed) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            for (paths, rating) in job_rx {
+                let count = paths
+                    .iter()
+                    .filter(|path| write_rating(path, rating).is_err())
+                    .count();
+                let _ = failed_tx.send(count);
+            }
+        });
+        Self {
+            jobs: Some(jobs),
+            failed,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues a rating to be written to the files.
+    pub fn write(&self, paths: Vec<PathBuf>, rating: u8) {
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send((paths, rating));
+        }
+    }
+
+    /// Returns how many tags couldn't be written since the last call.
+    pub fn failed(&self) -> usize {
+        self.failed.try_iter().sum()
+    }
+
+    /// Waits for the queued ratings to be written, so quitting doesn't cut a file short.
+    pub fn finish(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::TempDir;
+    use std::fs;
+
+    #[test]
+    fn ratings_are_written_in_the_background_and_failures_counted() {
+        let dir = TempDir::new("ratings");
+        let (mp3, wav) = (dir.join("a.mp3"), dir.join("b.wav"));
+        fs::write(&mp3, b"").unwrap();
+        fs::write(&wav, b"").unwrap();
+
+        let mut writer = RatingWriter::spawn();
+        writer.write(vec![mp3.clone(), wav], 2);
+        writer.write(vec![mp3.clone()], 4);
+        writer.finish();
+        assert_eq!(writer.failed(), 1);
+
+        // The last rating wins.
+        let tag = id3::Tag::read_from_path(&mp3).unwrap();
+        let popm = tag
+            .frames()
+            .find_map(|frame| frame.content().popularimeter());
+        assert_eq!(popm.map(|popm| popm.rating), Some(196));
+    }
+}
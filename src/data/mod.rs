@@ -1,2 +1,14 @@
+pub mod bookmarks;
 pub mod config;
+pub mod cover;
+pub mod cue;
+pub mod files;
+pub mod http;
+pub mod library;
+pub mod loudness;
 pub mod metadata;
+pub mod organiser;
+pub mod path_tags;
+pub mod playlist;
+pub mod podcasts;
+pub mod waveform;
@@ -0,0 +1,350 @@
+use crate::data::metadata::tag_fields::{TagField, read_fields};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Characters that can't be part of a file name on common file systems.
+const FORBIDDEN: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+/// Longest file name written, in bytes, leaving room for the extension.
+const MAX_NAME: usize = 200;
+
+/// A file to move, and whether it can be.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub status: RenameStatus,
+}
+
+/// What happens to a file when the renames are applied.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RenameStatus {
+    Move,
+    /// The file already has the name of the pattern.
+    Unchanged,
+    /// Another file has or would get the same name, so neither is moved.
+    Collision,
+}
+
+/// A part of a file name pattern.
+#[derive(PartialEq, Debug)]
+pub enum Token {
+    Text(String),
+    /// A tag, zero-padded to a width.
+    Field(TagField, usize),
+}
+
+/// Parses a pattern like `{albumartist}/{year} - {album}/{disc}{track:02} {title}`.
+pub fn parse_pattern(pattern: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            tokens.push(Token::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed {{ in {}", pattern))?;
+        let inner = &rest[start + 1..start + end];
+        let (key, width) = match inner.split_once(':') {
+            Some((key, width)) => (
+                key,
+                width
+                    .parse()
+                    .map_err(|_| format!("bad width in {{{}}}", inner))?,
+            ),
+            None => (inner, 0),
+        };
+        let field =
+            TagField::from_key(key.trim()).ok_or_else(|| format!("unknown tag {{{}}}", key))?;
+        tokens.push(Token::Field(field, width));
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+    Ok(tokens)
+}
+
+/// Fills a pattern with the tags of a file. The album artist falls back to the artist.
+fn expand(tokens: &[Token], fields: &[String]) -> PathBuf {
+    let mut expanded = String::new();
+    for token in tokens {
+        match token {
+            Token::Text(text) => expanded.push_str(text),
+            Token::Field(field, width) => {
+                let value = |field: TagField| {
+                    let index = TagField::ALL.iter().position(|other| *other == field);
+                    index
+                        .and_then(|index| fields.get(index))
+                        .map_or("", String::as_str)
+                };
+                let value = match (field, value(*field)) {
+                    (TagField::AlbumArtist, "") => sanitise(value(TagField::Artist)),
+                    (_, other) => sanitise(other),
+                };
+                let value = match value.is_empty() {
+                    true => value,
+                    false => format!("{:0>width$}", value, width = width),
+                };
+                expanded.push_str(&value);
+            }
+        }
+    }
+    expanded
+        .split('/')
+        .map(|component| {
+            let component = clean_component(component);
+            match component.is_empty() {
+                true => "Unknown".to_string(),
+                false => component,
+            }
+        })
+        .collect()
+}
+
+/// Replaces the characters a tag can't bring into a file name.
+fn sanitise(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match FORBIDDEN.contains(&c) || c.is_control() {
+            true => '_',
+            false => c,
+        })
+        .collect()
+}
+
+/// Trims a file or directory name: no surrounding spaces, leading dots that would hide it, or trailing dots Windows drops.
+fn clean_component(component: &str) -> String {
+    let mut component = component
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if component.len() > MAX_NAME {
+        let mut end = MAX_NAME;
+        while !component.is_char_boundary(end) {
+            end -= 1;
+        }
+        component.truncate(end);
+        component = component.trim_end_matches(['.', ' ']).to_string();
+    }
+    component
+}
+
+/// Works out where each file goes under the root according to the pattern, without moving anything.
+/// Files that would end up with the same name, or on an existing file, are marked as collisions.
+pub fn plan(paths: &[PathBuf], pattern: &str, root: &Path) -> Result<Vec<Rename>, String> {
+    let tokens = parse_pattern(pattern.trim().trim_matches('/'))?;
+    let mut renames: Vec<Rename> = paths
+        .iter()
+        .map(|path| {
+            let mut to = root.join(expand(&tokens, &read_fields(path)));
+            if let Some(ext) = path.extension() {
+                let name = format!(
+                    "{}.{}",
+                    to.file_name().unwrap_or_default().to_string_lossy(),
+                    ext.to_string_lossy()
+                );
+                to.set_file_name(name);
+            }
+            let status = match to == *path {
+                true => RenameStatus::Unchanged,
+                false => RenameStatus::Move,
+            };
+            Rename {
+                from: path.clone(),
+                to,
+                status,
+            }
+        })
+        .collect();
+
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    for rename in &renames {
+        *targets.entry(rename.to.clone()).or_default() += 1;
+    }
+    for rename in &mut renames {
+        if rename.status == RenameStatus::Move && (rename.to.exists() || targets[&rename.to] > 1) {
+            rename.status = RenameStatus::Collision;
+        }
+    }
+    Ok(renames)
+}
+
+/// Moves a file, creating the directories it goes into and copying it if it's going to another file system.
+/// Directories left empty are removed, up to the root.
+pub fn move_file(from: &Path, to: &Path, root: &Path) -> io::Result<()> {
+    if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists", to.display()),
+        ));
+    }
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+
+    let mut dir = from.parent();
+    while let Some(current) = dir
+        && current.starts_with(root)
+        && current != root
+        && fs::remove_dir(current).is_ok()
+    {
+        dir = current.parent();
+    }
+    Ok(())
+}
+
+/// A move done by the organiser, kept to undo it.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// The moves of one run of the organiser.
+#[derive(Deserialize, Serialize)]
+struct Batch {
+    moves: Vec<Move>,
+}
+
+/// Encapsulates the batches of moves done by the organiser, the latest last.
+#[derive(Deserialize, Serialize, Default)]
+pub struct UndoLog {
+    batches: Vec<Batch>,
+}
+
+impl UndoLog {
+    /// Returns the path of the undo log in the user's data directory.
+    fn file_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rmpr/rename-log.toml"))
+    }
+
+    /// Loads the undo log, or an empty one if the file is missing or unreadable.
+    pub fn load() -> Self {
+        UndoLog::file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the undo log to the user's data directory.
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = UndoLog::file_path().ok_or("no data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Records a batch of moves.
+    pub fn push(&mut self, moves: Vec<Move>) {
+        if !moves.is_empty() {
+            self.batches.push(Batch { moves });
+        }
+    }
+
+    /// Takes the latest batch of moves.
+    pub fn pop(&mut self) -> Option<Vec<Move>> {
+        self.batches.pop().map(|batch| batch.moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::TempDir;
+
+    fn fields(values: [&str; 8]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn patterns_expand_padded_and_sanitised() {
+        let tokens =
+            parse_pattern("{albumartist}/{year} - {album}/{disc}{track:02} {title}").unwrap();
+        let path = expand(
+            &tokens,
+            &fields([
+                "What? / Why",
+                "AC/DC",
+                "...Live.",
+                "",
+                "1992",
+                "7",
+                "2",
+                "Rock",
+            ]),
+        );
+        assert_eq!(path, PathBuf::from("AC_DC/1992 - ...Live/207 What_ _ Why"));
+
+        let path = expand(&tokens, &fields(["", "", "", "", "", "", "", ""]));
+        assert_eq!(path, PathBuf::from("Unknown/-/Unknown"));
+
+        assert!(parse_pattern("{composer}").is_err());
+        assert!(parse_pattern("{title").is_err());
+        assert!(parse_pattern("{track:x}").is_err());
+    }
+
+    #[test]
+    fn collisions_and_moves() {
+        let dir = TempDir::new("organiser");
+        let root = dir.path();
+        let source = root.join("in");
+        fs::create_dir_all(&source).unwrap();
+        let paths: Vec<PathBuf> = ["a.mp3", "b.mp3", "c.ogg"]
+            .iter()
+            .map(|name| source.join(name))
+            .collect();
+        for path in &paths {
+            fs::write(path, b"").unwrap();
+        }
+        fs::write(root.join("Unknown.ogg"), b"").unwrap();
+
+        // Untagged files all expand to Unknown; the mp3s collide with each other and the ogg with an existing file.
+        let renames = plan(&paths, "{title}", root).unwrap();
+        assert!(
+            renames
+                .iter()
+                .all(|rename| rename.status == RenameStatus::Collision)
+        );
+        let renames = plan(&paths[..1], "/{artist}/{title}/", root).unwrap();
+        assert_eq!(renames[0].to, root.join("Unknown/Unknown.mp3"));
+        assert_eq!(renames[0].status, RenameStatus::Move);
+
+        move_file(&paths[0], &renames[0].to, root).unwrap();
+        assert!(renames[0].to.exists());
+        assert!(move_file(&paths[1], &renames[0].to, root).is_err());
+        move_file(&paths[1], &root.join("b.mp3"), root).unwrap();
+        move_file(&paths[2], &root.join("c.ogg"), root).unwrap();
+        // The emptied source directory is gone, the root stays.
+        assert!(!source.exists());
+        assert!(root.exists());
+    }
+
+    #[test]
+    fn undo_log_keeps_batches_in_order() {
+        let mut log = UndoLog::default();
+        let step = |name: &str| Move {
+            from: PathBuf::from(format!("/in/{}", name)),
+            to: PathBuf::from(format!("/out/{}", name)),
+        };
+        log.push(vec![step("a"), step("b")]);
+        log.push(Vec::new());
+        log.push(vec![step("c")]);
+
+        let mut log: UndoLog = toml::from_str(&toml::to_string(&log).unwrap()).unwrap();
+        assert_eq!(log.pop(), Some(vec![step("c")]));
+        assert_eq!(log.pop(), Some(vec![step("a"), step("b")]));
+        assert_eq!(log.pop(), None);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::data::{
+    metadata::tag_fields::TagField,
+    organiser::{Token, parse_pattern},
+};
+use std::path::{Path, PathBuf};
+
+/// The tags read from the path of a file, or None if it doesn't match the pattern.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Guess {
+    pub path: PathBuf,
+    pub fields: Option<Vec<(TagField, String)>>,
+}
+
+/// Reads tags from the paths of files with a pattern like `{artist}/{album}/{track} - {title}`.
+/// The pattern is matched against as many trailing directories as it has, without the extension.
+/// Returns the fields of the pattern along with the guesses.
+pub fn guess_tags(paths: &[PathBuf], pattern: &str) -> Result<(Vec<TagField>, Vec<Guess>), String> {
+    let pattern = pattern.trim().trim_matches('/');
+    let tokens = parse_pattern(pattern)?;
+    let mut fields = Vec::new();
+    for token in &tokens {
+        if let Token::Field(field, _) = token
+            && !fields.contains(field)
+        {
+            fields.push(*field);
+        }
+    }
+    if fields.is_empty() {
+        return Err("the pattern has no tags".to_string());
+    }
+
+    let depth = pattern.matches('/').count() + 1;
+    let guesses = paths
+        .iter()
+        .map(|path| Guess {
+            path: path.clone(),
+            fields: tail(path, depth).and_then(|text| match_tokens(&tokens, &text)),
+        })
+        .collect();
+    Ok((fields, guesses))
+}
+
+/// Joins the last components of a path with `/`, dropping the extension.
+fn tail(path: &Path, depth: usize) -> Option<String> {
+    let stem = path.with_extension("");
+    let components: Vec<String> = stem
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let start = components.len().checked_sub(depth)?;
+    Some(components[start..].join("/"))
+}
+
+/// Matches text against the tokens, each tag taking as little as it can, at least a character but never a `/`.
+/// Numeric tags only match numbers, and a tag used twice must match the same text.
+fn match_tokens(tokens: &[Token], text: &str) -> Option<Vec<(TagField, String)>> {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty().then(Vec::new);
+    };
+    match token {
+        Token::Text(literal) => match_tokens(rest, text.strip_prefix(literal.as_str())?),
+        Token::Field(field, _) => {
+            let limit = text.find('/').unwrap_or(text.len());
+            for end in (1..=limit).filter(|end| text.is_char_boundary(*end)) {
+                let value = text[..end].trim();
+                if field.validate(value).is_err() {
+                    continue;
+                }
+                if let Some(mut matched) = match_tokens(rest, &text[end..]) {
+                    match matched.iter().find(|(other, _)| other == field) {
+                        Some((_, other)) if other != value => continue,
+                        Some(_) => {}
+                        None => matched.insert(0, (*field, value.to_string())),
+                    }
+                    return Some(matched);
+                }
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_read_from_trailing_directories() {
+        let paths = [
+            PathBuf::from("/music/Miles Davis/Kind of Blue/02 - Freddie - Freeloader.flac"),
+            PathBuf::from("/music/Kind of Blue/Freddie Freeloader.mp3"),
+            PathBuf::from("Album/xx - Title.mp3"),
+        ];
+        let (fields, guesses) =
+            guess_tags(&paths, "/{artist}/{album}/{track:02} - {title}").unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                TagField::Artist,
+                TagField::Album,
+                TagField::Track,
+                TagField::Title
+            ]
+        );
+        assert_eq!(
+            guesses[0].fields,
+            Some(vec![
+                (TagField::Artist, "Miles Davis".to_string()),
+                (TagField::Album, "Kind of Blue".to_string()),
+                (TagField::Track, "02".to_string()),
+                (TagField::Title, "Freddie - Freeloader".to_string()),
+            ])
+        );
+        // No separator to split the track from the title.
+        assert_eq!(guesses[1].fields, None);
+        // Too shallow for the pattern.
+        assert_eq!(guesses[2].fields, None);
+
+        let (_, guesses) = guess_tags(&paths[2..], "{album}/{title}").unwrap();
+        assert_eq!(
+            guesses[0].fields,
+            Some(vec![
+                (TagField::Album, "Album".to_string()),
+                (TagField::Title, "xx - Title".to_string()),
+            ])
+        );
+        assert!(guess_tags(&paths, "{track} - {track}").is_ok());
+        assert!(guess_tags(&paths, "no tags").is_err());
+    }
+}
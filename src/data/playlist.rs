@@ -0,0 +1,127 @@
+use crate::data::files::is_stream;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Extensions of the playlist files that can be read.
+pub const PLAYLIST_EXTS: [&str; 4] = ["m3u", "m3u8", "pls", "smart"];
+
+/// Returns true if the path has a playlist extension.
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        PLAYLIST_EXTS.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str())
+    })
+}
+
+/// Returns true if the path is a smart playlist, which holds a library query rather than entries.
+pub fn is_smart_playlist(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("smart"))
+}
+
+/// Reads the query of a smart playlist.
+pub fn read_smart_query(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    Some(smart_query(&String::from_utf8_lossy(&bytes)))
+}
+
+/// Reads the entries of an M3U or PLS playlist. Relative paths are resolved against the playlist's directory.
+/// Smart playlists have no entries of their own; see `read_smart_query`.
+pub fn read_playlist(path: &Path) -> Vec<PathBuf> {
+    let Ok(bytes) = fs::read(path) else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("pls") => parse_pls(&text, base_dir),
+        Some("smart") => Vec::new(),
+        _ => parse_m3u(&text, base_dir),
+    }
+}
+
+/// Joins the lines of a smart playlist into its query, skipping '#' comments.
+fn smart_query(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses an M3U playlist: one entry per line, comments and directives start with '#'.
+pub fn parse_m3u(text: &str, base_dir: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches('\u{feff}'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| resolve_entry(line, base_dir))
+        .collect()
+}
+
+/// Parses a PLS playlist, ordering the FileN entries by N.
+pub fn parse_pls(text: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<(u32, PathBuf)> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let index = key.trim().strip_prefix("File")?.parse().ok()?;
+            Some((index, resolve_entry(value.trim(), base_dir)))
+        })
+        .collect();
+    entries.sort_by_key(|(index, _)| *index);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Keeps URLs as they are and makes file paths absolute.
+fn resolve_entry(entry: &str, base_dir: &Path) -> PathBuf {
+    let entry = entry.strip_prefix("file://").unwrap_or(entry);
+    let path = PathBuf::from(entry);
+    match is_stream(&path) || path.is_absolute() {
+        true => path,
+        false => base_dir.join(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_keeps_urls_and_resolves_files() {
+        let text = "#EXTM3U\n#EXTINF:-1,Some Radio\nhttp://radio.example:8000/live\n\nsongs/one.mp3\n/music/two.flac\n";
+        assert_eq!(
+            parse_m3u(text, Path::new("/lists")),
+            vec![
+                PathBuf::from("http://radio.example:8000/live"),
+                PathBuf::from("/lists/songs/one.mp3"),
+                PathBuf::from("/music/two.flac"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_orders_entries_by_number() {
+        let text = "[playlist]\nNumberOfEntries=2\nFile2=http://b.example/stream\nTitle2=B\nFile1=http://a.example/stream\nVersion=2\n";
+        assert_eq!(
+            parse_pls(text, Path::new("/lists")),
+            vec![
+                PathBuf::from("http://a.example/stream"),
+                PathBuf::from("http://b.example/stream"),
+            ]
+        );
+    }
+
+    #[test]
+    fn smart_playlists_join_lines_into_a_query() {
+        let text = "# Late fifties jazz\ngenre:jazz\n  year:1955..1965  \n\nsort:year limit:20\n";
+        assert_eq!(
+            smart_query(text),
+            "genre:jazz year:1955..1965 sort:year limit:20"
+        );
+        assert!(is_playlist(Path::new("/lists/Jazz.SMART")));
+    }
+}
@@ -0,0 +1,161 @@
+use super::xml::{Element, parse};
+
+/// A feed as it was fetched, before being merged with what was saved about it.
+#[derive(Debug, PartialEq)]
+pub struct ParsedFeed {
+    pub title: Option<String>,
+    pub episodes: Vec<ParsedEpisode>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedEpisode {
+    /// The guid or Atom id, falling back to the enclosure URL.
+    pub id: String,
+    pub title: String,
+    /// The publication date as YYYY-MM-DD.
+    pub published: Option<String>,
+    /// The duration in seconds.
+    pub duration: Option<u64>,
+    pub url: String,
+}
+
+/// Parses an RSS 2.0 or Atom feed. Entries without audio are left out.
+pub fn parse_feed(text: &str) -> Option<ParsedFeed> {
+    let root = parse(text)?;
+    match root.name.as_str() {
+        "rss" => Some(parse_rss(root.child("channel")?)),
+        "feed" => Some(parse_atom(&root)),
+        _ => None,
+    }
+}
+
+fn parse_rss(channel: &Element) -> ParsedFeed {
+    let episodes = channel
+        .children_named("item")
+        .filter_map(|item| {
+            let url = item.child("enclosure")?.attribute("url")?.to_string();
+            Some(ParsedEpisode {
+                id: item.child_text("guid").unwrap_or_else(|| url.clone()),
+                title: item
+                    .child_text("title")
+                    .unwrap_or_else(|| "Untitled".to_string()),
+                published: item
+                    .child_text("pubDate")
+                    .and_then(|date| rfc2822_date(&date)),
+                duration: item
+                    .child_text("itunes:duration")
+                    .and_then(|duration| parse_duration(&duration)),
+                url,
+            })
+        })
+        .collect();
+    ParsedFeed {
+        title: channel.child_text("title"),
+        episodes,
+    }
+}
+
+fn parse_atom(feed: &Element) -> ParsedFeed {
+    let episodes = feed
+        .children_named("entry")
+        .filter_map(|entry| {
+            let url = entry
+                .children_named("link")
+                .find(|link| link.attribute("rel") == Some("enclosure"))?
+                .attribute("href")?
+                .to_string();
+            Some(ParsedEpisode {
+                id: entry.child_text("id").unwrap_or_else(|| url.clone()),
+                title: entry
+                    .child_text("title")
+                    .unwrap_or_else(|| "Untitled".to_string()),
+                published: entry
+                    .child_text("published")
+                    .or_else(|| entry.child_text("updated"))
+                    .and_then(|date| date.get(..10).map(str::to_string)),
+                duration: entry
+                    .child_text("itunes:duration")
+                    .and_then(|duration| parse_duration(&duration)),
+                url,
+            })
+        })
+        .collect();
+    ParsedFeed {
+        title: feed.child_text("title"),
+        episodes,
+    }
+}
+
+/// Parses an itunes:duration, given as seconds, MM:SS or HH:MM:SS.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    text.trim().split(':').try_fold(0u64, |total, part| {
+        Some(total * 60 + part.trim().parse::<f64>().ok()? as u64)
+    })
+}
+
+/// Turns an RFC 2822 date such as "Tue, 10 Jun 2003 04:00:00 GMT" into "2003-06-10".
+pub fn rfc2822_date(text: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let text = text.split_once(',').map_or(text, |(_, date)| date);
+    let mut parts = text.split_whitespace();
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?.to_ascii_lowercase();
+    let month = MONTHS.iter().position(|name| month.starts_with(name))? + 1;
+    let year: u32 = parts.next()?.parse().ok()?;
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rss_items_with_enclosures() {
+        let feed = parse_feed(
+            r#"<rss xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd"><channel><title>Show</title>
+            <item><title>Two</title><guid>ep-2</guid><pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate>
+            <itunes:duration>1:02:03</itunes:duration><enclosure url="http://x/2.mp3" type="audio/mpeg"/></item>
+            <item><title>Notes only</title></item>
+            <item><title>One</title><itunes:duration>95</itunes:duration><enclosure url="http://x/1.mp3"/></item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Show"));
+        assert_eq!(
+            feed.episodes,
+            vec![
+                ParsedEpisode {
+                    id: "ep-2".to_string(),
+                    title: "Two".to_string(),
+                    published: Some("2003-06-10".to_string()),
+                    duration: Some(3723),
+                    url: "http://x/2.mp3".to_string(),
+                },
+                ParsedEpisode {
+                    id: "http://x/1.mp3".to_string(),
+                    title: "One".to_string(),
+                    published: None,
+                    duration: Some(95),
+                    url: "http://x/1.mp3".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_atom_entries_with_enclosure_links() {
+        let feed = parse_feed(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Atom Show</title>
+            <entry><id>urn:1</id><title>First</title><updated>2024-05-01T10:00:00Z</updated>
+            <link rel="alternate" href="http://x/page"/><link rel="enclosure" href="http://x/1.ogg"/></entry>
+            </feed>"#,
+        )
+        .unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Atom Show"));
+        assert_eq!(feed.episodes[0].id, "urn:1");
+        assert_eq!(feed.episodes[0].published.as_deref(), Some("2024-05-01"));
+        assert_eq!(feed.episodes[0].url, "http://x/1.ogg");
+    }
+}
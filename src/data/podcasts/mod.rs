@@ -0,0 +1,350 @@
+pub mod feed;
+pub mod xml;
+
+use crate::data::http;
+use feed::{ParsedFeed, parse_feed};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// How many bytes are downloaded between progress reports.
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+/// Progress of the feed fetches and downloads running in the background, keyed by URL.
+pub enum PodcastEvent {
+    Fetched {
+        url: String,
+        result: Result<ParsedFeed, String>,
+    },
+    Progress {
+        url: String,
+        received: u64,
+        total: Option<u64>,
+    },
+    Downloaded {
+        url: String,
+        result: Result<PathBuf, String>,
+    },
+}
+
+/// Encapsulates the subscribed feeds and what has been played and downloaded of them.
+#[derive(Deserialize, Serialize, Default)]
+pub struct Podcasts {
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Feed {
+    pub url: String,
+    pub title: String,
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Episode {
+    pub id: String,
+    pub title: String,
+    pub published: Option<String>,
+    /// The duration in seconds, as announced by the feed.
+    pub duration: Option<u64>,
+    pub url: String,
+    #[serde(default)]
+    pub played: bool,
+    /// Where playback stopped, in milliseconds.
+    #[serde(default)]
+    pub position: u64,
+    /// The downloaded file.
+    pub file: Option<PathBuf>,
+}
+
+impl Episode {
+    /// Returns what to play: the download if it's still there, otherwise the URL to stream.
+    pub fn source(&self) -> PathBuf {
+        match &self.file {
+            Some(file) if file.exists() => file.clone(),
+            _ => PathBuf::from(&self.url),
+        }
+    }
+
+    /// Returns true if the path is this episode's URL or download.
+    pub fn is_played_from(&self, path: &Path) -> bool {
+        self.file.as_deref() == Some(path) || *path.to_string_lossy() == self.url
+    }
+}
+
+impl Feed {
+    /// Updates the episodes from a fetch, keeping the saved state of the ones already known.
+    pub fn merge(&mut self, parsed: ParsedFeed) {
+        if let Some(title) = parsed.title {
+            self.title = title;
+        }
+        let mut known = std::mem::take(&mut self.episodes);
+        self.episodes = parsed
+            .episodes
+            .into_iter()
+            .map(|episode| {
+                let saved = known
+                    .iter()
+                    .position(|saved| saved.id == episode.id)
+                    .map(|index| known.remove(index));
+                Episode {
+                    played: saved.as_ref().is_some_and(|saved| saved.played),
+                    position: saved.as_ref().map_or(0, |saved| saved.position),
+                    file: saved.and_then(|saved| saved.file),
+                    id: episode.id,
+                    title: episode.title,
+                    published: episode.published,
+                    duration: episode.duration,
+                    url: episode.url,
+                }
+            })
+            .collect();
+        // Episodes that left the feed are kept while they're downloaded.
+        self.episodes
+            .extend(known.into_iter().filter(|episode| episode.file.is_some()));
+    }
+
+    /// Returns how many episodes haven't been played.
+    pub fn unplayed(&self) -> usize {
+        self.episodes
+            .iter()
+            .filter(|episode| !episode.played)
+            .count()
+    }
+}
+
+impl Podcasts {
+    /// Returns the path of the podcasts file in the user's data directory.
+    fn file_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rmpr/podcasts.toml"))
+    }
+
+    /// Loads the subscriptions, or none if the file is missing or unreadable.
+    pub fn load() -> Self {
+        Podcasts::file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the subscriptions to the user's data directory.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Podcasts::file_path().ok_or("no data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Adds or updates the feed at the URL with what was fetched from it.
+    pub fn update(&mut self, url: &str, parsed: ParsedFeed) {
+        match self.feeds.iter_mut().find(|feed| feed.url == url) {
+            Some(feed) => feed.merge(parsed),
+            None => {
+                let mut feed = Feed {
+                    url: url.to_string(),
+                    title: url.to_string(),
+                    episodes: Vec::new(),
+                };
+                feed.merge(parsed);
+                self.feeds.push(feed);
+            }
+        }
+    }
+
+    /// Returns the feed and episode played from the path.
+    pub fn episode(&self, path: &Path) -> Option<(&Feed, &Episode)> {
+        self.feeds.iter().find_map(|feed| {
+            feed.episodes
+                .iter()
+                .find(|episode| episode.is_played_from(path))
+                .map(|episode| (feed, episode))
+        })
+    }
+
+    /// Returns the episode played from the path, for updating it.
+    pub fn episode_mut(&mut self, path: &Path) -> Option<&mut Episode> {
+        self.feeds
+            .iter_mut()
+            .flat_map(|feed| feed.episodes.iter_mut())
+            .find(|episode| episode.is_played_from(path))
+    }
+}
+
+/// Fetches and parses the feed at the URL.
+pub fn fetch_feed(url: &str) -> Result<ParsedFeed, Box<dyn Error>> {
+    let text = http::get(url, &[])?.text()?;
+    parse_feed(&text).ok_or_else(|| "not an RSS or Atom feed".into())
+}
+
+/// Downloads an episode into a directory named after its feed, reporting the bytes received and the total if known.
+/// The file only gets its final name once it's complete.
+pub fn download(
+    episode: &Episode,
+    feed_title: &str,
+    dir: &Path,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<PathBuf, Box<dyn Error>> {
+    let response = http::get(&episode.url, &[])?;
+    let total = response.content_length();
+
+    let url_path = episode.url.split(['?', '#']).next().unwrap_or_default();
+    let ext = url_path
+        .rsplit_once('/')
+        .and_then(|(_, name)| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty() && ext.len() <= 4)
+        .unwrap_or_else(|| "mp3".to_string());
+    let dir = dir.join(sanitize(feed_title));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.{}", sanitize(&episode.title), ext));
+    let partial = path.with_extension(format!("{}.part", ext));
+
+    let mut body = response.body;
+    let mut file = File::create(&partial)?;
+    let mut buffer = [0; 64 * 1024];
+    let mut received = 0;
+    let mut reported = 0;
+    loop {
+        let read = body.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])?;
+        received += read as u64;
+        if received - reported >= PROGRESS_STEP {
+            reported = received;
+            progress(received, total);
+        }
+    }
+    if total.is_some_and(|total| received < total) {
+        return Err("download was cut short".into());
+    }
+    file.flush()?;
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Makes a title usable as a file name.
+fn sanitize(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    match name.is_empty() {
+        true => "untitled".to_string(),
+        false => name.chars().take(120).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::http::tests::serve, test_files::TempDir};
+
+    const FEED: &str = r#"<rss><channel><title>Mock Show</title>
+        <item><title>Episode 2: More?</title><guid>2</guid><enclosure url="EPISODE"/></item>
+        <item><title>Episode 1</title><guid>1</guid><enclosure url="http://example.invalid/1.mp3"/></item>
+        </channel></rss>"#;
+
+    fn mock_server() -> String {
+        serve(|head, stream| {
+            let (content_type, body) = match head.starts_with("GET /feed.xml ") {
+                true => ("application/rss+xml", FEED.as_bytes().to_vec()),
+                false => ("audio/mpeg", vec![7; 300_000]),
+            };
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+                content_type,
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        })
+    }
+
+    #[test]
+    fn refreshing_keeps_the_state_of_known_episodes() {
+        let base = mock_server();
+        let url = format!("{}/feed.xml", base);
+        let mut podcasts = Podcasts::default();
+        podcasts.update(&url, fetch_feed(&url).unwrap());
+        assert_eq!(podcasts.feeds[0].title, "Mock Show");
+        assert_eq!(podcasts.feeds[0].episodes.len(), 2);
+
+        let first = PathBuf::from("http://example.invalid/1.mp3");
+        podcasts.episode_mut(&first).unwrap().played = true;
+        podcasts.episode_mut(&first).unwrap().position = 1234;
+        podcasts.update(&url, fetch_feed(&url).unwrap());
+
+        assert_eq!(podcasts.feeds.len(), 1);
+        let (feed, episode) = podcasts.episode(&first).unwrap();
+        assert_eq!(feed.unplayed(), 1);
+        assert!(episode.played);
+        assert_eq!(episode.position, 1234);
+    }
+
+    #[test]
+    fn downloads_into_the_feed_directory() {
+        let base = mock_server();
+        let dir = TempDir::new("podcasts");
+        let parsed =
+            parse_feed(&FEED.replace("EPISODE", &format!("{}/ep2.mp3?x=1", base))).unwrap();
+        let mut podcasts = Podcasts::default();
+        podcasts.update("feed", parsed);
+        let episode = podcasts.feeds[0].episodes[0].clone();
+
+        let mut reports = Vec::new();
+        let path = download(&episode, "Mock Show", dir.path(), |received, total| {
+            reports.push((received, total))
+        })
+        .unwrap();
+        assert_eq!(path, dir.join("Mock Show/Episode 2_ More_.mp3"));
+        assert_eq!(fs::read(&path).unwrap().len(), 300_000);
+        assert!(reports.iter().all(|(_, total)| *total == Some(300_000)));
+        assert!(!reports.is_empty());
+
+        assert!(!dir.join("Mock Show/Episode 2_ More_.mp3.part").exists());
+        podcasts
+            .episode_mut(&PathBuf::from(&episode.url))
+            .unwrap()
+            .file = Some(path.clone());
+        assert_eq!(podcasts.feeds[0].episodes[0].source(), path);
+    }
+
+    #[test]
+    fn feeds_moved_to_https_are_fetched_over_tls() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let secure = format!(
+            "https://127.0.0.1:{}/feed.xml",
+            listener.local_addr().unwrap().port()
+        );
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut record = [0; 2];
+            stream.read_exact(&mut record).unwrap();
+            record
+        });
+        let base = serve(move |_, stream| {
+            let head = format!(
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+                secure
+            );
+            let _ = stream.write_all(head.as_bytes());
+        });
+        assert!(fetch_feed(&format!("{}/feed.xml", base)).is_err());
+        assert_eq!(server.join().unwrap(), [0x16, 0x03]);
+    }
+}
@@ -0,0 +1,260 @@
+/// An element of an XML document. Names keep their namespace prefix, e.g. "itunes:duration".
+#[derive(Debug, Default)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    /// Returns the value of an attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the child elements.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Returns the first child element with the given name.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Returns the child elements with the given name.
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    /// Returns the text inside the element, trimmed.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            match child {
+                Node::Text(part) => text.push_str(part),
+                Node::Element(element) => text.push_str(&element.text()),
+            }
+        }
+        text.trim().to_string()
+    }
+
+    /// Returns the trimmed text of the first child element with the given name, unless it's empty.
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name)
+            .map(Element::text)
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// Parses a document into its root element. Feeds in the wild are often sloppy,
+/// so unknown entities are kept as they are and unclosed elements are closed at the end.
+pub fn parse(text: &str) -> Option<Element> {
+    let mut stack = vec![Element::default()];
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut stack, &decode_entities(rest));
+            break;
+        };
+        if start > 0 {
+            push_text(&mut stack, &decode_entities(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body.find("]]>").unwrap_or(body.len());
+            push_text(&mut stack, &body[..end]);
+            rest = body.get(end + 3..).unwrap_or_default();
+        } else if let Some(body) = rest.strip_prefix("<!--") {
+            rest = body.find("-->").map_or("", |end| &body[end + 3..]);
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(body) = rest.strip_prefix("</") {
+            let end = body.find('>').unwrap_or(body.len());
+            let name = body[..end].trim();
+            rest = body.get(end + 1..).unwrap_or_default();
+            // Closes up to the matching element; a stray end tag is ignored.
+            if let Some(depth) = stack.iter().rposition(|element| element.name == name)
+                && depth > 0
+            {
+                while stack.len() > depth {
+                    close(&mut stack);
+                }
+            }
+        } else {
+            let end = tag_end(rest)?;
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let element = parse_tag(tag);
+            match empty {
+                true => stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(Node::Element(element)),
+                false => stack.push(element),
+            }
+        }
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack
+        .pop()?
+        .children
+        .into_iter()
+        .find_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+}
+
+/// Finds the '>' ending a start tag, skipping quoted attribute values.
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses the name and attributes of a start tag.
+fn parse_tag(tag: &str) -> Element {
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let mut element = Element {
+        name: tag[..name_end].to_string(),
+        ..Element::default()
+    };
+
+    let mut rest = tag[name_end..].trim_start();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let value = &value[1..];
+        let end = value.find(quote).unwrap_or(value.len());
+        element
+            .attributes
+            .push((key, decode_entities(&value[..end])));
+        rest = value.get(end + 1..).unwrap_or_default().trim_start();
+    }
+    element
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(Node::Text(text.to_string()));
+    }
+}
+
+/// Moves the innermost open element into its parent.
+fn close(stack: &mut Vec<Element>) {
+    if let Some(element) = stack.pop()
+        && let Some(parent) = stack.last_mut()
+    {
+        parent.children.push(Node::Element(element));
+    }
+}
+
+/// Replaces the predefined entities and character references.
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|dec| dec.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        match replacement {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_elements_and_text() {
+        let root = parse(
+            "<?xml version=\"1.0\"?>\n<!-- feed --><rss version='2.0'><channel><title>A &amp; B</title><item><enclosure url=\"http://x/1.mp3?a=1&amp;b=2\" /><description><![CDATA[<p>Hi</p>]]></description></item></channel></rss>",
+        )
+        .unwrap();
+        assert_eq!(root.name, "rss");
+        assert_eq!(root.attribute("version"), Some("2.0"));
+        let channel = root.child("channel").unwrap();
+        assert_eq!(channel.child_text("title").as_deref(), Some("A & B"));
+        let item = channel.child("item").unwrap();
+        assert_eq!(
+            item.child("enclosure").unwrap().attribute("url"),
+            Some("http://x/1.mp3?a=1&b=2")
+        );
+        assert_eq!(item.child_text("description").as_deref(), Some("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn tolerates_sloppy_documents() {
+        assert_eq!(
+            decode_entities("&#233;t&#xE9; & co &nbsp;"),
+            "été & co &nbsp;"
+        );
+        let root = parse("<a><b>one<c>two</a>").unwrap();
+        assert_eq!(root.text(), "onetwo");
+        assert!(root.child("b").unwrap().child("c").is_some());
+    }
+}
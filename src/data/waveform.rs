@@ -0,0 +1,128 @@
+use crate::{data::cue::resolve, handlers::dsp::segment::Segment};
+use rodio::{Decoder, Source};
+use std::{
+    error::Error,
+    fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// How many peaks an envelope holds, enough for a seekbar across a wide terminal.
+pub const POINTS: usize = 1024;
+/// Peaks measured per second of audio, before they're gathered into the envelope.
+const PEAKS_PER_SECOND: u32 = 50;
+
+/// Returns the peak envelope of a file, from the cache if it's been computed before.
+pub fn load(path: &Path) -> Result<Vec<f32>, Box<dyn Error>> {
+    let cache = cache_path(path);
+    if let Some(envelope) = cache
+        .as_ref()
+        .and_then(|cache| fs::read(cache).ok())
+        .filter(|bytes| bytes.len() == POINTS)
+    {
+        return Ok(envelope
+            .into_iter()
+            .map(|peak| peak as f32 / 255.0)
+            .collect());
+    }
+
+    let envelope = compute(path)?;
+    if let Some(cache) = cache {
+        if let Some(dir) = cache.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let bytes: Vec<u8> = envelope
+            .iter()
+            .map(|peak| (peak * 255.0).round() as u8)
+            .collect();
+        fs::write(cache, bytes)?;
+    }
+    Ok(envelope)
+}
+
+/// Returns where the envelope of a file is cached, named after its path, size and modification time so edits are noticed.
+fn cache_path(path: &Path) -> Option<PathBuf> {
+    let file = resolve(path).map_or(path.to_path_buf(), |(_, track)| track.file);
+    let metadata = fs::metadata(file).ok()?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok().hash(&mut hasher);
+    dirs::cache_dir().map(|dir| dir.join(format!("rmpr/waveforms/{:016x}", hasher.finish())))
+}
+
+/// Decodes a file and measures its peaks, scaled so the loudest is 1. Tracks of CUE sheets only cover their range.
+fn compute(path: &Path) -> Result<Vec<f32>, Box<dyn Error>> {
+    let (file, start, end) = match resolve(path) {
+        Some((_, track)) => (track.file, track.start, track.end),
+        None => (path.to_path_buf(), Duration::ZERO, None),
+    };
+    let decoder = Decoder::new(BufReader::new(File::open(file)?))?.convert_samples::<f32>();
+    let source = Segment::new(decoder, start, end)?;
+
+    let chunk =
+        (source.sample_rate() / PEAKS_PER_SECOND).max(1) as usize * source.channels() as usize;
+    let mut peaks = Vec::new();
+    let mut peak = 0.0f32;
+    for (index, sample) in source.enumerate() {
+        peak = peak.max(sample.abs());
+        if (index + 1) % chunk == 0 {
+            peaks.push(peak);
+            peak = 0.0;
+        }
+    }
+    if peak > 0.0 {
+        peaks.push(peak);
+    }
+    if peaks.is_empty() {
+        return Err("no audio".into());
+    }
+
+    let loudest = peaks
+        .iter()
+        .fold(0.0f32, |loudest, peak| loudest.max(*peak));
+    let scale = match loudest > 0.0 {
+        true => 1.0 / loudest,
+        false => 0.0,
+    };
+    Ok((0..POINTS)
+        .map(|point| {
+            let first = point * peaks.len() / POINTS;
+            let last = ((point + 1) * peaks.len() / POINTS).max(first + 1);
+            peaks[first..last.min(peaks.len())]
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(*sample))
+                * scale
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::{TempDir, write_wav};
+
+    #[test]
+    fn follows_the_loudness_of_the_track() {
+        let dir = TempDir::new("waveform");
+        let path = dir.join("ramp.wav");
+        // Two seconds getting louder, then one of silence.
+        let samples = (0..24000).map(|n| {
+            let level = match n < 16000 {
+                true => n as f32 / 16000.0 * 0.5,
+                false => 0.0,
+            };
+            if n % 2 == 0 { level } else { -level }
+        });
+        write_wav(&path, 1, 8000, samples);
+
+        let envelope = compute(&path).unwrap();
+        assert_eq!(envelope.len(), POINTS);
+        let at = |fraction: f32| envelope[(fraction * POINTS as f32) as usize];
+        assert!((at(0.33) - 0.5).abs() < 0.05, "{}", at(0.33));
+        assert!(at(0.66) > 0.95);
+        assert_eq!(at(0.9), 0.0);
+    }
+}
@@ -0,0 +1,169 @@
+pub mod null;
+pub mod rodio_output;
+pub mod wav;
+
+use crate::handlers::output::SlotSource;
+use null::NullBackend;
+use rodio::{
+    Source,
+    cpal::{
+        self,
+        traits::{DeviceTrait, HostTrait},
+    },
+};
+use rodio_output::RodioBackend;
+use std::{
+    error::Error,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle, sleep},
+    time::{Duration, Instant},
+};
+use wav::WavBackend;
+
+/// Name of the system's default output device.
+pub const DEFAULT_DEVICE: &str = "default";
+/// Name of the device that discards samples in real time.
+pub const NULL_DEVICE: &str = "null";
+/// Prefix of device names that write the output to a WAV file, e.g. "wav:/tmp/out.wav".
+pub const WAV_PREFIX: &str = "wav:";
+
+/// A destination for the samples of the output slot.
+pub trait Backend {
+    /// Starts pulling samples from the source until it ends or the backend is dropped.
+    fn start(&mut self, source: SlotSource) -> Result<(), Box<dyn Error>>;
+}
+
+/// Lists the selectable devices: default, null, then every output device of every host.
+pub fn list_devices() -> Vec<String> {
+    let mut devices = vec![DEFAULT_DEVICE.to_string(), NULL_DEVICE.to_string()];
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+        let Ok(outputs) = host.output_devices() else {
+            continue;
+        };
+        for device in outputs {
+            if let Ok(name) = device.name()
+                && !devices.contains(&name)
+            {
+                devices.push(name);
+            }
+        }
+    }
+    devices
+}
+
+/// Finds an output device by name across every host.
+fn find_device(name: &str) -> Option<cpal::Device> {
+    cpal::available_hosts()
+        .into_iter()
+        .filter_map(|host_id| cpal::host_from_id(host_id).ok())
+        .filter_map(|host| host.output_devices().ok())
+        .flatten()
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+}
+
+/// Opens the backend for a device name as listed by `list_devices`, or a `wav:` path.
+pub fn open_backend(name: &str) -> Result<Box<dyn Backend>, Box<dyn Error>> {
+    if let Some(path) = name.strip_prefix(WAV_PREFIX) {
+        return Ok(Box::new(WavBackend::create(Path::new(path), 1.0)?));
+    }
+    match name {
+        NULL_DEVICE => Ok(Box::new(NullBackend::new(1.0))),
+        _ => Ok(Box::new(RodioBackend::open(name)?)),
+    }
+}
+
+/// A thread pulling samples at a multiple of real time, stopped and joined when dropped.
+struct PacedWorker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PacedWorker {
+    /// Hands the source's samples to `write` in 10 ms chunks, `speed` times faster than a sound card would.
+    fn spawn<S, F>(mut source: S, speed: f32, mut write: F) -> Self
+    where
+        S: Source<Item = f32> + Send + 'static,
+        F: FnMut(&[f32]) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let tick = Duration::from_millis(10);
+            let start = Instant::now();
+            let mut played = Duration::ZERO;
+            let mut chunk = Vec::new();
+            while !stop_flag.load(Ordering::Relaxed) {
+                let channels = source.channels().max(1) as usize;
+                let frames = (source.sample_rate() as usize / 100).max(1);
+                chunk.clear();
+                // Pushed one by one: `extend` asks for a size hint, which overflows in rodio's channel converter.
+                while chunk.len() < frames * channels
+                    && let Some(sample) = source.next()
+                {
+                    chunk.push(sample);
+                }
+                write(&chunk);
+                if chunk.len() < frames * channels {
+                    return;
+                }
+                played += tick.div_f32(speed);
+                if let Some(ahead) = played.checked_sub(start.elapsed()) {
+                    sleep(ahead);
+                }
+            }
+        });
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for PacedWorker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::TempDir;
+    use rodio::source::Zero;
+    use std::sync::Mutex;
+
+    #[test]
+    fn paced_worker_keeps_pace_and_stops_when_dropped() {
+        let written = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&written);
+        let worker = PacedWorker::spawn(Zero::<f32>::new(1, 1000), 10.0, move |samples| {
+            *counter.lock().unwrap() += samples.len();
+        });
+        sleep(Duration::from_millis(200));
+        drop(worker);
+        // Two seconds of audio in 200ms at ten times real time, give or take the scheduler.
+        let after_drop = *written.lock().unwrap();
+        assert!((1000..=3000).contains(&after_drop), "{}", after_drop);
+        sleep(Duration::from_millis(50));
+        assert_eq!(*written.lock().unwrap(), after_drop);
+    }
+
+    #[test]
+    fn wav_devices_are_files() {
+        let dir = TempDir::new("backend");
+        let path = dir.join("output.wav");
+        assert!(open_backend(&format!("{}{}", WAV_PREFIX, path.display())).is_ok());
+        assert!(path.exists());
+        assert!(open_backend(NULL_DEVICE).is_ok());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::handlers::{
+    backend::{Backend, PacedWorker},
+    output::SlotSource,
+};
+use std::error::Error;
+
+/// Discards samples at real time, or faster for tests.
+pub struct NullBackend {
+    speed: f32,
+    worker: Option<PacedWorker>,
+}
+
+impl NullBackend {
+    /// Creates a backend consuming `speed` seconds of audio per second; 1.0 behaves like a sound card.
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            worker: None,
+        }
+    }
+}
+
+impl Backend for NullBackend {
+    fn start(&mut self, source: SlotSource) -> Result<(), Box<dyn Error>> {
+        self.worker = Some(PacedWorker::spawn(source, self.speed, |_| {}));
+        Ok(())
+    }
+}
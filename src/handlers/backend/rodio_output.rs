@@ -0,0 +1,36 @@
+use crate::handlers::{
+    backend::{Backend, DEFAULT_DEVICE, find_device},
+    output::SlotSource,
+};
+use rodio::{OutputStream, OutputStreamHandle};
+use std::error::Error;
+
+/// Plays samples on a sound card through rodio.
+pub struct RodioBackend {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+impl RodioBackend {
+    /// Opens the named output device, or the system's default one.
+    pub fn open(name: &str) -> Result<Self, Box<dyn Error>> {
+        let (stream, handle) = match name {
+            DEFAULT_DEVICE => OutputStream::try_default()?,
+            _ => {
+                let device = find_device(name).ok_or(format!("no output device named {}", name))?;
+                OutputStream::try_from_device(&device)?
+            }
+        };
+        Ok(Self {
+            _stream: stream,
+            handle,
+        })
+    }
+}
+
+impl Backend for RodioBackend {
+    fn start(&mut self, source: SlotSource) -> Result<(), Box<dyn Error>> {
+        self.handle.play_raw(source)?;
+        Ok(())
+    }
+}
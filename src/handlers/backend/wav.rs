@@ -0,0 +1,53 @@
+use crate::handlers::{
+    backend::{Backend, PacedWorker},
+    output::SlotSource,
+};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::source::UniformSourceIterator;
+use std::{error::Error, fs::File, io::BufWriter, path::Path};
+
+/// Format of the written file. Sources in other formats are converted.
+const CHANNELS: u16 = 2;
+const SAMPLE_RATE: u32 = 44100;
+
+/// Writes samples to a 32-bit float WAV file, paced like a sound card or faster.
+pub struct WavBackend {
+    speed: f32,
+    writer: Option<WavWriter<BufWriter<File>>>,
+    worker: Option<PacedWorker>,
+}
+
+impl WavBackend {
+    /// Creates the file; samples are written once the backend is started.
+    pub fn create(path: &Path, speed: f32) -> Result<Self, Box<dyn Error>> {
+        let spec = WavSpec {
+            channels: CHANNELS,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        Ok(Self {
+            speed,
+            writer: Some(WavWriter::create(path, spec)?),
+            worker: None,
+        })
+    }
+}
+
+impl Backend for WavBackend {
+    fn start(&mut self, source: SlotSource) -> Result<(), Box<dyn Error>> {
+        let mut writer = self
+            .writer
+            .take()
+            .ok_or("the WAV file was already written")?;
+        let source = UniformSourceIterator::<_, f32>::new(source, CHANNELS, SAMPLE_RATE);
+        self.worker = Some(PacedWorker::spawn(source, self.speed, move |samples| {
+            for &sample in samples {
+                let _ = writer.write_sample(sample);
+            }
+            // Keep the header valid so the file can be read while it's being written.
+            let _ = writer.flush();
+        }));
+        Ok(())
+    }
+}
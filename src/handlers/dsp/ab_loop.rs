@@ -0,0 +1,202 @@
+use rodio::{Source, source::SeekError};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Length of the fade at each end of the loop, so the jump doesn't click.
+const DECLICK: Duration = Duration::from_millis(5);
+
+/// A range of a track to repeat.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LoopRange {
+    pub path: PathBuf,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// The loop range shared between the UI and the playing sources.
+pub struct LoopControl {
+    range: Mutex<Option<LoopRange>>,
+}
+
+impl LoopControl {
+    pub fn new() -> Self {
+        Self {
+            range: Mutex::new(None),
+        }
+    }
+
+    /// Sets the range to repeat, or stops repeating.
+    pub fn set(&self, range: Option<LoopRange>) {
+        *self.range.lock().unwrap() = range;
+    }
+
+    /// Returns the range if it belongs to the given track.
+    fn range_of(&self, path: &Path) -> Option<(Duration, Duration)> {
+        self.range
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|range| range.path == path)
+            .map(|range| (range.start, range.end))
+    }
+}
+
+/// Source adapter jumping back to the start of the loop range whenever it reaches its end.
+/// Positions are counted like `PositionTracker`, from the input's start or its last seek.
+pub struct AbLoop<S> {
+    input: S,
+    control: Arc<LoopControl>,
+    path: PathBuf,
+    samples: u64,
+    offset: Duration,
+    /// The range in effect, refreshed at every frame.
+    range: Option<(Duration, Duration)>,
+}
+
+impl<S> AbLoop<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: Arc<LoopControl>, path: PathBuf) -> Self {
+        Self {
+            input,
+            control,
+            path,
+            samples: 0,
+            offset: Duration::ZERO,
+            range: None,
+        }
+    }
+
+    fn rate(&self) -> f64 {
+        self.input.sample_rate().max(1) as f64 * self.input.channels().max(1) as f64
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.offset + Duration::from_secs_f64(self.samples as f64 / self.rate())
+    }
+
+    /// Returns the gain near the ends of the loop, fading out before its end and in after its start.
+    fn declick_gain(&self, pos: Duration) -> f32 {
+        let Some((start, end)) = self.range else {
+            return 1.0;
+        };
+        if pos < start || pos >= end {
+            return 1.0;
+        }
+        let fade = DECLICK
+            .min((end - start) / 4)
+            .as_secs_f32()
+            .max(f32::EPSILON);
+        let from_start = (pos - start).as_secs_f32() / fade;
+        let to_end = (end - pos).as_secs_f32() / fade;
+        from_start.min(to_end).min(1.0)
+    }
+}
+
+impl<S> Iterator for AbLoop<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channels = self.input.channels().max(1) as u64;
+        if self.samples.is_multiple_of(channels) {
+            self.range = self.control.range_of(&self.path);
+            if let Some((start, end)) = self.range
+                && self.elapsed() >= end
+                && self.input.try_seek(start).is_ok()
+            {
+                self.offset = start;
+                self.samples = 0;
+            }
+        }
+        let pos = self.elapsed();
+        let sample = self.input.next()?;
+        self.samples += 1;
+        Some(sample * self.declick_gain(pos))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for AbLoop<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.offset = pos;
+        self.samples = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn jumps_back_at_the_end_of_the_range() {
+        // One sample per millisecond, valued by its position.
+        let samples: Vec<f32> = (0..1000).map(|n| n as f32).collect();
+        let control = Arc::new(LoopControl::new());
+        let path = PathBuf::from("track.wav");
+        control.set(Some(LoopRange {
+            path: path.clone(),
+            start: Duration::from_millis(100),
+            end: Duration::from_millis(200),
+        }));
+
+        let source = AbLoop::new(SamplesBuffer::new(1, 1000, samples), control, path);
+        let played: Vec<f32> = source.take(450).collect();
+        // Plays up to the end of the range, then continues from its start, faded at both ends.
+        assert_eq!(played[150], 150.0);
+        assert!(played[199] < 199.0 * 0.25);
+        assert_eq!(played[200], 0.0);
+        assert_eq!(played[250], 150.0);
+        assert_eq!(played[350], 150.0);
+        assert!(played[200..].iter().all(|sample| *sample < 200.0));
+    }
+
+    #[test]
+    fn ignores_ranges_of_other_tracks() {
+        let samples: Vec<f32> = (0..300).map(|n| n as f32).collect();
+        let control = Arc::new(LoopControl::new());
+        control.set(Some(LoopRange {
+            path: PathBuf::from("other.wav"),
+            start: Duration::ZERO,
+            end: Duration::from_millis(100),
+        }));
+
+        let source = AbLoop::new(
+            SamplesBuffer::new(1, 1000, samples),
+            control,
+            PathBuf::from("track.wav"),
+        );
+        assert_eq!(source.count(), 300);
+    }
+}
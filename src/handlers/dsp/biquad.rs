@@ -0,0 +1,107 @@
+use std::f64::consts::PI;
+
+/// Second order IIR filter in transposed direct form II.
+#[derive(Clone)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Creates a filter from coefficients already normalised by a0.
+    pub fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Creates a filter from unnormalised coefficients.
+    fn normalised(b: [f64; 3], a: [f64; 3]) -> Self {
+        Biquad::new(
+            b[0] / a[0],
+            b[1] / a[0],
+            b[2] / a[0],
+            a[1] / a[0],
+            a[2] / a[0],
+        )
+    }
+
+    /// Peaking filter from the RBJ audio EQ cookbook.
+    pub fn peaking(sample_rate: f64, frequency: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        Biquad::normalised(
+            [1.0 + alpha * a, -2.0 * w0.cos(), 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * w0.cos(), 1.0 - alpha / a],
+        )
+    }
+
+    /// Low shelf with a slope of 1 from the RBJ audio EQ cookbook.
+    pub fn low_shelf(sample_rate: f64, frequency: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (cos, alpha) = (w0.cos(), w0.sin() / 2.0 * 2f64.sqrt());
+        let root = 2.0 * a.sqrt() * alpha;
+        Biquad::normalised(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + root),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - root),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + root,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    /// High shelf with a slope of 1 from the RBJ audio EQ cookbook.
+    pub fn high_shelf(sample_rate: f64, frequency: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (cos, alpha) = (w0.cos(), w0.sin() / 2.0 * 2f64.sqrt());
+        let root = 2.0 * a.sqrt() * alpha;
+        Biquad::normalised(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + root),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - root),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + root,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    /// Takes the coefficients of another filter but keeps its own state, so the output doesn't jump.
+    pub fn retune(&mut self, other: &Biquad) {
+        self.b0 = other.b0;
+        self.b1 = other.b1;
+        self.b2 = other.b2;
+        self.a1 = other.a1;
+        self.a2 = other.a2;
+    }
+
+    /// Filters one sample.
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{data::config::EqPreset, handlers::dsp::biquad::Biquad};
+use rodio::{Source, source::SeekError};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// Centre frequencies of the graphic equalizer bands, in Hz.
+pub const BAND_FREQUENCIES: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// Bandwidth of each peaking filter; about one octave.
+const BAND_Q: f64 = 1.41;
+
+/// Bands at or above this fraction of the sample rate are skipped, as they can't be realised.
+const MAX_BAND_RATIO: f32 = 0.45;
+
+/// Encapsulates the equalizer state applied to the playback chain.
+#[derive(Clone)]
+pub struct EqSettings {
+    pub enabled: bool,
+    pub name: String,
+    pub preset: EqPreset,
+}
+
+/// Encapsulates equalizer settings shared between the UI and the playing sources.
+pub struct EqControl {
+    settings: Mutex<EqSettings>,
+    generation: AtomicU64,
+}
+
+impl EqControl {
+    pub fn new(settings: EqSettings) -> Self {
+        Self {
+            settings: Mutex::new(settings),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns a copy of the current settings.
+    pub fn settings(&self) -> EqSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Modifies the settings; playing sources pick up the change on their next frame.
+    pub fn update(&self, f: impl FnOnce(&mut EqSettings)) {
+        f(&mut self.settings.lock().unwrap());
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+}
+
+/// Source adapter applying the equalizer to any decoded source.
+pub struct EqSource<S> {
+    input: S,
+    control: Arc<EqControl>,
+    generation: u64,
+    channels: u16,
+    sample_rate: u32,
+    /// A slot per band and shelf for each channel, empty while it's flat.
+    filters: Vec<Vec<Option<Biquad>>>,
+    preamp: f64,
+    active: bool,
+    channel: usize,
+}
+
+impl<S> EqSource<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: Arc<EqControl>) -> Self {
+        let mut source = Self {
+            input,
+            control,
+            generation: 0,
+            channels: 0,
+            sample_rate: 0,
+            filters: Vec::new(),
+            preamp: 1.0,
+            active: false,
+            channel: 0,
+        };
+        source.rebuild();
+        source
+    }
+
+    /// Recomputes the filters from the shared settings and the input's format, starting from a clean state.
+    fn rebuild(&mut self) {
+        self.channels = self.input.channels().max(1);
+        self.sample_rate = self.input.sample_rate();
+        self.channel = 0;
+        self.filters = vec![Vec::new(); self.channels as usize];
+        self.retune();
+    }
+
+    /// Picks up changed settings without resetting the filters that stay, which would click.
+    fn retune(&mut self) {
+        self.generation = self.control.generation();
+        let settings = self.control.settings();
+        self.active = settings.enabled;
+        self.preamp = 10f64.powf(settings.preset.preamp as f64 / 20.0);
+
+        let slots = self.design(&settings.preset);
+        for chain in &mut self.filters {
+            chain.resize(slots.len(), None);
+            for (slot, designed) in chain.iter_mut().zip(&slots) {
+                match (slot.as_mut(), designed) {
+                    (Some(filter), Some(designed)) => filter.retune(designed),
+                    // A flat filter passes its input through with a zero state, so a new one starts from there.
+                    _ => *slot = designed.clone(),
+                }
+            }
+        }
+    }
+
+    /// Designs the filter of every band and shelf for the input's sample rate; flat ones are left out.
+    fn design(&self, preset: &EqPreset) -> Vec<Option<Biquad>> {
+        let rate = self.sample_rate as f64;
+        let bands = BAND_FREQUENCIES
+            .iter()
+            .zip(preset.bands.iter())
+            .map(|(frequency, gain)| {
+                (*gain != 0.0 && *frequency < self.sample_rate as f32 * MAX_BAND_RATIO)
+                    .then(|| Biquad::peaking(rate, *frequency as f64, BAND_Q, *gain as f64))
+            });
+        let low_shelf = preset
+            .low_shelf
+            .filter(|shelf| shelf.gain != 0.0)
+            .map(|shelf| Biquad::low_shelf(rate, shelf.frequency as f64, shelf.gain as f64));
+        let high_shelf = preset
+            .high_shelf
+            .filter(|shelf| shelf.gain != 0.0)
+            .map(|shelf| Biquad::high_shelf(rate, shelf.frequency as f64, shelf.gain as f64));
+        bands.chain([low_shelf, high_shelf]).collect()
+    }
+}
+
+impl<S> Iterator for EqSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            if self.input.sample_rate() != self.sample_rate
+                || self.input.channels() != self.channels
+            {
+                self.rebuild();
+            } else if self.control.generation() != self.generation {
+                self.retune();
+            }
+        }
+
+        let sample = self.input.next()?;
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.channels as usize;
+
+        if !self.active {
+            return Some(sample);
+        }
+        let filtered = self.filters[channel]
+            .iter_mut()
+            .flatten()
+            .fold(sample as f64 * self.preamp, |acc, filter| {
+                filter.process(acc)
+            });
+        Some(filtered as f32)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for EqSource<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.channel = 0;
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::config::Shelf;
+    use rodio::source::SineWave;
+
+    /// Runs a sine through the equalizer and returns the output/input amplitude ratio in dB.
+    fn response_db(frequency: f32, preset: EqPreset) -> f64 {
+        let control = Arc::new(EqControl::new(EqSettings {
+            enabled: true,
+            name: "test".to_string(),
+            preset,
+        }));
+        let sample_rate = SineWave::new(frequency).sample_rate() as usize;
+        let input: Vec<f32> = SineWave::new(frequency).take(sample_rate).collect();
+        let output: Vec<f32> = EqSource::new(SineWave::new(frequency), control)
+            .take(sample_rate)
+            .collect();
+
+        // Skip the first half second so the filters have settled.
+        let rms = |samples: &[f32]| {
+            let tail = &samples[sample_rate / 2..];
+            (tail.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / tail.len() as f64).sqrt()
+        };
+        20.0 * (rms(&output) / rms(&input)).log10()
+    }
+
+    fn preset_with_band(band: usize, gain: f32) -> EqPreset {
+        let mut preset = EqPreset::default();
+        preset.bands[band] = gain;
+        preset
+    }
+
+    #[test]
+    fn flat_preset_is_transparent() {
+        for frequency in [50.0, 1000.0, 10000.0] {
+            assert!(response_db(frequency, EqPreset::default()).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn band_boosts_its_centre_frequency() {
+        let response = response_db(1000.0, preset_with_band(5, 6.0));
+        assert!((response - 6.0).abs() < 0.1, "got {response} dB");
+    }
+
+    #[test]
+    fn band_cuts_its_centre_frequency() {
+        let response = response_db(125.0, preset_with_band(2, -9.0));
+        assert!((response + 9.0).abs() < 0.1, "got {response} dB");
+    }
+
+    #[test]
+    fn band_leaves_distant_frequencies_alone() {
+        let response = response_db(8000.0, preset_with_band(1, 12.0));
+        assert!(response.abs() < 0.1, "got {response} dB");
+    }
+
+    #[test]
+    fn preamp_scales_everything() {
+        let preset = EqPreset {
+            preamp: -6.0,
+            ..EqPreset::default()
+        };
+        let response = response_db(440.0, preset);
+        assert!((response + 6.0).abs() < 0.01, "got {response} dB");
+    }
+
+    #[test]
+    fn changing_a_band_while_playing_doesnt_click() {
+        let control = Arc::new(EqControl::new(EqSettings {
+            enabled: true,
+            name: "test".to_string(),
+            preset: preset_with_band(5, 6.0),
+        }));
+        let sample_rate = SineWave::new(1000.0).sample_rate() as usize;
+        let mut source = EqSource::new(SineWave::new(1000.0), control.clone());
+        // The lowest peak of each 1 kHz cycle over 20ms.
+        let lowest_peak = |source: &mut EqSource<SineWave>| {
+            let samples: Vec<f32> = source.take(sample_rate / 50).collect();
+            samples
+                .chunks(sample_rate / 1000)
+                .map(|cycle| cycle.iter().fold(0.0, |peak: f32, s| peak.max(s.abs())))
+                .fold(f32::MAX, f32::min)
+        };
+
+        source.by_ref().take(sample_rate / 2).for_each(drop);
+        let steady = lowest_peak(&mut source);
+        control.update(|settings| settings.preset.bands[5] = 6.5);
+        let changed = lowest_peak(&mut source);
+        // Filters restarted from silence would lose their boost for a few cycles.
+        assert!(changed > steady * 0.99, "{changed} against {steady}");
+    }
+
+    #[test]
+    fn shelves_boost_their_side_of_the_spectrum() {
+        let preset = EqPreset {
+            low_shelf: Some(Shelf {
+                frequency: 100.0,
+                gain: 6.0,
+            }),
+            high_shelf: Some(Shelf {
+                frequency: 8000.0,
+                gain: -6.0,
+            }),
+            ..EqPreset::default()
+        };
+        let low = response_db(30.0, preset.clone());
+        let high = response_db(18000.0, preset.clone());
+        let middle = response_db(1000.0, preset);
+        assert!((low - 6.0).abs() < 0.5, "got {low} dB");
+        assert!((high + 6.0).abs() < 0.5, "got {high} dB");
+        assert!(middle.abs() < 0.5, "got {middle} dB");
+    }
+}
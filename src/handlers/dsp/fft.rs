@@ -0,0 +1,78 @@
+use std::f32::consts::PI;
+
+/// Returns the magnitude spectrum of a block of samples seen through a Hann window, one value per bin up to half the sample rate.
+/// Magnitudes are scaled so a full-scale sine peaks near 1. The block length must be a power of two.
+pub fn spectrum(samples: &[f32]) -> Vec<f32> {
+    let len = samples.len();
+    assert!(len.is_power_of_two(), "FFT length must be a power of two");
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(n, sample)| sample * (0.5 - 0.5 * (2.0 * PI * n as f32 / len as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; len];
+    fft(&mut re, &mut im);
+
+    // The Hann window halves the amplitude, and a real sine splits between two bins.
+    let scale = 4.0 / len as f32;
+    re.iter()
+        .zip(&im)
+        .take(len / 2)
+        .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let len = re.len();
+    if len < 2 {
+        return;
+    }
+    let bits = len.trailing_zeros();
+    for index in 0..len {
+        let reversed = index.reverse_bits() >> (usize::BITS - bits);
+        if reversed > index {
+            re.swap(index, reversed);
+            im.swap(index, reversed);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let angle = -2.0 * PI / size as f32;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (even, odd) = (start + k, start + k + size / 2);
+                let odd_re = re[odd] * cos - im[odd] * sin;
+                let odd_im = re[odd] * sin + im[odd] * cos;
+                re[odd] = re[even] - odd_re;
+                im[odd] = im[even] - odd_im;
+                re[even] += odd_re;
+                im[even] += odd_im;
+            }
+        }
+        size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_frequency_of_a_sine() {
+        // 1 kHz at 32 kHz lands on bin 32 of a 1024-point FFT.
+        let samples: Vec<f32> = (0..1024)
+            .map(|n| (2.0 * PI * 1000.0 * n as f32 / 32000.0).sin())
+            .collect();
+        let bins = spectrum(&samples);
+        assert_eq!(bins.len(), 512);
+        let peak = (0..bins.len())
+            .max_by(|a, b| bins[*a].total_cmp(&bins[*b]))
+            .unwrap();
+        assert_eq!(peak, 32);
+        assert!((bins[32] - 1.0).abs() < 0.01, "peak {}", bins[32]);
+        assert!(bins[100] < 1e-3);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod ab_loop;
+pub mod biquad;
+pub mod equalizer;
+pub mod fft;
+pub mod position;
+pub mod ramp;
+pub mod segment;
+pub mod tap;
+pub mod time_stretch;
+pub mod volume;
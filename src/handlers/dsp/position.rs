@@ -0,0 +1,196 @@
+use rodio::{Source, source::SeekError};
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// The source that is currently being played: its path and media position, shared with the UI.
+pub struct NowPlaying {
+    pub millis: AtomicU64,
+    pub path: Mutex<Option<PathBuf>>,
+    /// The track after which playback holds, instead of moving on to the next one.
+    pub stop_after: Mutex<Option<PathBuf>>,
+    /// Set while the next track is held at its start, playing silence.
+    pub held: AtomicBool,
+    /// Bumped on every reset, so the sources of a sink that is fading away stop publishing.
+    generation: AtomicU64,
+}
+
+impl NowPlaying {
+    pub fn new() -> Self {
+        Self {
+            millis: AtomicU64::new(0),
+            path: Mutex::new(None),
+            stop_after: Mutex::new(None),
+            held: AtomicBool::new(false),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Forgets the playing source, until the next one starts.
+    pub fn reset(&self) {
+        self.millis.store(0, Ordering::Relaxed);
+        *self.path.lock().unwrap() = None;
+        self.held.store(false, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the start of a track, holding it if the previous one was the track to stop after.
+    fn start(&self, path: &PathBuf) {
+        let mut playing = self.path.lock().unwrap();
+        let mut stop_after = self.stop_after.lock().unwrap();
+        if playing.as_ref() != Some(path) && playing.is_some() && *playing == *stop_after {
+            *stop_after = None;
+            self.held.store(true, Ordering::Relaxed);
+        }
+        *playing = Some(path.clone());
+    }
+}
+
+/// Source adapter publishing the path and media position of the source that is currently being played.
+///
+/// Unlike `Sink::get_pos`, the position is counted in the decoded stream, so it isn't skewed by speed changes further down the chain.
+pub struct PositionTracker<S> {
+    input: S,
+    now_playing: Arc<NowPlaying>,
+    path: PathBuf,
+    generation: u64,
+    started: bool,
+    samples: u64,
+    offset: Duration,
+}
+
+impl<S> PositionTracker<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, now_playing: Arc<NowPlaying>, path: PathBuf) -> Self {
+        Self {
+            input,
+            generation: now_playing.generation.load(Ordering::Relaxed),
+            now_playing,
+            path,
+            started: false,
+            samples: 0,
+            offset: Duration::ZERO,
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        let rate = self.input.sample_rate().max(1) as f64 * self.input.channels().max(1) as f64;
+        self.offset + Duration::from_secs_f64(self.samples as f64 / rate)
+    }
+}
+
+impl<S> Iterator for PositionTracker<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // Stays at the start of the track while held.
+        if self.started && self.now_playing.held.load(Ordering::Relaxed) {
+            return Some(0.0);
+        }
+        let sample = self.input.next()?;
+        // Publish on the first sample so a freshly started track doesn't show the previous position.
+        if (!self.started || self.samples.is_multiple_of(512))
+            && self.now_playing.generation.load(Ordering::Relaxed) == self.generation
+        {
+            if !self.started {
+                self.now_playing.start(&self.path);
+            }
+            self.started = true;
+            self.now_playing
+                .millis
+                .store(self.elapsed().as_millis() as u64, Ordering::Relaxed);
+        }
+        self.samples += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for PositionTracker<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.offset = pos;
+        self.samples = 0;
+        self.started = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::dsp::time_stretch::{StretchControl, TimeStretch};
+    use rodio::buffer::SamplesBuffer;
+
+    /// Plays a second of stereo output through the tracker and a time stretcher, and returns the published position.
+    fn position_after_a_second(factor: f32) -> u64 {
+        let now_playing = Arc::new(NowPlaying::new());
+        let input = SamplesBuffer::new(2, 48000, vec![0.1; 48000 * 2 * 4]);
+        let tracker = PositionTracker::new(input, now_playing.clone(), PathBuf::from("a.wav"));
+        let mut output = TimeStretch::new(tracker, Arc::new(StretchControl::new(factor)));
+        output.by_ref().take(48000 * 2).for_each(drop);
+        now_playing.millis.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn position_follows_the_media_at_any_speed() {
+        // The stretcher reads a window ahead of what it outputs.
+        for (factor, expected) in [(1.0, 1000), (2.0, 2000), (0.5, 500)] {
+            let millis = position_after_a_second(factor);
+            assert!(
+                millis.abs_diff(expected) < 80,
+                "{} ms at {}x",
+                millis,
+                factor
+            );
+        }
+    }
+
+    #[test]
+    fn seeking_moves_the_position_and_a_held_track_stays_put() {
+        let now_playing = Arc::new(NowPlaying::new());
+        let input = SamplesBuffer::new(1, 1000, vec![0.1; 5000]);
+        let mut tracker = PositionTracker::new(input, now_playing.clone(), PathBuf::from("a"));
+        tracker.by_ref().take(1000).for_each(drop);
+        tracker.try_seek(Duration::from_secs(3)).unwrap();
+        tracker.next();
+        assert_eq!(now_playing.millis.load(Ordering::Relaxed), 3000);
+        assert_eq!(*now_playing.path.lock().unwrap(), Some(PathBuf::from("a")));
+
+        now_playing.held.store(true, Ordering::Relaxed);
+        assert!(tracker.by_ref().take(600).all(|sample| sample == 0.0));
+        assert_eq!(now_playing.millis.load(Ordering::Relaxed), 3000);
+    }
+}
@@ -0,0 +1,315 @@
+use rodio::{Source, source::SeekError};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// The gain the sources of a sink ramp towards, and what they do once they've faded out, shared with the sink handler.
+/// Pausing, stopping and seeking all happen on the audio side, so the caller never waits for a ramp.
+pub struct RampControl {
+    target: AtomicU32,
+    /// The gain reached by the source being played.
+    gain: AtomicU32,
+    /// Time to ramp between silence and full volume, in microseconds.
+    length: AtomicU64,
+    /// The source being played outputs silence once faded out, without moving on.
+    paused: AtomicBool,
+    /// The sources end once faded out.
+    stopped: AtomicBool,
+    /// Set while a seek waits for the fade out.
+    seeking: AtomicBool,
+    seek: Mutex<Option<Duration>>,
+    seek_error: Mutex<Option<SeekError>>,
+}
+
+impl RampControl {
+    /// Starts silent, so the first source fades in.
+    pub fn new(length: Duration) -> Self {
+        Self {
+            target: AtomicU32::new(1f32.to_bits()),
+            gain: AtomicU32::new(0f32.to_bits()),
+            length: AtomicU64::new(length.as_micros() as u64),
+            paused: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            seeking: AtomicBool::new(false),
+            seek: Mutex::new(None),
+            seek_error: Mutex::new(None),
+        }
+    }
+
+    pub fn set_length(&self, length: Duration) {
+        self.length
+            .store(length.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn length(&self) -> Duration {
+        Duration::from_micros(self.length.load(Ordering::Relaxed))
+    }
+
+    fn target(&self) -> f32 {
+        f32::from_bits(self.target.load(Ordering::Relaxed))
+    }
+
+    fn gain(&self) -> f32 {
+        f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    /// Ramps to silence while paused, stopped or about to seek, and to full volume otherwise.
+    fn update_target(&self) {
+        let silent = self.paused.load(Ordering::Relaxed)
+            || self.stopped.load(Ordering::Relaxed)
+            || self.seeking.load(Ordering::Relaxed);
+        let target: f32 = match silent {
+            true => 0.0,
+            false => 1.0,
+        };
+        self.target.store(target.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Fades out, then holds the source being played where it is.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+        self.update_target();
+    }
+
+    /// Fades back in after a pause.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+        self.update_target();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Fades out, then ends the source being played and every source after it.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.update_target();
+    }
+
+    /// Fades out, seeks the source being played, then fades back in unless paused.
+    pub fn seek(&self, pos: Duration) {
+        *self.seek.lock().unwrap() = Some(pos);
+        self.seeking.store(true, Ordering::Relaxed);
+        self.update_target();
+    }
+
+    /// Returns why the last seek failed, forgetting it.
+    pub fn take_seek_error(&self) -> Option<SeekError> {
+        self.seek_error.lock().unwrap().take()
+    }
+}
+
+/// Source adapter moving the gain linearly towards the ramp's target, a frame at a time, and acting on a pause, stop or seek once silent.
+/// A source starts at the gain the previous one was left at, so a track started after a fade out fades in.
+pub struct Ramp<S> {
+    input: S,
+    control: Arc<RampControl>,
+    gain: Option<f32>,
+    samples: u64,
+    held: bool,
+}
+
+impl<S> Ramp<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: Arc<RampControl>) -> Self {
+        Self {
+            input,
+            control,
+            gain: None,
+            samples: 0,
+            held: false,
+        }
+    }
+
+    /// Acts on what was asked of the ramp once the source has faded out. Returns false if the source should end.
+    fn faded_out(&mut self) -> bool {
+        let control = &self.control;
+        if control.stopped.load(Ordering::Relaxed) {
+            return false;
+        }
+        if control.seeking.load(Ordering::Relaxed) {
+            if let Some(pos) = control.seek.lock().unwrap().take()
+                && let Err(err) = self.input.try_seek(pos)
+            {
+                *control.seek_error.lock().unwrap() = Some(err);
+            }
+            control.seeking.store(false, Ordering::Relaxed);
+            control.update_target();
+        }
+        self.held = control.paused.load(Ordering::Relaxed);
+        true
+    }
+
+    /// Moves the gain one frame towards the target.
+    fn step(&self, gain: f32) -> f32 {
+        let target = self.control.target();
+        if gain == target {
+            return gain;
+        }
+        let frames = self.control.length().as_secs_f32() * self.input.sample_rate() as f32;
+        let gain = match frames < 1.0 {
+            true => target,
+            false => match target > gain {
+                true => (gain + 1.0 / frames).min(target),
+                false => (gain - 1.0 / frames).max(target),
+            },
+        };
+        self.control.gain.store(gain.to_bits(), Ordering::Relaxed);
+        gain
+    }
+}
+
+impl<S> Iterator for Ramp<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channels = self.input.channels().max(1) as u64;
+        if self.samples.is_multiple_of(channels) {
+            self.held = false;
+            let gain = self.gain.unwrap_or_else(|| self.control.gain());
+            if gain == 0.0 && self.control.target() == 0.0 && !self.faded_out() {
+                return None;
+            }
+        }
+        if self.held {
+            self.samples += 1;
+            return Some(0.0);
+        }
+        let sample = self.input.next()?;
+        let gain = match self.gain {
+            Some(gain) if !self.samples.is_multiple_of(channels) => gain,
+            gain => self.step(gain.unwrap_or_else(|| self.control.gain())),
+        };
+        self.gain = Some(gain);
+        self.samples += 1;
+        Some(sample * gain)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Ramp<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    /// A stereo source at 1 kHz whose samples count up, with ramps of 100 frames.
+    fn counting(control: &Arc<RampControl>) -> Ramp<SamplesBuffer<f32>> {
+        let samples = (0..2000).map(|i| (i / 2) as f32).collect::<Vec<_>>();
+        Ramp::new(SamplesBuffer::new(2, 1000, samples), Arc::clone(control))
+    }
+
+    #[test]
+    fn fades_in_then_out_then_the_next_source_fades_in() {
+        let control = Arc::new(RampControl::new(Duration::from_millis(100)));
+        let mut source = Ramp::new(
+            SamplesBuffer::new(2, 1000, vec![1.0; 800]),
+            Arc::clone(&control),
+        );
+        let faded_in: Vec<f32> = source.by_ref().take(200).collect();
+        // Both channels of a frame get the same gain, rising steadily to full volume.
+        assert_eq!(faded_in[0], faded_in[1]);
+        assert!((faded_in[0] - 0.01).abs() < 1e-4);
+        assert!((faded_in[198] - 1.0).abs() < 1e-4);
+
+        control.stop();
+        let faded: Vec<f32> = source.by_ref().collect();
+        assert!((faded[0] - 0.99).abs() < 1e-4);
+        assert!((faded[100] - 0.49).abs() < 1e-4);
+        assert!(faded.windows(2).all(|pair| pair[1] <= pair[0]));
+        // The source ends once silent, long before its samples run out.
+        assert!((200..=202).contains(&faded.len()), "{}", faded.len());
+        assert_eq!(control.gain(), 0.0);
+
+        // A sink started after it gets its own control, and fades in.
+        let control = Arc::new(RampControl::new(Duration::from_millis(100)));
+        let next = Ramp::new(SamplesBuffer::new(1, 1000, vec![1.0; 200]), control);
+        let played: Vec<f32> = next.collect();
+        assert!((played[0] - 0.01).abs() < 1e-4);
+        assert!((played[49] - 0.5).abs() < 1e-4);
+        assert_eq!(played[150], 1.0);
+    }
+
+    #[test]
+    fn pausing_holds_the_source_until_resumed() {
+        let control = Arc::new(RampControl::new(Duration::from_millis(100)));
+        let mut source = counting(&control);
+        source.by_ref().take(400).count();
+        control.pause();
+        assert!(control.is_paused());
+        // Fades out over about 100 frames, then outputs silence without reading on.
+        let paused: Vec<f32> = source.by_ref().take(1000).collect();
+        assert!(paused[210..].iter().all(|sample| *sample == 0.0));
+
+        control.resume();
+        let resumed: Vec<f32> = source.by_ref().take(2).collect();
+        // Picks up at the frame after the last one faded out.
+        assert!((resumed[0] - 300.0 * 0.01).abs() < 0.02);
+        assert_eq!(resumed[0], resumed[1]);
+    }
+
+    #[test]
+    fn seeking_fades_out_jumps_then_fades_back_in() {
+        let control = Arc::new(RampControl::new(Duration::from_millis(100)));
+        let mut source = counting(&control);
+        source.by_ref().take(400).count();
+        control.seek(Duration::from_millis(800));
+        let seeked: Vec<f32> = source.by_ref().take(400).collect();
+        // Back up from the new position, at frame 800.
+        let silent = seeked.iter().position(|sample| *sample == 0.0).unwrap();
+        let back = seeked[silent..]
+            .iter()
+            .find(|sample| **sample > 0.0)
+            .unwrap();
+        assert!(seeked[..silent].windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!((back - 800.0 * 0.01).abs() < 1e-3);
+        assert!(control.take_seek_error().is_none());
+
+        // Seeking while paused stays paused.
+        control.pause();
+        control.seek(Duration::from_millis(100));
+        let paused: Vec<f32> = source.by_ref().take(400).collect();
+        assert!(paused[210..].iter().all(|sample| *sample == 0.0));
+        control.resume();
+        let resumed: Vec<f32> = source.by_ref().take(2).collect();
+        assert!((resumed[0] - 100.0 * 0.01).abs() < 1e-3);
+        assert_eq!(resumed[0], resumed[1]);
+    }
+}
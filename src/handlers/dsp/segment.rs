@@ -0,0 +1,100 @@
+use rodio::{Source, source::SeekError};
+use std::time::Duration;
+
+/// Source adapter playing a range of its input, e.g. one track of a CUE sheet.
+///
+/// Positions and seeks are relative to the start of the range.
+pub struct Segment<S> {
+    input: S,
+    start: Duration,
+    end: Option<Duration>,
+    /// Samples left in the range, counted once the input's format is known.
+    remaining: Option<u64>,
+}
+
+impl<S> Segment<S>
+where
+    S: Source<Item = f32>,
+{
+    /// Seeks the input to `start`. Plays to the end of the input when `end` is None.
+    pub fn new(mut input: S, start: Duration, end: Option<Duration>) -> Result<Self, SeekError> {
+        if !start.is_zero() {
+            input.try_seek(start)?;
+        }
+        let mut segment = Self {
+            input,
+            start,
+            end,
+            remaining: None,
+        };
+        segment.count_from(start);
+        Ok(segment)
+    }
+
+    /// Sets how many samples are left when playing from the given absolute position.
+    fn count_from(&mut self, pos: Duration) {
+        let rate = self.input.sample_rate() as f64 * self.input.channels() as f64;
+        let channels = self.input.channels().max(1) as u64;
+        self.remaining = self.end.map(|end| {
+            let samples = (end.saturating_sub(pos).as_secs_f64() * rate).round() as u64;
+            samples / channels * channels
+        });
+    }
+}
+
+impl<S> Iterator for Segment<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(remaining) = self.remaining.as_mut() {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
+        self.input.next()
+    }
+}
+
+impl<S> Source for Segment<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let frame_len = self.input.current_frame_len();
+        match self.remaining {
+            Some(remaining) => {
+                Some(frame_len.map_or(remaining as usize, |len| len.min(remaining as usize)))
+            }
+            None => frame_len,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self.end {
+            Some(end) => Some(end.saturating_sub(self.start)),
+            None => self
+                .input
+                .total_duration()
+                .map(|total| total.saturating_sub(self.start)),
+        }
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let pos = self.start + pos;
+        self.input.try_seek(pos)?;
+        self.count_from(pos);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use rodio::{Source, source::SeekError};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// How many mono samples the tap keeps.
+const TAP_LEN: usize = 4096;
+/// How many frames a source collects before handing them to the tap, so the lock isn't taken for every sample.
+const BATCH: usize = 256;
+
+/// The latest samples played, mixed down to mono, for the visualizer.
+pub struct SampleTap {
+    ring: Mutex<Ring>,
+}
+
+struct Ring {
+    samples: Vec<f32>,
+    /// Where the next sample is written.
+    pos: usize,
+    sample_rate: u32,
+}
+
+impl SampleTap {
+    pub fn new() -> Self {
+        Self {
+            ring: Mutex::new(Ring {
+                samples: vec![0.0; TAP_LEN],
+                pos: 0,
+                sample_rate: 44100,
+            }),
+        }
+    }
+
+    fn push(&self, samples: &[f32], sample_rate: u32) {
+        let mut ring = self.ring.lock().unwrap();
+        ring.sample_rate = sample_rate;
+        for sample in samples {
+            let pos = ring.pos;
+            ring.samples[pos] = *sample;
+            ring.pos = (pos + 1) % TAP_LEN;
+        }
+    }
+
+    /// Returns the latest samples, oldest first, up to the size of the tap, and their sample rate.
+    pub fn latest(&self, len: usize) -> (Vec<f32>, u32) {
+        let ring = self.ring.lock().unwrap();
+        let len = len.min(TAP_LEN);
+        let start = (ring.pos + TAP_LEN - len) % TAP_LEN;
+        let samples = (0..len)
+            .map(|offset| ring.samples[(start + offset) % TAP_LEN])
+            .collect();
+        (samples, ring.sample_rate)
+    }
+}
+
+/// Source adapter copying what passes through it into a `SampleTap`.
+pub struct Tap<S> {
+    input: S,
+    tap: Arc<SampleTap>,
+    /// Mono samples not handed over yet.
+    pending: Vec<f32>,
+    /// Sum of the channels of the current frame so far.
+    frame: f32,
+    channel: u16,
+}
+
+impl<S> Tap<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, tap: Arc<SampleTap>) -> Self {
+        Self {
+            input,
+            tap,
+            pending: Vec::with_capacity(BATCH),
+            frame: 0.0,
+            channel: 0,
+        }
+    }
+}
+
+impl<S> Iterator for Tap<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        let channels = self.input.channels().max(1);
+        self.frame += sample;
+        self.channel += 1;
+        if self.channel >= channels {
+            self.pending.push(self.frame / channels as f32);
+            self.frame = 0.0;
+            self.channel = 0;
+            if self.pending.len() >= BATCH {
+                self.tap.push(&self.pending, self.input.sample_rate());
+                self.pending.clear();
+            }
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Tap<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn keeps_the_latest_frames_mixed_to_mono() {
+        let tap = Arc::new(SampleTap::new());
+        // Stereo frames (0, 2n) average to n.
+        let samples: Vec<f32> = (0..5000).flat_map(|n| [0.0, 2.0 * n as f32]).collect();
+        let source = Tap::new(SamplesBuffer::new(2, 8000, samples), Arc::clone(&tap));
+        assert_eq!(source.count(), 10000);
+
+        let (latest, rate) = tap.latest(3);
+        assert_eq!(rate, 8000);
+        // The last partial batch of 5000 % 256 frames hasn't been handed over.
+        let last = (5000 - 5000 % BATCH) as f32;
+        assert_eq!(latest, vec![last - 3.0, last - 2.0, last - 1.0]);
+        assert_eq!(tap.latest(10_000).0.len(), TAP_LEN);
+    }
+}
@@ -0,0 +1,326 @@
+use rodio::{Source, source::SeekError};
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+/// Length of a WSOLA analysis window.
+const WINDOW: Duration = Duration::from_millis(40);
+/// How far from the ideal position a window may be moved to line up with the previous one.
+const TOLERANCE: Duration = Duration::from_millis(10);
+/// Only every n-th frame is compared when searching, which is plenty for voice and music.
+const SEARCH_STRIDE: usize = 4;
+/// Candidate positions are first tried in steps of this many frames, then refined around the best one.
+const COARSE_STEP: usize = 4;
+
+/// Stretch factor shared with the playing sources.
+pub struct StretchControl(AtomicU32);
+
+impl StretchControl {
+    pub fn new(factor: f32) -> Self {
+        Self(AtomicU32::new(factor.to_bits()))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, factor: f32) {
+        self.0.store(factor.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Source adapter changing the tempo without changing the pitch, using WSOLA (waveform similarity overlap-add).
+///
+/// Windows of the input are taken every `hop * factor` frames and overlap-added every `hop` frames.
+/// Each window is shifted within a small tolerance to the position that best continues the previous one,
+/// which avoids the phasing artifacts of plain overlap-add.
+pub struct TimeStretch<S> {
+    input: S,
+    control: Arc<StretchControl>,
+    channels: usize,
+    window_len: usize,
+    hop: usize,
+    tolerance: usize,
+    window: Vec<f32>,
+    /// Interleaved input frames, starting at absolute frame `buffer_start`.
+    buffer: Vec<f32>,
+    buffer_start: usize,
+    input_done: bool,
+    /// Ideal absolute frame of the next window.
+    analysis_pos: f64,
+    /// Absolute frame of the previous window, if any.
+    previous: Option<usize>,
+    /// Second half of the previous windowed segment, waiting to be overlapped.
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+}
+
+impl<S> TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: Arc<StretchControl>) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let rate = input.sample_rate().max(1) as f64;
+        let window_len = ((WINDOW.as_secs_f64() * rate) as usize / 2 * 2).max(2);
+        let hop = window_len / 2;
+        // Periodic Hann windows at 50% overlap sum to exactly one.
+        let window = (0..window_len)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / window_len as f32).cos())
+            .collect();
+
+        Self {
+            input,
+            control,
+            channels,
+            window_len,
+            hop,
+            tolerance: (TOLERANCE.as_secs_f64() * rate) as usize,
+            window,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            input_done: false,
+            analysis_pos: 0.0,
+            previous: None,
+            overlap: vec![0.0; hop * channels],
+            output: VecDeque::new(),
+        }
+    }
+
+    /// Reads input until the buffer holds the given absolute frame, or the input ends.
+    fn fill_to(&mut self, frame: usize) {
+        let wanted = (frame.saturating_sub(self.buffer_start)) * self.channels;
+        while !self.input_done && self.buffer.len() < wanted {
+            match self.input.next() {
+                Some(sample) => self.buffer.push(sample),
+                None => self.input_done = true,
+            }
+        }
+    }
+
+    /// Returns the interleaved sample at an absolute frame, or silence past the end of the input.
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        frame
+            .checked_sub(self.buffer_start)
+            .and_then(|frame| self.buffer.get(frame * self.channels + channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Cross-correlates the overlap region of a candidate window with the natural continuation of the previous one.
+    fn similarity(&self, candidate: usize, natural: usize) -> f32 {
+        (0..self.hop)
+            .step_by(SEARCH_STRIDE)
+            .map(|n| {
+                (0..self.channels)
+                    .map(|c| self.sample(candidate + n, c) * self.sample(natural + n, c))
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+
+    /// Produces the next `hop` frames of output. Returns false once the input is exhausted.
+    fn process_block(&mut self, factor: f32) -> bool {
+        let ideal = self.analysis_pos.round() as usize;
+        let natural = self.previous.map(|previous| previous + self.hop);
+        self.fill_to(ideal.max(natural.unwrap_or(0)) + self.tolerance + self.window_len);
+
+        let available = self.buffer_start + self.buffer.len() / self.channels;
+        if ideal >= available {
+            // Flush what is left of the previous window, up to the end of the input.
+            if let Some(previous) = self.previous.take() {
+                let frames = self.hop.min(available.saturating_sub(previous + self.hop));
+                self.output
+                    .extend(self.overlap[..frames * self.channels].iter().copied());
+            }
+            return false;
+        }
+
+        let start = match natural {
+            // At normal speed the natural continuation is the ideal position; reconstruction is exact.
+            Some(natural) if factor == 1.0 => natural,
+            Some(natural) => {
+                let low = ideal.saturating_sub(self.tolerance).max(self.buffer_start);
+                let high = ideal + self.tolerance;
+                let best = |candidates: &mut dyn Iterator<Item = usize>| {
+                    candidates
+                        .map(|candidate| (candidate, self.similarity(candidate, natural)))
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                        .map_or(ideal, |(candidate, _)| candidate)
+                };
+                let coarse = best(&mut (low..=high).step_by(COARSE_STEP));
+                best(
+                    &mut ((coarse.saturating_sub(COARSE_STEP - 1)).max(low)
+                        ..=(coarse + COARSE_STEP - 1).min(high)),
+                )
+            }
+            None => ideal,
+        };
+
+        // Output frames map to the input frames of the window, so the output ends with the input instead of on a whole hop.
+        let frames = match self.input_done {
+            true => self.hop.min(available.saturating_sub(start)),
+            false => self.hop,
+        };
+        for n in 0..frames {
+            for c in 0..self.channels {
+                let sample = self.sample(start + n, c);
+                // The very first window has nothing to overlap with, so its first half is passed through untouched.
+                let value = match self.previous {
+                    Some(_) => self.overlap[n * self.channels + c] + self.window[n] * sample,
+                    None => sample,
+                };
+                self.output.push_back(value);
+            }
+        }
+        for n in 0..self.hop {
+            for c in 0..self.channels {
+                self.overlap[n * self.channels + c] =
+                    self.window[self.hop + n] * self.sample(start + self.hop + n, c);
+            }
+        }
+
+        self.previous = Some(start);
+        self.analysis_pos = match factor == 1.0 {
+            true => (start + self.hop) as f64,
+            false => self.analysis_pos + self.hop as f64 * factor as f64,
+        };
+
+        // Drop input that no future window can reach.
+        let keep_from = (self.analysis_pos as usize)
+            .saturating_sub(self.tolerance)
+            .min(start + self.hop);
+        if keep_from > self.buffer_start {
+            let drop = ((keep_from - self.buffer_start) * self.channels).min(self.buffer.len());
+            self.buffer.drain(..drop);
+            self.buffer_start += drop / self.channels;
+        }
+        true
+    }
+}
+
+impl<S> Iterator for TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.output.is_empty() {
+            let factor = self.control.get();
+            if !self.process_block(factor) && self.output.is_empty() {
+                return None;
+            }
+        }
+        self.output.pop_front()
+    }
+}
+
+impl<S> Source for TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.buffer.clear();
+        self.output.clear();
+        self.overlap.iter_mut().for_each(|sample| *sample = 0.0);
+        self.input_done = false;
+        self.previous = None;
+        self.buffer_start = 0;
+        self.analysis_pos = 0.0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    const RATE: u32 = 48000;
+
+    /// One channel of a sine, a number of seconds long.
+    fn sine(frequency: f32, secs: f32) -> Vec<f32> {
+        (0..(secs * RATE as f32) as usize)
+            .map(|n| 0.5 * (2.0 * PI * frequency * n as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    fn stretch(samples: Vec<f32>, factor: f32) -> Vec<f32> {
+        let input = SamplesBuffer::new(1, RATE, samples);
+        TimeStretch::new(input, Arc::new(StretchControl::new(factor))).collect()
+    }
+
+    /// Estimates the frequency of a signal from how often it crosses zero, leaving out its edges.
+    fn frequency(samples: &[f32]) -> f32 {
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        let crossings = middle
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        crossings as f32 / 2.0 / (middle.len() as f32 / RATE as f32)
+    }
+
+    #[test]
+    fn normal_speed_passes_the_input_through() {
+        let input = sine(440.0, 1.0);
+        let output = stretch(input.clone(), 1.0);
+        assert_eq!(output.len(), input.len());
+        let error = input
+            .iter()
+            .zip(&output)
+            .fold(0f32, |error, (a, b)| error.max((a - b).abs()));
+        assert!(error < 1e-5, "{}", error);
+    }
+
+    #[test]
+    fn length_scales_with_the_inverse_of_the_speed() {
+        for factor in [0.5, 0.8, 1.5, 2.0] {
+            let output = stretch(sine(440.0, 2.0), factor);
+            let expected = 2.0 * RATE as f32 / factor;
+            // Off by at most a window at the end.
+            assert!(
+                (output.len() as f32 - expected).abs() < RATE as f32 * 0.05,
+                "{} frames at {}x",
+                output.len(),
+                factor
+            );
+        }
+    }
+
+    #[test]
+    fn pitch_is_kept_at_any_speed() {
+        for factor in [0.5, 2.0] {
+            let measured = frequency(&stretch(sine(440.0, 2.0), factor));
+            assert!(
+                (measured - 440.0).abs() < 5.0,
+                "{} Hz at {}x",
+                measured,
+                factor
+            );
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::data::config::VolumeCurve;
+use rodio::{Source, source::SeekError};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+/// Level above which the soft limiter starts compressing.
+const KNEE: f32 = 0.8;
+
+/// Maps a volume level in percent to an amplitude factor.
+///
+/// The curve shapes 0–100%; boost above 100% is linear, so 150% is 1.5x (+3.5 dB).
+pub fn curve_gain(curve: VolumeCurve, level: i16, db_range: f32) -> f32 {
+    let level = level.max(0) as f32 / 100.0;
+    if level > 1.0 {
+        return level;
+    }
+    match curve {
+        VolumeCurve::Linear => level,
+        VolumeCurve::Cubic => level.powi(3),
+        VolumeCurve::Db => match level {
+            0.0 => 0.0,
+            _ => 10f32.powf((level - 1.0) * db_range / 20.0),
+        },
+    }
+}
+
+/// Volume factor shared with the playing sources.
+pub struct VolumeControl(AtomicU32);
+
+impl VolumeControl {
+    pub fn new(gain: f32) -> Self {
+        Self(AtomicU32::new(gain.to_bits()))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, gain: f32) {
+        self.0.store(gain.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Source adapter applying the volume. When boosting above 1x, peaks are softly limited instead of clipping.
+pub struct Volume<S> {
+    input: S,
+    control: Arc<VolumeControl>,
+}
+
+impl<S> Volume<S> {
+    pub fn new(input: S, control: Arc<VolumeControl>) -> Self {
+        Self { input, control }
+    }
+}
+
+/// Passes samples below the knee unchanged and bends louder ones smoothly towards ±1.
+fn soft_limit(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= KNEE {
+        return sample;
+    }
+    let range = 1.0 - KNEE;
+    (KNEE + range * ((level - KNEE) / range).tanh()).copysign(sample)
+}
+
+impl<S> Iterator for Volume<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let gain = self.control.get();
+        let sample = self.input.next()? * gain;
+        Some(match gain > 1.0 {
+            true => soft_limit(sample),
+            false => sample,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Volume<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_meet_at_the_ends() {
+        for curve in [VolumeCurve::Linear, VolumeCurve::Cubic, VolumeCurve::Db] {
+            assert_eq!(curve_gain(curve, 0, 60.0), 0.0);
+            assert_eq!(curve_gain(curve, 100, 60.0), 1.0);
+            assert_eq!(curve_gain(curve, 150, 60.0), 1.5);
+        }
+        assert_eq!(curve_gain(VolumeCurve::Cubic, 50, 60.0), 0.125);
+        // Half the level is half the dB range.
+        assert!((curve_gain(VolumeCurve::Db, 50, 60.0) - 10f32.powf(-1.5)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn soft_limit_is_continuous_and_bounded() {
+        assert_eq!(soft_limit(0.5), 0.5);
+        assert_eq!(soft_limit(-KNEE), -KNEE);
+        assert!((soft_limit(KNEE + 1e-4) - (KNEE + 1e-4)).abs() < 1e-5);
+        for sample in [1.0, 1.5, 3.0, 100.0] {
+            let limited = soft_limit(sample);
+            assert!(limited <= 1.0 && limited > KNEE, "{sample} -> {limited}");
+            assert_eq!(soft_limit(-sample), -limited);
+        }
+    }
+}
@@ -1,48 +1,132 @@
-use crate::handlers::sink_handler::SinkHandler;
+use crate::{
+    data::config::VolumeCurve,
+    handlers::{
+        backend::{Backend, DEFAULT_DEVICE, NULL_DEVICE, null::NullBackend, open_backend},
+        dsp::{ab_loop::LoopRange, equalizer::EqControl, volume::curve_gain},
+        output::OutputSlot,
+        sink_handler::SinkHandler,
+        stream::StreamInfo,
+    },
+};
 
-use rodio::OutputStream;
-use std::{error::Error, path::PathBuf, sync::Arc, thread};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How long a device may stop pulling samples before it is considered gone.
+const OUTPUT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long to wait before trying the default device again after it failed to open, doubling up to the maximum.
+const FALLBACK_DELAY: Duration = Duration::from_secs(1);
+const MAX_FALLBACK_DELAY: Duration = Duration::from_secs(30);
 
 /// Encapsulates audio-related state and controls.
 pub struct InputHandler {
-    pub _stream: OutputStream,
+    pub backend: Box<dyn Backend>,
+    pub output_slot: Arc<OutputSlot>,
+    pub device: String,
     pub audio_player: Arc<SinkHandler>,
+    pub equalizer: Arc<EqControl>,
     pub vol: i16,
+    pub muted: bool,
+    pub volume_curve: VolumeCurve,
+    pub volume_db_range: f32,
+    pub max_volume: i16,
+    pub speed: f32,
+    pub preserve_pitch: bool,
     pub paused: bool,
+    /// Volume factor of a fade out in progress.
+    fade: f32,
+    /// When the default device may be tried again after a failed fallback, and the wait after the next failure.
+    next_fallback: Instant,
+    fallback_delay: Duration,
+    /// The latest play or append, which the next one waits for so they reach the sink in order.
+    last_job: Option<JoinHandle<()>>,
 }
 impl InputHandler {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
-        let (stream, stream_handle) = OutputStream::try_default()?;
-        let audio_player = Arc::new(SinkHandler::new(stream_handle));
+    /// Opens the named device, falling back to the default one and then to the null device, so it works without a sound card.
+    pub fn new(equalizer: EqControl, device: &str) -> Result<Self, Box<dyn Error>> {
+        let (backend, device) = match open_backend(device) {
+            Ok(backend) => (backend, device),
+            Err(_) => match open_backend(DEFAULT_DEVICE) {
+                Ok(backend) => (backend, DEFAULT_DEVICE),
+                Err(_) => (
+                    Box::new(NullBackend::new(1.0)) as Box<dyn Backend>,
+                    NULL_DEVICE,
+                ),
+            },
+        };
+        Self::with_backend(equalizer, device, backend)
+    }
+
+    /// Plays through the given backend, shown under the given device name.
+    pub fn with_backend(
+        equalizer: EqControl,
+        device: &str,
+        mut backend: Box<dyn Backend>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let output_slot = Arc::new(OutputSlot::new());
+        output_slot.attach(backend.as_mut())?;
+        let equalizer = Arc::new(equalizer);
+        let audio_player = Arc::new(SinkHandler::new(
+            Arc::clone(&output_slot),
+            Arc::clone(&equalizer),
+        ));
         Ok(Self {
-            _stream: stream,
+            backend,
+            output_slot,
+            device: device.to_string(),
             audio_player,
+            equalizer,
             vol: 100,
+            muted: false,
+            volume_curve: VolumeCurve::Linear,
+            volume_db_range: 60.0,
+            max_volume: 100,
+            speed: 1.0,
+            preserve_pitch: true,
             paused: false,
+            fade: 1.0,
+            next_fallback: Instant::now(),
+            fallback_delay: FALLBACK_DELAY,
+            last_job: None,
         })
     }
 
-    /// Starts playing the file on a new thread using the AudioPlayer.
-    pub fn play(&mut self, path: &PathBuf) {
-        let path_clone = path.clone();
-        let current_vol = self.vol;
+    /// Starts playing the file on a new thread using the AudioPlayer, scaled by the ReplayGain factor.
+    pub fn play(&mut self, path: &Path, gain: f32) {
+        let path_clone = path.to_path_buf();
         let sink_handler = Arc::clone(&self.audio_player);
-        thread::spawn(move || {
-            sink_handler.play_file(path_clone, current_vol);
+        sink_handler.forget_now_playing();
+        self.spawn_job(move || {
+            sink_handler.play_file(path_clone, gain);
         });
         self.paused = false;
     }
 
-    /// Append audio to the sink.
-    pub fn append(&mut self, path: &PathBuf) {
-        let path_clone = path.clone();
-        let current_vol = self.vol;
+    /// Append audio to the sink, scaled by the ReplayGain factor.
+    pub fn append(&mut self, path: &Path, gain: f32) {
+        let path_clone = path.to_path_buf();
         let sink_handler = Arc::clone(&self.audio_player);
-        thread::spawn(move || {
-            sink_handler.append_to_sink(path_clone, current_vol);
+        self.spawn_job(move || {
+            sink_handler.append_to_sink(path_clone, gain);
         });
     }
 
+    /// Opens a source on a new thread, after the previous one, since opening a stream can take a while.
+    fn spawn_job(&mut self, job: impl FnOnce() + Send + 'static) {
+        let previous = self.last_job.take();
+        self.last_job = Some(thread::spawn(move || {
+            if let Some(previous) = previous {
+                let _ = previous.join();
+            }
+            job();
+        }));
+    }
+
     /// Removes all currently loaded Sources from the Sink, and pauses it.
     pub fn clear_sink(&self) {
         self.audio_player.clear();
@@ -51,16 +135,148 @@ impl InputHandler {
     /// Toggles between play and pause.
     pub fn toggle_play_pause(&mut self) {
         self.paused = !self.paused;
+        if !self.paused {
+            self.audio_player.release();
+        }
         self.audio_player.toggle_play_pause();
     }
 
-    /// Adjusts the volume by a given delta.
+    /// Adjusts the volume by a given delta, up to the maximum volume. Unmutes.
     pub fn adjust_volume(&mut self, delta: i16) {
-        let new_vol = self.vol + delta;
-        if new_vol >= 0 && new_vol <= 100 {
-            self.vol = new_vol;
-            self.audio_player.set_volume(self.vol);
+        self.vol = (self.vol + delta).clamp(0, self.max_volume);
+        self.muted = false;
+        self.apply_volume();
+    }
+
+    /// Mutes, or restores the previous level.
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.apply_volume();
+    }
+
+    /// Sets how levels map to amplitude and the highest level, between 100% and 150%.
+    pub fn set_volume_curve(&mut self, curve: VolumeCurve, db_range: f32, max_volume: i16) {
+        self.volume_curve = curve;
+        self.volume_db_range = db_range;
+        self.max_volume = max_volume.clamp(100, 150);
+        self.vol = self.vol.min(self.max_volume);
+        self.apply_volume();
+    }
+
+    /// Hands the amplitude factor for the current level to the playing sources.
+    fn apply_volume(&self) {
+        let gain = match self.muted {
+            true => 0.0,
+            false => curve_gain(self.volume_curve, self.vol, self.volume_db_range) * self.fade,
+        };
+        self.audio_player.set_volume(gain);
+    }
+
+    /// Adjusts the playback speed by a given delta, between 0.5x and 3x.
+    pub fn adjust_speed(&mut self, delta: f32) {
+        self.speed = ((self.speed + delta).clamp(0.5, 3.0) * 100.0).round() / 100.0;
+        self.audio_player.set_speed(self.speed, self.preserve_pitch);
+    }
+
+    /// Resets the playback speed to 1x.
+    pub fn reset_speed(&mut self) {
+        self.speed = 1.0;
+        self.audio_player.set_speed(self.speed, self.preserve_pitch);
+    }
+
+    /// Switches between time stretching (pitch is kept) and resampling (pitch follows speed).
+    pub fn set_preserve_pitch(&mut self, preserve_pitch: bool) {
+        self.preserve_pitch = preserve_pitch;
+        self.audio_player.set_speed(self.speed, self.preserve_pitch);
+    }
+
+    /// Seeks the current track by a number of seconds, forwards or backwards.
+    pub fn seek_by(&self, delta: i64) {
+        let target = (self.sink_pos_millis() as i64 + delta * 1000).max(0);
+        self.seek(Duration::from_millis(target as u64))
+    }
+
+    /// Seeks the current track to the given position. Errors show up in `take_error`.
+    pub fn seek(&self, pos: Duration) {
+        self.audio_player.seek(pos)
+    }
+
+    /// Holds playback at the start of the track following the given one, or lets it move on.
+    pub fn stop_after(&self, path: Option<&Path>) {
+        self.audio_player.stop_after(path.map(Path::to_path_buf));
+    }
+
+    /// Returns true when playback is held after the track to stop after.
+    pub fn is_held(&self) -> bool {
+        self.audio_player.is_held()
+    }
+
+    /// Sets the length of the volume ramps that keep pausing, resuming, stopping, skipping and seeking from clicking.
+    pub fn set_transition_fade(&self, length: Duration) {
+        self.audio_player.set_ramp_length(length);
+    }
+
+    /// Returns the latest samples played, mixed to mono, and their sample rate, for the visualizer.
+    pub fn latest_samples(&self, len: usize) -> (Vec<f32>, u32) {
+        self.audio_player.latest_samples(len)
+    }
+
+    /// Scales the volume by a factor between 0 and 1, for fading out.
+    pub fn set_fade(&mut self, fade: f32) {
+        self.fade = fade.clamp(0.0, 1.0);
+        self.apply_volume();
+    }
+
+    /// Repeats a range of a track seamlessly, or stops repeating.
+    pub fn set_loop(&self, range: Option<LoopRange>) {
+        self.audio_player.set_loop(range);
+    }
+
+    /// Moves playback to another output device, keeping the queue and position.
+    pub fn switch_device(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut backend = open_backend(name)?;
+        self.output_slot.attach(backend.as_mut())?;
+        self.backend = backend;
+        self.device = name.to_string();
+        Ok(())
+    }
+
+    /// Falls back to the default device when the current one stops pulling samples, e.g. after being unplugged.
+    /// Returns the name of the lost device if a fallback happened.
+    pub fn check_output(&mut self) -> Option<String> {
+        if self.device == DEFAULT_DEVICE || self.output_slot.stalled_for() < OUTPUT_TIMEOUT {
+            return None;
+        }
+        if Instant::now() < self.next_fallback {
+            return None;
         }
+        let lost = self.device.clone();
+        match self.switch_device(DEFAULT_DEVICE) {
+            Ok(()) => {
+                self.fallback_delay = FALLBACK_DELAY;
+                Some(lost)
+            }
+            Err(_) => {
+                self.next_fallback = Instant::now() + self.fallback_delay;
+                self.fallback_delay = (self.fallback_delay * 2).min(MAX_FALLBACK_DELAY);
+                None
+            }
+        }
+    }
+
+    /// Returns the path of the file being played.
+    pub fn now_playing(&self) -> Option<PathBuf> {
+        self.audio_player.now_playing()
+    }
+
+    /// Returns what is known about the stream at the URL, if it's been opened.
+    pub fn stream_info(&self, url: &Path) -> Option<Arc<StreamInfo>> {
+        self.audio_player.stream_info(url)
+    }
+
+    /// Returns the last error that kept a track from playing or seeking, forgetting it.
+    pub fn take_error(&self) -> Option<String> {
+        self.audio_player.take_error()
     }
 
     /// Returns the sink's position in seconds.
@@ -83,3 +299,334 @@ impl InputHandler {
         self.audio_player.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::config::EqPreset,
+        handlers::{
+            backend::wav::WavBackend, dsp::equalizer::EqSettings, stream::tests::serve_icy,
+        },
+        test_files::{TempDir, write_tone},
+    };
+    use hound::WavReader;
+    use std::{
+        fs,
+        path::PathBuf,
+        thread::sleep,
+        time::{Duration, Instant},
+    };
+
+    fn handler(backend: Box<dyn Backend>) -> InputHandler {
+        let equalizer = EqControl::new(EqSettings {
+            enabled: false,
+            name: "flat".to_string(),
+            preset: EqPreset::default(),
+        });
+        InputHandler::with_backend(equalizer, "test", backend).unwrap()
+    }
+
+    /// Polls the condition until it holds, failing the test after a few seconds.
+    fn wait_until(what: &str, condition: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "timed out: {what}"
+            );
+            sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn queue_plays_in_order_then_empties() {
+        let dir = TempDir::new("queue");
+        let (first, second) = (dir.join("first.wav"), dir.join("second.wav"));
+        write_tone(&first, 0.5, 0.5);
+        write_tone(&second, 0.5, 0.5);
+
+        let mut audio = handler(Box::new(NullBackend::new(10.0)));
+        audio.play(&first, 1.0);
+        wait_until("first track queued", || audio.get_len() == 1);
+        audio.append(&second, 1.0);
+        wait_until("second track queued", || audio.get_len() == 2);
+
+        wait_until("first track finished", || audio.get_len() == 1);
+        assert!(audio.sink_pos_millis() < 500);
+        wait_until("queue finished", || audio.is_empty());
+    }
+
+    #[test]
+    fn skip_restarts_at_the_next_track() {
+        let dir = TempDir::new("skip");
+        let (first, second) = (dir.join("first.wav"), dir.join("second.wav"));
+        write_tone(&first, 5.0, 0.5);
+        write_tone(&second, 5.0, 0.5);
+
+        let mut audio = handler(Box::new(NullBackend::new(2.0)));
+        audio.play(&first, 1.0);
+        wait_until("first track queued", || audio.get_len() == 1);
+        audio.append(&second, 1.0);
+        wait_until("second track queued", || audio.get_len() == 2);
+        wait_until("first track playing", || audio.sink_pos_millis() >= 500);
+
+        // Same steps as skipping in the app: clear, then play what's left of the queue.
+        audio.clear_sink();
+        audio.play(&second, 1.0);
+        wait_until("second track playing", || {
+            audio.get_len() == 1 && audio.sink_pos_millis() < 500
+        });
+    }
+
+    #[test]
+    fn seek_moves_the_position() {
+        let dir = TempDir::new("seek");
+        let track = dir.join("track.wav");
+        write_tone(&track, 5.0, 0.5);
+
+        let mut audio = handler(Box::new(NullBackend::new(1.0)));
+        audio.play(&track, 1.0);
+        wait_until("track playing", || audio.get_len() == 1);
+
+        audio.seek(Duration::from_secs(3));
+        wait_until("position moved forward", || audio.sink_pos_millis() >= 3000);
+        audio.seek_by(-2);
+        wait_until("position moved back", || audio.sink_pos_millis() < 2000);
+        assert!(audio.sink_pos_millis() >= 900);
+    }
+
+    #[test]
+    fn wav_backend_records_the_output() {
+        let dir = TempDir::new("wav");
+        let (track, output) = (dir.join("track.wav"), dir.join("output.wav"));
+        write_tone(&track, 0.25, 0.5);
+
+        let mut audio = handler(Box::new(WavBackend::create(&output, 20.0).unwrap()));
+        audio.play(&track, 1.0);
+        wait_until("track queued", || audio.get_len() == 1);
+        wait_until("track finished", || audio.is_empty());
+        // Dropping the handler stops the writer and finalizes the file.
+        drop(audio);
+
+        let samples: Vec<f32> = WavReader::open(&output)
+            .unwrap()
+            .into_samples::<f32>()
+            .map(Result::unwrap)
+            .collect();
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let audible = samples.iter().filter(|s| s.abs() > 0.01).count();
+        assert!((peak - 0.5).abs() < 0.02, "peak {peak}");
+        // Roughly 0.25 s of stereo 44.1 kHz (22050 samples), recorded once.
+        assert!((20000..=23200).contains(&audible), "audible {audible}");
+    }
+
+    #[test]
+    fn pausing_and_seeking_ramp_without_clicks() {
+        let dir = TempDir::new("ramp");
+        let (track, output) = (dir.join("track.wav"), dir.join("output.wav"));
+        write_tone(&track, 1.0, 0.5);
+
+        let mut audio = handler(Box::new(WavBackend::create(&output, 4.0).unwrap()));
+        audio.set_transition_fade(Duration::from_millis(20));
+        audio.play(&track, 1.0);
+        wait_until("track playing", || audio.sink_pos_millis() >= 200);
+        audio.toggle_play_pause();
+        sleep(Duration::from_millis(20));
+        audio.toggle_play_pause();
+        wait_until("track resumed", || audio.sink_pos_millis() >= 400);
+        audio.seek(Duration::from_millis(100));
+        wait_until("track finished", || audio.is_empty());
+        drop(audio);
+
+        let samples: Vec<f32> = WavReader::open(&output)
+            .unwrap()
+            .into_samples::<f32>()
+            .map(Result::unwrap)
+            .collect();
+        // The tone itself moves at most ~0.03 between samples; a cut would jump by up to 0.5.
+        let jump = samples
+            .iter()
+            .step_by(2)
+            .zip(samples.iter().step_by(2).skip(1))
+            .fold(0.0f32, |jump, (a, b)| jump.max((b - a).abs()));
+        assert!(jump < 0.05, "jump {jump}");
+    }
+
+    #[test]
+    fn fades_happen_without_blocking_the_caller() {
+        let dir = TempDir::new("fade");
+        let track = dir.join("track.wav");
+        write_tone(&track, 5.0, 0.5);
+
+        let mut audio = handler(Box::new(NullBackend::new(1.0)));
+        audio.set_transition_fade(Duration::from_millis(300));
+        audio.play(&track, 1.0);
+        wait_until("track playing", || audio.sink_pos_millis() >= 500);
+
+        let start = Instant::now();
+        audio.toggle_play_pause();
+        assert!(start.elapsed() < Duration::from_millis(50));
+        // Once faded out, the track holds its position.
+        sleep(Duration::from_millis(500));
+        let held = audio.sink_pos_millis();
+        sleep(Duration::from_millis(200));
+        assert_eq!(audio.sink_pos_millis(), held);
+        assert_eq!(audio.get_len(), 1);
+
+        let start = Instant::now();
+        audio.clear_sink();
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert!(audio.is_empty());
+    }
+
+    #[test]
+    fn cue_tracks_play_back_to_back() {
+        let dir = TempDir::new("cue");
+        let (album, output) = (dir.join("album.wav"), dir.join("output.wav"));
+        write_tone(&album, 1.0, 0.5);
+        let sheet = dir.join("album.cue");
+        fs::write(
+            &sheet,
+            "FILE \"album.wav\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 00:00:30\n",
+        )
+        .unwrap();
+
+        let mut audio = handler(Box::new(WavBackend::create(&output, 20.0).unwrap()));
+        audio.play(&sheet.join("track01"), 1.0);
+        wait_until("first track queued", || audio.get_len() == 1);
+        audio.append(&sheet.join("track02"), 1.0);
+        wait_until("second track queued", || audio.get_len() == 2);
+        wait_until("album finished", || audio.is_empty());
+        drop(audio);
+
+        let samples: Vec<f32> = WavReader::open(&output)
+            .unwrap()
+            .into_samples::<f32>()
+            .map(Result::unwrap)
+            .collect();
+        let first = samples.iter().position(|s| s.abs() > 0.01).unwrap();
+        let last = samples.iter().rposition(|s| s.abs() > 0.01).unwrap();
+        // Both tracks together are the whole file, without a gap at the transition.
+        let played = (last - first) as f32 / (44100.0 * 2.0);
+        assert!((played - 1.0).abs() < 0.01, "played {played}s");
+        let longest_silence = samples[first..last]
+            .split(|s| s.abs() > 0.01)
+            .map(<[f32]>::len)
+            .max()
+            .unwrap();
+        assert!(longest_silence < 8, "gap of {longest_silence} samples");
+    }
+
+    #[test]
+    fn seeking_in_a_cue_track_is_relative_to_its_start() {
+        let dir = TempDir::new("cue-seek");
+        let album = dir.join("album.wav");
+        write_tone(&album, 4.0, 0.5);
+        let sheet = dir.join("album.cue");
+        fs::write(
+            &sheet,
+            "FILE \"album.wav\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 00:02:00\n",
+        )
+        .unwrap();
+
+        let mut audio = handler(Box::new(NullBackend::new(1.0)));
+        audio.play(&sheet.join("track02"), 1.0);
+        wait_until("track playing", || audio.get_len() == 1);
+        assert!(audio.sink_pos_millis() < 1000);
+
+        audio.seek(Duration::from_secs(1));
+        wait_until("position moved", || audio.sink_pos_millis() >= 1000);
+        assert!(audio.sink_pos_millis() < 1900);
+        // Only the second half of the file is left, so the track ends about a second later.
+        wait_until("track finished", || audio.is_empty());
+    }
+
+    #[test]
+    fn streams_play_with_their_title() {
+        let dir = TempDir::new("stream");
+        let track = dir.join("track.wav");
+        write_tone(&track, 2.0, 0.5);
+        let url = serve_icy(fs::read(&track).unwrap(), 4000, "Artist - Song", 0);
+        let url = PathBuf::from(format!("{}/live", url));
+
+        let mut audio = handler(Box::new(NullBackend::new(1.0)));
+        audio.play(&url, 1.0);
+        wait_until("stream playing", || audio.get_len() == 1);
+        wait_until("position moving", || audio.sink_pos_millis() >= 200);
+        assert_eq!(audio.now_playing(), Some(url.clone()));
+        let info = audio.stream_info(&url).unwrap();
+        assert_eq!(info.title(), Some("Artist - Song".to_string()));
+        assert_eq!(info.station(), Some("Test Radio".to_string()));
+    }
+
+    #[test]
+    fn unreachable_streams_report_an_error() {
+        let mut audio = handler(Box::new(NullBackend::new(1.0)));
+        audio.play(Path::new("http://127.0.0.1:1/live"), 1.0);
+        let start = Instant::now();
+        let error = loop {
+            if let Some(error) = audio.take_error() {
+                break error;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "no error");
+            sleep(Duration::from_millis(5));
+        };
+        assert!(error.contains("127.0.0.1:1"), "{error}");
+        assert!(audio.is_empty());
+    }
+
+    #[test]
+    fn ab_loop_repeats_until_cleared() {
+        let dir = TempDir::new("ab-loop");
+        let track = dir.join("track.wav");
+        write_tone(&track, 2.0, 0.5);
+
+        let mut audio = handler(Box::new(NullBackend::new(4.0)));
+        audio.set_loop(Some(LoopRange {
+            path: track.clone(),
+            start: Duration::from_millis(500),
+            end: Duration::from_millis(1000),
+        }));
+        audio.play(&track, 1.0);
+        wait_until("track playing", || audio.get_len() == 1);
+        wait_until("loop reached", || audio.sink_pos_millis() >= 500);
+
+        // Twice the track's length at 4x, it's still going round the loop.
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(1) {
+            let pos = audio.sink_pos_millis();
+            assert!((450..=1050).contains(&pos), "position {pos}");
+            sleep(Duration::from_millis(5));
+        }
+        assert_eq!(audio.get_len(), 1);
+
+        audio.set_loop(None);
+        wait_until("track finished", || audio.is_empty());
+    }
+
+    #[test]
+    fn stop_after_holds_the_next_track_until_resumed() {
+        let dir = TempDir::new("stop-after");
+        let (first, second) = (dir.join("first.wav"), dir.join("second.wav"));
+        write_tone(&first, 0.5, 0.5);
+        write_tone(&second, 0.5, 0.5);
+
+        let mut audio = handler(Box::new(NullBackend::new(10.0)));
+        audio.play(&first, 1.0);
+        audio.append(&second, 1.0);
+        wait_until("second track queued", || audio.get_len() == 2);
+        audio.stop_after(Some(&first));
+
+        wait_until("second track held", || audio.is_held());
+        assert_eq!(audio.now_playing(), Some(second.clone()));
+        sleep(Duration::from_millis(200));
+        assert_eq!(audio.sink_pos_millis(), 0);
+
+        // Pausing then resuming lets it play.
+        audio.toggle_play_pause();
+        audio.toggle_play_pause();
+        assert!(!audio.is_held());
+        wait_until("queue finished", || audio.is_empty());
+    }
+}
@@ -1,2 +1,6 @@
+pub mod backend;
+pub mod dsp;
 pub mod input_handler;
+pub mod output;
 pub mod sink_handler;
+pub mod stream;
@@ -0,0 +1,280 @@
+use crate::handlers::backend::Backend;
+use rodio::Source;
+use std::{
+    error::Error,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Samples pulled from the slot at once. Each chunk has a single format.
+const CHUNK: usize = 1024;
+/// Format reported while nothing is playing.
+const IDLE_CHANNELS: u16 = 2;
+const IDLE_SAMPLE_RATE: u32 = 44100;
+
+/// A source played by the slot.
+type SlotInput = Box<dyn Source<Item = f32> + Send>;
+
+/// Encapsulates the source that the active output device pulls samples from.
+///
+/// The sink's queue lives here rather than in a device's stream, so switching devices keeps the queue and its position.
+pub struct OutputSlot {
+    source: Mutex<Option<SlotInput>>,
+    /// The source to play once the current one ends, and when to stop waiting for it to.
+    next: Mutex<Option<(SlotInput, Instant)>>,
+    generation: AtomicU64,
+    started: Instant,
+    last_pull: AtomicU64,
+}
+
+impl OutputSlot {
+    pub fn new() -> Self {
+        Self {
+            source: Mutex::new(None),
+            next: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            started: Instant::now(),
+            last_pull: AtomicU64::new(0),
+        }
+    }
+
+    /// Plays the source once the current one ends, such as after fading out, or once the timeout has passed.
+    pub fn set_source_after(&self, source: SlotInput, timeout: Duration) {
+        *self.next.lock().unwrap() = Some((source, Instant::now() + timeout));
+    }
+
+    /// Returns how long it has been since a device last pulled samples.
+    pub fn stalled_for(&self) -> Duration {
+        let last_pull = Duration::from_millis(self.last_pull.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last_pull)
+    }
+
+    /// Makes the backend the one playing the slot.
+    /// The previously attached backend only stops pulling once the new one has started, so a failed switch keeps it playing.
+    pub fn attach(self: &Arc<Self>, backend: &mut dyn Backend) -> Result<(), Box<dyn Error>> {
+        let generation = self.generation.load(Ordering::SeqCst) + 1;
+        backend.start(SlotSource::new(Arc::clone(self), generation))?;
+        self.last_pull
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+        self.generation.store(generation, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Source handed to a backend, reading the slot chunk by chunk.
+pub struct SlotSource {
+    slot: Arc<OutputSlot>,
+    generation: u64,
+    chunk: Vec<f32>,
+    pos: usize,
+    channels: u16,
+    sample_rate: u32,
+    ended: bool,
+}
+
+impl SlotSource {
+    fn new(slot: Arc<OutputSlot>, generation: u64) -> Self {
+        let mut source = Self {
+            slot,
+            generation,
+            chunk: Vec::with_capacity(CHUNK),
+            pos: 0,
+            channels: IDLE_CHANNELS,
+            sample_rate: IDLE_SAMPLE_RATE,
+            ended: false,
+        };
+        source.refill();
+        source
+    }
+
+    /// Reads the next chunk from the slot, or silence if nothing is playing.
+    fn refill(&mut self) {
+        self.chunk.clear();
+        self.pos = 0;
+        let current = self.slot.generation.load(Ordering::SeqCst);
+        if current > self.generation {
+            // A newer device took over.
+            self.ended = true;
+            return;
+        }
+        if current < self.generation {
+            // The device is starting and the previous one still plays the slot.
+            self.channels = IDLE_CHANNELS;
+            self.sample_rate = IDLE_SAMPLE_RATE;
+            self.chunk.resize(CHUNK, 0.0);
+            return;
+        }
+        self.slot.last_pull.store(
+            self.slot.started.elapsed().as_millis() as u64,
+            Ordering::Relaxed,
+        );
+
+        let mut guard = self.slot.source.lock().unwrap();
+        let mut next = self.slot.next.lock().unwrap();
+        if let Some((_, deadline)) = next.as_ref()
+            && (guard.is_none() || Instant::now() >= *deadline)
+        {
+            *guard = next.take().map(|(source, _)| source);
+        }
+        drop(next);
+        if let Some(source) = guard.as_mut() {
+            self.channels = source.channels().max(1);
+            self.sample_rate = source.sample_rate();
+            let channels = self.channels as usize;
+            let len = source
+                .current_frame_len()
+                .unwrap_or(CHUNK)
+                .min(CHUNK / channels * channels)
+                .max(channels);
+            self.chunk.extend(source.by_ref().take(len));
+            if self.chunk.len() < len {
+                *guard = None;
+            }
+        }
+
+        match self.chunk.is_empty() {
+            // Nothing is playing, so output a chunk of silence.
+            true => {
+                self.channels = IDLE_CHANNELS;
+                self.sample_rate = IDLE_SAMPLE_RATE;
+                self.chunk.resize(CHUNK, 0.0);
+            }
+            // Keep whole frames even when a source ends mid-frame.
+            false => {
+                let channels = self.channels as usize;
+                self.chunk
+                    .resize(self.chunk.len().div_ceil(channels) * channels, 0.0);
+            }
+        }
+    }
+}
+
+impl Iterator for SlotSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.ended {
+            return None;
+        }
+        let sample = self.chunk[self.pos];
+        self.pos += 1;
+        // Refill right away so the format of the next chunk is known before it's asked for.
+        if self.pos >= self.chunk.len() {
+            self.refill();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for SlotSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.ended {
+            true => Some(0),
+            false => Some(self.chunk.len() - self.pos),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    /// Keeps the source it's started with, so the test can pull from it.
+    struct Capture(Option<SlotSource>);
+
+    impl Backend for Capture {
+        fn start(&mut self, source: SlotSource) -> Result<(), Box<dyn Error>> {
+            self.0 = Some(source);
+            Ok(())
+        }
+    }
+
+    /// A mono source at 8 kHz holding the same sample.
+    fn constant(sample: f32, len: usize) -> SlotInput {
+        Box::new(SamplesBuffer::new(1, 8000, vec![sample; len]))
+    }
+
+    /// A device that can't be opened.
+    struct Broken;
+
+    impl Backend for Broken {
+        fn start(&mut self, _: SlotSource) -> Result<(), Box<dyn Error>> {
+            Err("unplugged".into())
+        }
+    }
+
+    #[test]
+    fn plays_the_source_then_silence() {
+        let slot = Arc::new(OutputSlot::new());
+        let mut backend = Capture(None);
+        slot.attach(&mut backend).unwrap();
+        slot.set_source_after(constant(0.5, 3), Duration::ZERO);
+        let mut source = backend.0.unwrap();
+        // The chunk read before the source was set is silent.
+        assert!(source.by_ref().take(CHUNK).all(|sample| sample == 0.0));
+        assert_eq!(source.by_ref().take(2).collect::<Vec<_>>(), [0.5; 2]);
+        assert_eq!((source.channels(), source.sample_rate()), (1, 8000));
+        assert_eq!(source.next(), Some(0.5));
+        assert!(source.take(CHUNK).all(|sample| sample == 0.0));
+    }
+
+    #[test]
+    fn queued_sources_wait_for_the_current_one_or_the_timeout() {
+        let slot = Arc::new(OutputSlot::new());
+        let mut backend = Capture(None);
+        slot.attach(&mut backend).unwrap();
+        let mut source = backend.0.unwrap();
+        slot.set_source_after(constant(0.5, CHUNK + 10), Duration::ZERO);
+        source.by_ref().take(CHUNK).count();
+        slot.set_source_after(constant(0.25, 10), Duration::from_secs(60));
+        let played: Vec<f32> = source.by_ref().take(4 * CHUNK).collect();
+        let next = played.iter().position(|sample| *sample == 0.25).unwrap();
+        assert_eq!(
+            played[..next]
+                .iter()
+                .filter(|sample| **sample == 0.5)
+                .count(),
+            CHUNK + 10
+        );
+
+        // A source that doesn't end in time is cut.
+        slot.set_source_after(constant(0.5, 100 * CHUNK), Duration::ZERO);
+        assert!(source.by_ref().take(3 * CHUNK).any(|sample| sample == 0.5));
+        slot.set_source_after(constant(0.25, 10), Duration::ZERO);
+        assert!(source.take(3 * CHUNK).any(|sample| sample == 0.25));
+    }
+
+    #[test]
+    fn switching_stops_the_old_device_only_once_the_new_one_runs() {
+        let slot = Arc::new(OutputSlot::new());
+        let mut old = Capture(None);
+        slot.attach(&mut old).unwrap();
+        let mut old = old.0.unwrap();
+
+        assert!(slot.attach(&mut Broken).is_err());
+        assert_eq!(old.by_ref().take(CHUNK + 1).count(), CHUNK + 1);
+
+        let mut new = Capture(None);
+        slot.attach(&mut new).unwrap();
+        slot.set_source_after(constant(0.5, CHUNK), Duration::ZERO);
+        // The old device finishes its chunk, then ends.
+        assert_eq!(old.count(), CHUNK - 1);
+        assert!(new.0.unwrap().any(|sample| sample == 0.5));
+    }
+}
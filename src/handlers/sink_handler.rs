@@ -1,100 +1,303 @@
-use rodio::{Decoder, OutputStreamHandle, Sink};
-use std::{fs::File, io::BufReader, path::PathBuf, sync::Mutex, time::Duration};
+use crate::{
+    data::{cue::resolve, files::is_stream},
+    handlers::{
+        dsp::{
+            ab_loop::{AbLoop, LoopControl, LoopRange},
+            equalizer::{EqControl, EqSource},
+            position::{NowPlaying, PositionTracker},
+            ramp::{Ramp, RampControl},
+            segment::Segment,
+            tap::{SampleTap, Tap},
+            time_stretch::{StretchControl, TimeStretch},
+            volume::{Volume, VolumeControl},
+        },
+        output::OutputSlot,
+        stream::{StreamInfo, StreamReader, StreamStatus},
+    },
+};
+use rodio::{Decoder, Sink, Source};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::Duration,
+};
 
-/// Encapsulates an audio sink and an output stream handle.
+/// Encapsulates an audio sink and the output slot it plays into.
 pub struct SinkHandler {
-    stream_handle: OutputStreamHandle,
+    output_slot: Arc<OutputSlot>,
     sink: Mutex<Option<Sink>>,
+    equalizer: Arc<EqControl>,
+    stretch: Arc<StretchControl>,
+    volume: Arc<VolumeControl>,
+    /// The ramps of the current sink; each sink gets its own, so one can fade away while the next starts.
+    ramp: Mutex<Arc<RampControl>>,
+    ramp_length: Mutex<Duration>,
+    tap: Arc<SampleTap>,
+    resample_speed: Mutex<f32>,
+    now_playing: Arc<NowPlaying>,
+    ab_loop: Arc<LoopControl>,
+    streams: Mutex<HashMap<PathBuf, Arc<StreamInfo>>>,
+    error: Mutex<Option<String>>,
 }
 
 impl SinkHandler {
-    pub fn new(stream_handle: OutputStreamHandle) -> Self {
+    pub fn new(output_slot: Arc<OutputSlot>, equalizer: Arc<EqControl>) -> Self {
         Self {
-            stream_handle,
+            output_slot,
             sink: Mutex::new(None),
+            equalizer,
+            stretch: Arc::new(StretchControl::new(1.0)),
+            volume: Arc::new(VolumeControl::new(1.0)),
+            ramp: Mutex::new(Arc::new(RampControl::new(Duration::ZERO))),
+            ramp_length: Mutex::new(Duration::ZERO),
+            tap: Arc::new(SampleTap::new()),
+            resample_speed: Mutex::new(1.0),
+            now_playing: Arc::new(NowPlaying::new()),
+            ab_loop: Arc::new(LoopControl::new()),
+            streams: Mutex::new(HashMap::new()),
+            error: Mutex::new(None),
         }
     }
 
-    /// Plays the given file and sets its volume.
-    pub fn play_file(&self, path: PathBuf, vol: i16) {
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
-        let source = Decoder::new(reader).unwrap();
+    /// Decodes a file and runs it through the playback chain: position tracking, the A-B loop, ReplayGain, the equalizer, time stretching, the visualizer tap, the volume, then the transition ramps.
+    /// Tracks of CUE sheets are played from their range of the underlying file, URLs are received as streams.
+    fn build_source(
+        &self,
+        path: PathBuf,
+        gain: f32,
+        ramp: &Arc<RampControl>,
+    ) -> Result<impl Source<Item = f32> + Send + use<>, Box<dyn Error>> {
+        let (source, start, end) = match is_stream(&path) {
+            true => (self.open_stream(&path)?, Duration::ZERO, None),
+            false => {
+                let (file, start, end) = match resolve(&path) {
+                    Some((_, track)) => (track.file, track.start, track.end),
+                    None => (path.clone(), Duration::ZERO, None),
+                };
+                let reader = BufReader::new(File::open(file)?);
+                let source: Box<dyn Source<Item = f32> + Send> =
+                    Box::new(Decoder::new(reader)?.convert_samples::<f32>());
+                (source, start, end)
+            }
+        };
+        let source = Segment::new(source, start, end)?;
+        let source = PositionTracker::new(source, Arc::clone(&self.now_playing), path.clone());
+        let source = AbLoop::new(source, Arc::clone(&self.ab_loop), path).amplify(gain);
+        let source = EqSource::new(source, Arc::clone(&self.equalizer));
+        let source = TimeStretch::new(source, Arc::clone(&self.stretch));
+        let source = Tap::new(source, Arc::clone(&self.tap));
+        let source = Volume::new(source, Arc::clone(&self.volume));
+        Ok(Ramp::new(source, Arc::clone(ramp)))
+    }
+
+    /// Connects to a stream and decodes it once enough has been received to tell its format.
+    fn open_stream(
+        &self,
+        url: &Path,
+    ) -> Result<Box<dyn Source<Item = f32> + Send>, Box<dyn Error>> {
+        let info = Arc::new(StreamInfo::new());
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(url.to_path_buf(), Arc::clone(&info));
+        let reader = StreamReader::open(&url.to_string_lossy(), Arc::clone(&info));
+        match Decoder::new(reader) {
+            Ok(decoder) => Ok(Box::new(decoder.convert_samples::<f32>())),
+            Err(err) => Err(match info.status() {
+                StreamStatus::Failed(reason) => reason.into(),
+                _ => err.into(),
+            }),
+        }
+    }
+
+    /// Returns what is known about the stream at the URL, if it's been opened.
+    pub fn stream_info(&self, url: &Path) -> Option<Arc<StreamInfo>> {
+        self.streams.lock().unwrap().get(url).cloned()
+    }
+
+    /// Returns the last error that kept a track from playing or seeking, forgetting it.
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take().or_else(|| {
+            self.ramp()
+                .take_seek_error()
+                .map(|err| format!("can't seek: {}", err))
+        })
+    }
+
+    /// Returns the ramps of the current sink.
+    fn ramp(&self) -> Arc<RampControl> {
+        Arc::clone(&self.ramp.lock().unwrap())
+    }
+
+    /// Fades the sink out and lets it end by itself, without waiting for it.
+    /// Returns how long to wait at most before playing something else.
+    fn fade_away(&self, sink: Sink) -> Duration {
+        let ramp = self.ramp();
+        ramp.stop();
+        sink.detach();
+        // The output may be slowed down, or may have stopped pulling samples.
+        ramp.length() * 4 + Duration::from_millis(100)
+    }
+
+    /// Remembers why a track couldn't be played.
+    fn report(&self, path: &Path, err: Box<dyn Error>) {
+        *self.error.lock().unwrap() =
+            Some(format!("couldn't play {}: {}", path.to_string_lossy(), err));
+    }
+
+    /// Plays the given file. The ReplayGain factor is applied to the source itself.
+    /// The previous sink fades out first, on the audio side.
+    /// If it can't be opened, the previous sink is stopped and the error is kept for `take_error`.
+    pub fn play_file(&self, path: PathBuf, gain: f32) {
+        self.streams.lock().unwrap().clear();
+        self.now_playing.reset();
+        let ramp = Arc::new(RampControl::new(*self.ramp_length.lock().unwrap()));
+        let source = match self.build_source(path.clone(), gain, &ramp) {
+            Ok(source) => source,
+            Err(err) => {
+                if let Some(sink) = self.sink.lock().unwrap().take() {
+                    self.fade_away(sink);
+                }
+                self.report(&path, err);
+                return;
+            }
+        };
 
-        let sink = Sink::try_new(&self.stream_handle).unwrap();
+        let mut sink_guard = self.sink.lock().unwrap();
+        let (sink, output) = Sink::new_idle();
+        sink.set_speed(*self.resample_speed.lock().unwrap());
         sink.append(source);
+        // Waits for a sink faded away by `clear` too.
+        let timeout = match sink_guard.take() {
+            Some(previous) => self.fade_away(previous),
+            None => self.ramp().length() * 4 + Duration::from_millis(100),
+        };
+        self.output_slot.set_source_after(Box::new(output), timeout);
+        *self.ramp.lock().unwrap() = ramp;
 
         // Store the sink in the player's state
-        *self.sink.lock().unwrap() = Some(sink);
-
-        self.set_volume(vol);
+        *sink_guard = Some(sink);
     }
 
-    /// Toggles play and pause.
+    /// Toggles play and pause. The ramp holds the source once it has faded out.
     pub fn toggle_play_pause(&self) {
         let sink_guard = self.sink.lock().unwrap();
-        if let Some(ref sink) = *sink_guard {
-            match sink.is_paused() {
-                true => {
-                    sink.play();
-                }
-                false => {
-                    sink.pause();
-                }
+        if sink_guard.is_some() {
+            let ramp = self.ramp();
+            match ramp.is_paused() {
+                true => ramp.resume(),
+                false => ramp.pause(),
             }
         }
     }
 
-    /// Sets the playback volume.
-    pub fn set_volume(&self, mag: i16) {
+    /// Sets the track after which playback holds at the start of the next one, or lets it move on.
+    pub fn stop_after(&self, path: Option<PathBuf>) {
+        *self.now_playing.stop_after.lock().unwrap() = path;
+    }
+
+    /// Returns true while the next track is held at its start.
+    pub fn is_held(&self) -> bool {
+        self.now_playing.held.load(Ordering::Relaxed)
+    }
+
+    /// Lets a held track play.
+    pub fn release(&self) {
+        self.now_playing.held.store(false, Ordering::Relaxed);
+    }
+
+    /// Sets the range of a track to repeat, or stops repeating.
+    pub fn set_loop(&self, range: Option<LoopRange>) {
+        self.ab_loop.set(range);
+    }
+
+    /// Returns the latest samples played, mixed to mono, and their sample rate.
+    pub fn latest_samples(&self, len: usize) -> (Vec<f32>, u32) {
+        self.tap.latest(len)
+    }
+
+    /// Sets the length of the volume ramps on pause, resume, stop, skip and seek.
+    pub fn set_ramp_length(&self, length: Duration) {
+        *self.ramp_length.lock().unwrap() = length;
+        self.ramp().set_length(length);
+    }
+
+    /// Sets the playback volume as an amplitude factor.
+    pub fn set_volume(&self, gain: f32) {
+        self.volume.set(gain);
+    }
+
+    /// Sets the playback speed, either by resampling (pitch follows speed) or by time stretching (pitch is kept).
+    pub fn set_speed(&self, speed: f32, preserve_pitch: bool) {
+        let (resample, stretch) = match preserve_pitch {
+            true => (1.0, speed),
+            false => (speed, 1.0),
+        };
+        self.stretch.set(stretch);
+        *self.resample_speed.lock().unwrap() = resample;
+
         let sink_guard = self.sink.lock().unwrap();
         if let Some(ref sink) = *sink_guard {
-            sink.set_volume((mag as f32) / 100.0);
+            sink.set_speed(resample);
         }
     }
 
     /// Gets the sink's position in seconds.
     pub fn sink_pos(&self) -> u64 {
-        let sink_guard = self.sink.lock().unwrap();
-        match &*sink_guard {
-            Some(sink) => sink.get_pos().as_secs(),
-            None => Duration::new(0, 0).as_secs(),
-        }
+        (self.sink_pos_millis() / 1000) as u64
     }
 
-    /// Gets the sink's position in milliseconds.
+    /// Gets the position of the current track in milliseconds, independent of the playback speed.
     pub fn sink_pos_millis(&self) -> u128 {
         let sink_guard = self.sink.lock().unwrap();
         match &*sink_guard {
-            Some(sink) => sink.get_pos().as_millis(),
-            None => Duration::new(0, 0).as_millis(),
+            Some(_) => self.now_playing.millis.load(Ordering::Relaxed) as u128,
+            None => 0,
+        }
+    }
+
+    /// Returns the path of the source being played, once it has produced its first sample.
+    pub fn now_playing(&self) -> Option<PathBuf> {
+        self.now_playing.path.lock().unwrap().clone()
+    }
+
+    /// Forgets the source being played, so a stale path isn't reported while a new file is being opened.
+    pub fn forget_now_playing(&self) {
+        self.now_playing.reset();
+    }
+
+    /// Seeks the current source once it has faded out. Errors are kept for `take_error`.
+    pub fn seek(&self, pos: Duration) {
+        if self.sink.lock().unwrap().is_some() {
+            self.ramp().seek(pos);
         }
     }
 
     /// Appends source to sink.
-    pub fn append_to_sink(&self, path: PathBuf, vol: i16) {
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
-        let source = Decoder::new(reader).unwrap();
+    pub fn append_to_sink(&self, path: PathBuf, gain: f32) {
+        let source = match self.build_source(path.clone(), gain, &self.ramp()) {
+            Ok(source) => source,
+            Err(err) => return self.report(&path, err),
+        };
 
+        let sink_guard = self.sink.lock().unwrap();
+        if let Some(ref sink) = *sink_guard
+            && sink.len() >= 1
         {
-            let sink_guard = self.sink.lock().unwrap();
-            if let Some(ref sink) = *sink_guard {
-                if sink.len() >= 1 {
-                    sink.append(source);
-                }
-            }
-        } // Lock is dropped here. Prevents deadlocks.
-
-        self.set_volume(vol);
+            sink.append(source);
+        }
     }
 
-    /// Removes all currently loaded Sources from the Sink, and pauses it.
+    /// Removes all currently loaded Sources, fading out the one being played.
     pub fn clear(&self) {
-        let sink_guard = self.sink.lock().unwrap();
-        if let Some(ref sink) = *sink_guard {
-            sink.clear();
+        if let Some(sink) = self.sink.lock().unwrap().take() {
+            self.fade_away(sink);
         }
+        self.now_playing.reset();
     }
 
     /// Returns how many elements are in the sink.
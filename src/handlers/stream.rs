@@ -0,0 +1,532 @@
+use crate::data::http;
+use std::{
+    collections::VecDeque,
+    error::Error,
+    io::{self, Read, Seek, SeekFrom},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Bytes read ahead of playback before the network thread waits.
+const BUFFER_LIMIT: usize = 512 * 1024;
+/// Reconnection attempts in a row before a stream is given up.
+const RECONNECT_ATTEMPTS: u32 = 5;
+/// Delay before the first reconnection attempt, doubled for each further one.
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// State of the connection behind a stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamStatus {
+    Connecting,
+    Buffering,
+    Playing,
+    Reconnecting(u32),
+    Failed(String),
+    Ended,
+}
+
+/// What is known about a stream, updated live by its network thread.
+pub struct StreamInfo {
+    pub status: Mutex<StreamStatus>,
+    /// The station name from the icy-name header.
+    pub station: Mutex<Option<String>>,
+    /// The title from the latest in-band ICY metadata.
+    pub title: Mutex<Option<String>>,
+}
+
+impl StreamInfo {
+    pub fn new() -> Self {
+        Self {
+            status: Mutex::new(StreamStatus::Connecting),
+            station: Mutex::new(None),
+            title: Mutex::new(None),
+        }
+    }
+
+    /// Returns the current status.
+    pub fn status(&self) -> StreamStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Returns the latest title, e.g. "Artist - Song".
+    pub fn title(&self) -> Option<String> {
+        self.title.lock().unwrap().clone()
+    }
+
+    /// Returns the station name.
+    pub fn station(&self) -> Option<String> {
+        self.station.lock().unwrap().clone()
+    }
+
+    fn set_status(&self, status: StreamStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+/// Bytes received but not played yet.
+struct Buffer {
+    data: VecDeque<u8>,
+    /// The network thread has stopped for good.
+    done: bool,
+    /// The reader has been dropped.
+    closed: bool,
+}
+
+/// How much of a file with a known length was received, so a reconnection carries on where it stopped.
+struct Resume {
+    /// Audio bytes received over all connections.
+    received: u64,
+    /// The server takes byte ranges, so the rest can be asked for.
+    ranges: bool,
+}
+
+struct Shared {
+    info: Arc<StreamInfo>,
+    buffer: Mutex<Buffer>,
+    changed: Condvar,
+}
+
+/// Audio bytes of an http stream with the ICY metadata stripped out, for the decoder.
+/// A network thread fills the buffer and reconnects when the connection drops.
+pub struct StreamReader {
+    shared: Arc<Shared>,
+    position: u64,
+}
+
+impl StreamReader {
+    /// Starts receiving the stream on a new thread.
+    pub fn open(url: &str, info: Arc<StreamInfo>) -> Self {
+        let shared = Arc::new(Shared {
+            info,
+            buffer: Mutex::new(Buffer {
+                data: VecDeque::new(),
+                done: false,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let url = url.to_string();
+        let network = Arc::clone(&shared);
+        thread::spawn(move || receive(&url, &network));
+        Self {
+            shared,
+            position: 0,
+        }
+    }
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut buffer = self.shared.buffer.lock().unwrap();
+        if buffer.data.is_empty() && !buffer.done {
+            let info = &self.shared.info;
+            if info.status() == StreamStatus::Playing {
+                info.set_status(StreamStatus::Buffering);
+            }
+            while buffer.data.is_empty() && !buffer.done {
+                buffer = self.shared.changed.wait(buffer).unwrap();
+            }
+        }
+        let len = buf.len().min(buffer.data.len());
+        for (byte, value) in buf.iter_mut().zip(buffer.data.drain(..len)) {
+            *byte = value;
+        }
+        self.shared.changed.notify_all();
+        drop(buffer);
+
+        if len > 0 && self.shared.info.status() == StreamStatus::Buffering {
+            self.shared.info.set_status(StreamStatus::Playing);
+        }
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+/// Streams can't seek; only the current position can be queried or kept, which the decoder does when probing.
+impl Seek for StreamReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            SeekFrom::Start(position) if position == self.position => Ok(self.position),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "streams can't seek",
+            )),
+        }
+    }
+}
+
+impl Drop for StreamReader {
+    fn drop(&mut self) {
+        self.shared.buffer.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Receives the stream until it ends, the reader is dropped, or reconnecting keeps failing.
+/// A stream that can't be opened at all fails right away.
+fn receive(url: &str, shared: &Shared) {
+    let info = &shared.info;
+    let mut failures = 0;
+    let mut connected = false;
+    let mut resume = None;
+    loop {
+        let result = connect(url, shared, &mut failures, &mut connected, &mut resume);
+        if shared.buffer.lock().unwrap().closed {
+            return;
+        }
+        let (finished, error) = match result {
+            Ok(finished) => (finished, None),
+            Err(err) => (false, Some(err.to_string())),
+        };
+        if finished {
+            info.set_status(StreamStatus::Ended);
+            break;
+        }
+        failures += 1;
+        if !connected || failures > RECONNECT_ATTEMPTS {
+            info.set_status(StreamStatus::Failed(
+                error.unwrap_or_else(|| "connection lost".to_string()),
+            ));
+            break;
+        }
+        info.set_status(StreamStatus::Reconnecting(failures));
+        thread::sleep(RECONNECT_DELAY * 2u32.pow(failures - 1));
+    }
+    shared.buffer.lock().unwrap().done = true;
+    shared.changed.notify_all();
+}
+
+/// Runs one connection, moving the audio into the buffer.
+/// Returns true if the stream is finished, i.e. a file with a known length was received in full.
+///
+/// Files with a known length pick up where the last connection stopped: the rest is asked for with a Range header
+/// when the server takes ranges, and the bytes already received are skipped when it sends the file from the start.
+fn connect(
+    url: &str,
+    shared: &Shared,
+    failures: &mut u32,
+    connected: &mut bool,
+    resume: &mut Option<Resume>,
+) -> Result<bool, Box<dyn Error>> {
+    let range = resume
+        .as_ref()
+        .filter(|resume| resume.ranges)
+        .map(|resume| format!("bytes={}-", resume.received));
+    let mut headers = vec![("Icy-MetaData", "1")];
+    if let Some(range) = &range {
+        headers.push(("Range", range));
+    }
+    let response = http::get(url, &headers)?;
+    *connected = true;
+    let length = response.content_length();
+    let metaint: usize = response
+        .header("icy-metaint")
+        .and_then(|metaint| metaint.parse().ok())
+        .unwrap_or(0);
+    let mut skip = match (resume.as_ref(), response.status) {
+        (None, _) => 0,
+        (Some(resume), 206) => {
+            let start = response
+                .header("content-range")
+                .and_then(range_start)
+                .ok_or("malformed content range")?;
+            match start <= resume.received {
+                true => resume.received - start,
+                false => return Err("the server skipped part of the file".into()),
+            }
+        }
+        (Some(resume), _) => resume.received,
+    };
+    if resume.is_none() && metaint == 0 && length.is_some() {
+        *resume = Some(Resume {
+            received: 0,
+            ranges: response
+                .header("accept-ranges")
+                .is_some_and(|ranges| ranges.eq_ignore_ascii_case("bytes")),
+        });
+    }
+    if let Some(name) = response.header("icy-name").filter(|name| !name.is_empty()) {
+        *shared.info.station.lock().unwrap() = Some(name.to_string());
+    }
+    if shared.info.status() != StreamStatus::Playing {
+        shared.info.set_status(StreamStatus::Buffering);
+    }
+
+    let mut body = response.body;
+    let mut received = 0;
+    let mut until_metadata = metaint;
+    let mut chunk = [0; 8192];
+    loop {
+        let want = match metaint {
+            0 => chunk.len(),
+            _ => chunk.len().min(until_metadata),
+        };
+        let read = body.read(&mut chunk[..want])?;
+        if read == 0 {
+            return Ok(metaint == 0 && length.is_some_and(|length| received >= length));
+        }
+        received += read as u64;
+        *failures = 0;
+        let skipped = skip.min(read as u64) as usize;
+        skip -= skipped as u64;
+        if !push(shared, &chunk[skipped..read]) {
+            return Ok(true);
+        }
+        if let Some(resume) = resume {
+            resume.received += (read - skipped) as u64;
+        }
+        if metaint == 0 && length.is_some_and(|length| received >= length) {
+            return Ok(true);
+        }
+
+        if metaint > 0 {
+            until_metadata -= read;
+            if until_metadata == 0 {
+                let mut len = [0];
+                body.read_exact(&mut len)?;
+                let mut metadata = vec![0; len[0] as usize * 16];
+                body.read_exact(&mut metadata)?;
+                if let Some(title) = stream_title(&metadata) {
+                    *shared.info.title.lock().unwrap() = Some(title);
+                }
+                until_metadata = metaint;
+            }
+        }
+    }
+}
+
+/// Returns the first byte of a Content-Range header, e.g. 100 for "bytes 100-999/1000".
+fn range_start(content_range: &str) -> Option<u64> {
+    let (unit, range) = content_range.trim().split_once(' ')?;
+    match unit.eq_ignore_ascii_case("bytes") {
+        true => range.split_once('-')?.0.trim().parse().ok(),
+        false => None,
+    }
+}
+
+/// Adds bytes to the buffer, waiting while it's full. Returns false if the reader is gone.
+fn push(shared: &Shared, bytes: &[u8]) -> bool {
+    let mut buffer = shared.buffer.lock().unwrap();
+    while buffer.data.len() >= BUFFER_LIMIT && !buffer.closed {
+        buffer = shared.changed.wait(buffer).unwrap();
+    }
+    if buffer.closed {
+        return false;
+    }
+    buffer.data.extend(bytes);
+    shared.changed.notify_all();
+    true
+}
+
+/// Extracts the title from an ICY metadata block, e.g. "StreamTitle='Artist - Song';StreamUrl='';".
+pub fn stream_title(metadata: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(metadata);
+    let text = text.trim_end_matches('\0');
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+    let end = rest.find("';").unwrap_or(rest.trim_end_matches('\'').len());
+    let title = rest[..end].trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::data::{files::is_stream, http::tests::serve};
+    use std::{
+        io::Write,
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Instant,
+    };
+
+    /// Interleaves audio with an ICY metadata block every `metaint` bytes.
+    pub fn with_metadata(audio: &[u8], metaint: usize, title: &str) -> Vec<u8> {
+        let text = format!("StreamTitle='{}';", title);
+        let blocks = text.len().div_ceil(16);
+        let mut metadata = vec![blocks as u8];
+        metadata.extend(text.as_bytes());
+        metadata.resize(1 + blocks * 16, 0);
+
+        let mut body = Vec::new();
+        for chunk in audio.chunks(metaint) {
+            body.extend(chunk);
+            if chunk.len() == metaint {
+                body.extend(&metadata);
+            }
+        }
+        body
+    }
+
+    /// Serves the bytes as a live ICY stream, i.e. without a length, cutting the first connections short.
+    pub fn serve_icy(audio: Vec<u8>, metaint: usize, title: &'static str, drops: usize) -> String {
+        let connections = AtomicUsize::new(0);
+        serve(move |_, stream| {
+            let body = with_metadata(&audio, metaint, title);
+            let body = match connections.fetch_add(1, Ordering::SeqCst) < drops {
+                true => &body[..body.len() / 3],
+                false => &body[..],
+            };
+            let head = format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: audio/wav\r\nicy-name: Test Radio\r\nicy-metaint: {}\r\n\r\n",
+                metaint
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(body);
+        })
+    }
+
+    fn read_all(reader: &mut StreamReader) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn parses_stream_titles() {
+        assert_eq!(
+            stream_title(b"StreamTitle='Artist - Song';StreamUrl='';\0\0"),
+            Some("Artist - Song".to_string())
+        );
+        assert_eq!(stream_title(b"StreamTitle='';\0"), None);
+        assert_eq!(stream_title(b"StreamUrl='x';"), None);
+        assert!(is_stream(Path::new("HTTP://radio.example/live")));
+        assert!(!is_stream(Path::new("/music/http.mp3")));
+    }
+
+    #[test]
+    fn strips_metadata_and_reports_title() {
+        let audio: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+        let url = serve_icy(audio.clone(), 1000, "Artist - Song", 0);
+        let info = Arc::new(StreamInfo::new());
+        let mut reader = StreamReader::open(&url, Arc::clone(&info));
+
+        let start = Instant::now();
+        let mut received: Vec<u8> = Vec::new();
+        let mut chunk = [0; 4096];
+        while received.len() < audio.len() && start.elapsed() < Duration::from_secs(5) {
+            let read = reader.read(&mut chunk).unwrap();
+            received.extend(&chunk[..read]);
+        }
+        assert_eq!(&received[..audio.len()], &audio[..]);
+        assert_eq!(info.title(), Some("Artist - Song".to_string()));
+        assert_eq!(info.station(), Some("Test Radio".to_string()));
+    }
+
+    #[test]
+    fn reconnects_after_a_dropped_connection() {
+        let audio: Vec<u8> = (0..9_000).map(|i| (i % 7) as u8).collect();
+        let url = serve_icy(audio.clone(), 500, "Song", 1);
+        let info = Arc::new(StreamInfo::new());
+        let mut reader = StreamReader::open(&url, Arc::clone(&info));
+
+        let mut received: Vec<u8> = Vec::new();
+        let mut chunk = [0; 4096];
+        while received.len() < 2 * audio.len() {
+            let read = reader.read(&mut chunk).unwrap();
+            assert!(read > 0, "stream ended early: {:?}", info.status());
+            received.extend(&chunk[..read]);
+        }
+        // The first connection delivered part of the audio, the second one starts over.
+        let cut = (1..audio.len())
+            .find(|&cut| received[cut..cut + audio.len()] == audio[..])
+            .unwrap();
+        assert_eq!(&received[..cut], &audio[..cut]);
+    }
+
+    /// Serves the bytes as a file with a length, cutting the first connection short, and counts the connections.
+    /// With `ranges` the server takes byte ranges and records the ones asked for.
+    fn serve_file(
+        audio: Vec<u8>,
+        ranges: bool,
+        drops: usize,
+    ) -> (String, Arc<AtomicUsize>, Arc<Mutex<Vec<String>>>) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let (count, log) = (Arc::clone(&connections), Arc::clone(&asked));
+        let url = serve(move |head, stream| {
+            let dropped = count.fetch_add(1, Ordering::SeqCst) < drops;
+            let start = head
+                .lines()
+                .find_map(|line| line.strip_prefix("Range: bytes="))
+                .filter(|_| ranges)
+                .map(|range| {
+                    log.lock().unwrap().push(range.to_string());
+                    range.trim_end_matches('-').parse::<usize>().unwrap()
+                });
+            let body = &audio[start.unwrap_or(0)..];
+            let mut head = match start {
+                Some(start) => format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                    start,
+                    audio.len() - 1,
+                    audio.len()
+                ),
+                None => "HTTP/1.1 200 OK\r\n".to_string(),
+            };
+            if ranges {
+                head.push_str("Accept-Ranges: bytes\r\n");
+            }
+            head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+            let _ = stream.write_all(head.as_bytes());
+            let _ = match dropped {
+                true => stream.write_all(&body[..body.len() / 3]),
+                false => stream.write_all(body),
+            };
+        });
+        (url, connections, asked)
+    }
+
+    #[test]
+    fn files_resume_with_a_range_after_a_drop() {
+        let audio: Vec<u8> = (0..30_000).map(|i| (i % 13) as u8).collect();
+        let (url, connections, asked) = serve_file(audio.clone(), true, 1);
+        let info = Arc::new(StreamInfo::new());
+        let mut reader = StreamReader::open(&url, Arc::clone(&info));
+        assert_eq!(read_all(&mut reader), audio);
+        assert_eq!(info.status(), StreamStatus::Ended);
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(*asked.lock().unwrap(), ["10000-"]);
+    }
+
+    #[test]
+    fn files_sent_again_from_the_start_skip_what_was_received() {
+        let audio: Vec<u8> = (0..30_000).map(|i| (i % 11) as u8).collect();
+        let (url, connections, asked) = serve_file(audio.clone(), false, 1);
+        let info = Arc::new(StreamInfo::new());
+        let mut reader = StreamReader::open(&url, Arc::clone(&info));
+        assert_eq!(read_all(&mut reader), audio);
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert!(asked.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn files_read_in_full_are_not_reconnected() {
+        let audio: Vec<u8> = (0..30_000).map(|i| (i % 5) as u8).collect();
+        let (url, connections, _) = serve_file(audio.clone(), true, 0);
+        let info = Arc::new(StreamInfo::new());
+        let mut reader = StreamReader::open(&url, Arc::clone(&info));
+        assert_eq!(read_all(&mut reader), audio);
+        assert_eq!(info.status(), StreamStatus::Ended);
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn content_ranges_give_their_start() {
+        assert_eq!(range_start("bytes 100-999/1000"), Some(100));
+        assert_eq!(range_start("bytes 0-0/*"), Some(0));
+        assert_eq!(range_start("items 1-2/3"), None);
+        assert_eq!(range_start("bytes */1000"), None);
+    }
+
+    #[test]
+    fn gives_up_when_the_server_is_gone() {
+        let url = "http://127.0.0.1:1/live";
+        let info = Arc::new(StreamInfo::new());
+        let mut reader = StreamReader::open(url, Arc::clone(&info));
+        assert!(read_all(&mut reader).is_empty());
+        assert!(matches!(info.status(), StreamStatus::Failed(_)));
+    }
+}
@@ -1,11 +1,12 @@
 use std::error::Error;
 
-use crate::tui::render::app::run_tui;
-
+mod cli;
 mod data;
 mod handlers;
+#[cfg(test)]
+mod test_files;
 mod tui;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    run_tui()
+    cli::run()
 }
@@ -0,0 +1,93 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::{
+    f32::consts::TAU,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A scratch directory for a test, removed with everything in it when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory named after the test, unique to this process.
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rmpr-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes interleaved samples between -1 and 1 as a 16-bit WAV file.
+pub fn write_wav(
+    path: &Path,
+    channels: u16,
+    sample_rate: u32,
+    samples: impl IntoIterator<Item = f32>,
+) {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(path, spec).unwrap();
+    for sample in samples {
+        writer
+            .write_sample((sample * i16::MAX as f32) as i16)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// Adds a RIFF INFO chunk with a title to a WAV file, before its samples.
+pub fn add_wav_title(path: &Path, title: &str) {
+    let mut bytes = fs::read(path).unwrap();
+    let data = bytes
+        .windows(4)
+        .position(|window| window == b"data")
+        .unwrap();
+    let samples = bytes.split_off(data);
+    let mut name = title.as_bytes().to_vec();
+    name.push(0);
+    if name.len() % 2 == 1 {
+        name.push(0);
+    }
+    let mut list = b"INFO".to_vec();
+    list.extend_from_slice(b"INAM");
+    list.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    list.extend_from_slice(&name);
+    bytes.extend_from_slice(b"LIST");
+    bytes.extend_from_slice(&(list.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&list);
+    bytes.extend_from_slice(&samples);
+    let riff = (bytes.len() - 8) as u32;
+    bytes[4..8].copy_from_slice(&riff.to_le_bytes());
+    fs::write(path, bytes).unwrap();
+}
+
+/// Writes a stereo 440 Hz tone at 44.1 kHz of the given length and amplitude.
+pub fn write_tone(path: &Path, secs: f32, amplitude: f32) {
+    let frames = (secs * 44100.0) as usize;
+    write_wav(
+        path,
+        2,
+        44100,
+        (0..frames * 2).map(|n| (TAU * 440.0 * (n / 2) as f32 / 44100.0).sin() * amplitude),
+    );
+}
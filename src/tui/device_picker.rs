@@ -0,0 +1,32 @@
+use crate::handlers::backend::list_devices;
+
+/// Encapsulates the output device picker state.
+pub struct DevicePicker {
+    pub devices: Vec<String>,
+    pub selected: usize,
+}
+
+impl DevicePicker {
+    /// Enumerates the devices and puts the cursor on the current one.
+    pub fn new(current: &str) -> Self {
+        let devices = list_devices();
+        let selected = devices
+            .iter()
+            .position(|device| device == current)
+            .unwrap_or(0);
+        Self { devices, selected }
+    }
+
+    /// Moves the cursor up one element or goes to the bottom if at the top.
+    pub fn navigate_up(&mut self) {
+        self.selected = match self.selected {
+            0 => self.devices.len() - 1,
+            selected => selected - 1,
+        };
+    }
+
+    /// Moves the cursor down one element or goes to the top if at the bottom.
+    pub fn navigate_down(&mut self) {
+        self.selected = (self.selected + 1) % self.devices.len();
+    }
+}
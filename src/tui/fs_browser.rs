@@ -1,12 +1,23 @@
 use crate::data::{
-    config::{ConfigData, load_config},
+    config::{ConfigData, StatColumn, load_config},
+    cue::sheet_tracks,
+    files::{collect_playable, is_playable},
+    library::{Library, TrackStats},
     metadata::file_metadata::FileMetadata,
+    playlist::is_playlist,
 };
 use ratatui::{
     style::{Color, Style},
     widgets::{ListItem, ListState},
 };
-use std::{collections::HashMap, fs::read_dir, io, path::PathBuf, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{metadata, read_dir},
+    io,
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 /// Encapsulates file system browsing state and behavior.
 pub struct FileBrowser {
@@ -16,6 +27,16 @@ pub struct FileBrowser {
     pub list_state: ListState,
     pub sel_map: HashMap<PathBuf, usize>,
     pub entries: Vec<PathBuf>,
+    pub marked: HashSet<PathBuf>,
+    sheets: Option<SheetCache>,
+}
+
+/// The sheet tracks of a directory, kept until the directory changes so its files aren't opened on every refresh.
+struct SheetCache {
+    dir: PathBuf,
+    modified: Option<SystemTime>,
+    tracks: Vec<PathBuf>,
+    covered: Vec<PathBuf>,
 }
 
 impl FileBrowser {
@@ -31,57 +52,70 @@ impl FileBrowser {
             list_state,
             sel_map,
             entries: Vec::new(),
+            marked: HashSet::new(),
+            sheets: None,
         }
     }
 
     /// Refreshes the list of entries from the current directory.
     pub fn update_entries(&mut self) -> io::Result<()> {
         let mut directories = Vec::new();
+        let mut files = Vec::new();
         let mut metadata_list = Vec::new();
 
-        let playable_exts = ["flac", "mp3", "wav"];
+        for entry in read_dir(&self.current_dir)?.flatten() {
+            let path = entry.path();
+            if let Some(file_name) = path.file_name()
+                && file_name.to_string_lossy().starts_with('.')
+            {
+                continue;
+            }
 
-        for entry in read_dir(&self.current_dir)? {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if let Some(file_name) = path.file_name() {
-                    if file_name.to_string_lossy().starts_with('.') {
-                        continue;
-                    }
-                }
+            match path.is_dir() {
+                true => directories.push(path),
+                false => files.push(path),
+            }
+        }
 
-                match path.is_dir() {
-                    true => directories.push(path),
-                    false => {
-                        if let Some(ext) = path.extension() {
-                            if playable_exts
-                                .contains(&ext.to_string_lossy().to_ascii_lowercase().as_ref())
-                            {
-                                let mut file_data = FileMetadata::new();
-                                file_data.get_file_data(&path);
-                                let track_number = file_data.track_number.unwrap_or(0);
-                                let title = file_data
-                                    .title
-                                    .unwrap_or_else(|| path.to_string_lossy().to_string());
-
-                                metadata_list.push((track_number, title, path));
-                            }
-                        }
-                    }
+        // Files split by a CUE sheet are listed as the sheet's tracks.
+        let modified = metadata(&self.current_dir)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let sheets = match self.sheets.take() {
+            Some(sheets) if sheets.dir == self.current_dir && sheets.modified == modified => sheets,
+            _ => {
+                let (tracks, covered) = sheet_tracks(&files);
+                SheetCache {
+                    dir: self.current_dir.clone(),
+                    modified,
+                    tracks,
+                    covered,
                 }
             }
+        };
+        let playable = files.into_iter().filter(|path| {
+            (is_playable(path) || is_playlist(path)) && !sheets.covered.contains(path)
+        });
+        for path in playable.chain(sheets.tracks.iter().cloned()) {
+            let mut file_data = FileMetadata::new();
+            file_data.get_file_data(&path);
+            let track_number = file_data.track_number.unwrap_or(0);
+            let title = file_data
+                .title
+                .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+            metadata_list.push((track_number, title, path));
         }
 
+        self.sheets = Some(sheets);
+
         directories.sort();
         metadata_list.sort_by_key(|&(track_number, _, _)| track_number);
 
         let playable_files: Vec<PathBuf> =
             metadata_list.into_iter().map(|(_, _, path)| path).collect();
 
-        self.entries = directories
-            .into_iter()
-            .chain(playable_files.into_iter())
-            .collect();
+        self.entries = directories.into_iter().chain(playable_files).collect();
 
         self.list_state.select(match self.entries.is_empty() {
             true => None,
@@ -117,11 +151,11 @@ impl FileBrowser {
 
     /// Navigates into the selected directory, either setting the cursor to the saved position or 0.
     pub fn navigate_into(&mut self) {
-        if let Some(path) = self.entries.get(self.selected) {
-            if path.is_dir() {
-                self.current_dir = path.clone();
-                self.selected = *self.sel_map.get(&self.current_dir).unwrap_or(&0);
-            }
+        if let Some(path) = self.entries.get(self.selected)
+            && path.is_dir()
+        {
+            self.current_dir = path.clone();
+            self.selected = *self.sel_map.get(&self.current_dir).unwrap_or(&0);
         }
     }
 
@@ -155,8 +189,33 @@ impl FileBrowser {
         self.goto_top();
     }
 
+    /// Marks the selected file, or unmarks it if it's already marked, then moves down.
+    pub fn toggle_mark(&mut self) {
+        if let Some(path) = self.entries.get(self.selected)
+            && !path.is_dir()
+            && !self.marked.remove(path)
+        {
+            self.marked.insert(path.clone());
+        }
+        self.navigate_down();
+    }
+
+    /// Returns the files an operation should apply to: the marked files if any, otherwise every playable file under the cursor.
+    pub fn selection(&self) -> Vec<PathBuf> {
+        if !self.marked.is_empty() {
+            let mut marked: Vec<PathBuf> = self.marked.iter().cloned().collect();
+            marked.sort();
+            return marked;
+        }
+        match self.entries.get(self.selected) {
+            Some(path) => collect_playable(path),
+            None => Vec::new(),
+        }
+    }
+
     /// Lists all items in the directory; displays directories as their name, files as their metadata name, and both by their respective colors.
-    pub fn list_items(&self) -> Vec<ListItem> {
+    /// Files are followed by the listening statistics of the configured columns.
+    pub fn list_items(&self, library: &Library) -> Vec<ListItem<'_>> {
         let fs_directory = &self.config.colors.fs_directory;
         let fs_file = &self.config.colors.fs_file;
 
@@ -166,7 +225,7 @@ impl FileBrowser {
                 let display_name = match entry.is_dir() {
                     true => entry
                         .file_name()
-                        .map(|s| format!("[{}]", s.to_string_lossy().to_string()))
+                        .map(|s| format!("[{}]", s.to_string_lossy()))
                         .unwrap_or_else(|| "Unknown".to_string()),
                     false => {
                         let mut file_data = FileMetadata::new();
@@ -182,8 +241,52 @@ impl FileBrowser {
                     false => Style::default().fg(Color::from_str(fs_file).unwrap()),
                 };
 
+                let display_name = match self.marked.contains(entry) {
+                    true => format!("* {}", display_name),
+                    false => display_name,
+                };
+
+                let display_name = match entry.is_dir() || self.config.statistics.columns.is_empty()
+                {
+                    true => display_name,
+                    false => {
+                        let stats = library.stats(entry).cloned().unwrap_or_default();
+                        format!("{}  {}", display_name, self.stat_columns(&stats))
+                    }
+                };
+
                 ListItem::new(display_name).style(style)
             })
             .collect()
     }
+
+    /// Formats the configured statistics columns of a track.
+    fn stat_columns(&self, stats: &TrackStats) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        self.config
+            .statistics
+            .columns
+            .iter()
+            .map(|column| match column {
+                StatColumn::Rating => (1..=5)
+                    .map(|star| match star <= stats.rating {
+                        true => '★',
+                        false => '☆',
+                    })
+                    .collect(),
+                StatColumn::Plays => format!("{} plays", stats.plays),
+                StatColumn::Skips => format!("{} skips", stats.skips),
+                StatColumn::LastPlayed => match stats.last_played {
+                    Some(time) => match now.saturating_sub(time) / 86400 {
+                        0 => "played today".to_string(),
+                        days => format!("played {}d ago", days),
+                    },
+                    None => "never played".to_string(),
+                },
+            })
+            .collect::<Vec<String>>()
+            .join("  ")
+    }
 }
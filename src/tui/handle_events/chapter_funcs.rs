@@ -0,0 +1,206 @@
+use crate::{
+    data::metadata::chapters::{chapter_at, read_chapters},
+    tui::render::app::App,
+};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How often the position of a bookmarked file is saved while it plays.
+const BOOKMARK_INTERVAL: Duration = Duration::from_secs(10);
+/// Going to the previous chapter restarts the current one when it has played for longer than this.
+const RESTART_CHAPTER: Duration = Duration::from_secs(3);
+
+/// Formats a position as m:ss, or h:mm:ss for long files.
+pub fn format_position(pos: Duration) -> String {
+    let secs = pos.as_secs();
+    match secs >= 3600 {
+        true => format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+        false => format!("{}:{:02}", secs / 60, secs % 60),
+    }
+}
+
+impl App {
+    /// Returns true if the file is under one of the configured audiobook roots.
+    fn is_audiobook(&self, path: &Path) -> bool {
+        self.config
+            .directories
+            .audiobook_roots
+            .iter()
+            .any(|root| path.starts_with(root))
+    }
+
+    /// Returns true if the current file's position should be remembered: audiobooks, files with chapters, and long files.
+    /// Podcast episodes keep their position with the feed instead.
+    fn is_bookmarked(&self, path: &Path) -> bool {
+        let min_secs = self.config.playback.bookmark_min_minutes as f64 * 60.0;
+        self.is_audiobook(path)
+            || !self.chapters.is_empty()
+            || self
+                .data
+                .duration_as_secs
+                .is_some_and(|duration| duration >= min_secs)
+    }
+
+    /// Returns where the file was left: a podcast episode's saved position, otherwise its bookmark.
+    fn saved_position(&self, path: &Path) -> Option<Duration> {
+        match self.podcasts.episode(path) {
+            Some((_, episode)) => {
+                (episode.position > 0).then(|| Duration::from_millis(episode.position))
+            }
+            None => self.bookmarks.get(path),
+        }
+    }
+
+    /// Loads the chapters of the track that just started, and resumes it from its bookmark under an audiobook root or as a podcast episode.
+    pub fn enter_track(&mut self, path: &Path) {
+        self.chapters = read_chapters(path);
+        self.chapter_row = 0;
+        self.last_bookmark = Instant::now();
+        self.clear_loop();
+        self.arm_stop_after();
+        self.request_waveform(path);
+        self.request_cover(path);
+        self.load_lyrics(path);
+
+        let Some(pos) = self.saved_position(path) else {
+            return;
+        };
+        self.status = Some(
+            match self.is_audiobook(path) || self.podcasts.episode(path).is_some() {
+                true => {
+                    self.audio.seek(pos);
+                    format!("resumed at {}", format_position(pos))
+                }
+                false => format!("bookmark at {}, press b to resume", format_position(pos)),
+            },
+        );
+    }
+
+    /// Saves or clears the bookmark of the track being left and counts its play. Finished tracks start over next time, finished episodes are marked as played.
+    pub fn leave_track(&mut self, finished: bool) {
+        let Some(path) = self.started.take() else {
+            return;
+        };
+        self.record_listen(&path, finished);
+        let pos = self.audio.sink_pos_millis() as u64;
+        if let Some(episode) = self.podcasts.episode_mut(&path) {
+            match finished {
+                true => {
+                    episode.played = true;
+                    episode.position = 0;
+                }
+                false => episode.position = pos,
+            }
+            self.write_podcasts();
+            return;
+        }
+        match finished {
+            true => self.bookmarks.remove(&path),
+            false if self.is_bookmarked(&path) => self.bookmarks.set(
+                &path,
+                Duration::from_millis(self.audio.sink_pos_millis() as u64),
+            ),
+            false => return,
+        }
+        self.write_bookmarks();
+    }
+
+    /// Saves the current position every few seconds, so it survives a crash.
+    pub fn tick_bookmarks(&mut self) {
+        if self.audio.paused || self.last_bookmark.elapsed() < BOOKMARK_INTERVAL {
+            return;
+        }
+        self.last_bookmark = Instant::now();
+        let Some(path) = self.started.clone() else {
+            return;
+        };
+        if let Some(episode) = self.podcasts.episode_mut(&path) {
+            episode.position = self.audio.sink_pos_millis() as u64;
+            self.write_podcasts();
+        } else if self.is_bookmarked(&path) {
+            self.bookmarks.set(
+                &path,
+                Duration::from_millis(self.audio.sink_pos_millis() as u64),
+            );
+            self.write_bookmarks();
+        }
+    }
+
+    /// Writes the bookmarks file, reporting failures in the status line.
+    fn write_bookmarks(&mut self) {
+        if let Err(err) = self.bookmarks.save() {
+            self.status = Some(format!("can't save bookmarks: {}", err));
+        }
+    }
+
+    /// Seeks the current file to its bookmark.
+    pub fn resume_bookmark(&mut self) {
+        if let Some(path) = &self.started
+            && let Some(pos) = self.saved_position(path)
+        {
+            self.seek_to(pos);
+        }
+    }
+
+    /// Seeks to a position of the current file. Formats that can't seek are reported by `sync_stream`.
+    pub fn seek_to(&mut self, pos: Duration) {
+        self.audio.seek(pos);
+    }
+
+    /// Returns the index of the chapter being played.
+    pub fn current_chapter(&self) -> Option<usize> {
+        chapter_at(
+            &self.chapters,
+            Duration::from_millis(self.audio.sink_pos_millis() as u64),
+        )
+    }
+
+    /// Jumps to the start of the next chapter.
+    pub fn next_chapter(&mut self) {
+        let next = self.current_chapter().map_or(0, |current| current + 1);
+        if let Some(chapter) = self.chapters.get(next) {
+            self.seek_to(chapter.start);
+        }
+    }
+
+    /// Restarts the current chapter, or jumps to the previous one if it just started.
+    pub fn previous_chapter(&mut self) {
+        let Some(current) = self.current_chapter() else {
+            return;
+        };
+        let pos = Duration::from_millis(self.audio.sink_pos_millis() as u64);
+        let target = match pos.saturating_sub(self.chapters[current].start) > RESTART_CHAPTER {
+            true => current,
+            false => current.saturating_sub(1),
+        };
+        self.seek_to(self.chapters[target].start);
+    }
+
+    /// Moves the chapter list cursor up one element or goes to the bottom if at the top.
+    pub fn chapter_navigate_up(&mut self) {
+        if self.chapters.is_empty() {
+            return;
+        }
+        self.chapter_row = match self.chapter_row {
+            0 => self.chapters.len() - 1,
+            row => row - 1,
+        };
+    }
+
+    /// Moves the chapter list cursor down one element or goes to the top if at the bottom.
+    pub fn chapter_navigate_down(&mut self) {
+        if self.chapters.is_empty() {
+            return;
+        }
+        self.chapter_row = (self.chapter_row + 1) % self.chapters.len();
+    }
+
+    /// Jumps to the chapter under the cursor.
+    pub fn play_chapter(&mut self) {
+        if let Some(chapter) = self.chapters.get(self.chapter_row) {
+            self.seek_to(chapter.start);
+        }
+    }
+}
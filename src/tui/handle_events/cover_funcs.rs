@@ -0,0 +1,54 @@
+use crate::{
+    data::{
+        config::GraphicsProtocol,
+        cover::{Image, find_cover},
+        files::is_stream,
+    },
+    tui::render::app::App,
+};
+use std::{path::Path, thread};
+
+/// Covers are kept at most this many pixels wide and high, more than any terminal panel needs.
+const COVER_SIZE: usize = 512;
+
+impl App {
+    /// Looks for the cover of a track on a new thread, unless cover art is off.
+    pub fn request_cover(&mut self, path: &Path) {
+        if self.graphics == GraphicsProtocol::Off || is_stream(path) {
+            self.cover = None;
+            return;
+        }
+        if self.cover.as_ref().is_some_and(|(known, _)| known == path) {
+            return;
+        }
+        self.cover = Some((path.to_path_buf(), None));
+        let path = path.to_path_buf();
+        let tx = self.cover_tx.clone();
+        thread::spawn(move || {
+            let cover = find_cover(&path).map(|image| {
+                match image.width > COVER_SIZE || image.height > COVER_SIZE {
+                    true => image.fit(COVER_SIZE, COVER_SIZE),
+                    false => image,
+                }
+            });
+            let _ = tx.send((path, cover));
+        });
+    }
+
+    /// Keeps the cover found in the background, if it's still for the track being played.
+    pub fn poll_covers(&mut self) {
+        for (path, cover) in self.cover_rx.try_iter() {
+            if self.cover.as_ref().is_some_and(|(known, _)| *known == path) {
+                self.cover = Some((path, cover));
+            }
+        }
+    }
+
+    /// Returns the cover of the track being played, once it's been found.
+    pub fn current_cover(&self) -> Option<&Image> {
+        match &self.cover {
+            Some((path, cover)) if self.started.as_ref() == Some(path) => cover.as_ref(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::tui::{device_picker::DevicePicker, render::app::App};
+
+impl App {
+    /// Opens the output device picker with the cursor on the current device.
+    pub fn open_device_picker(&mut self) {
+        self.device_picker = Some(DevicePicker::new(&self.audio.device));
+    }
+
+    /// Switches playback to the device under the picker's cursor and closes the picker.
+    pub fn confirm_device(&mut self) {
+        let Some(picker) = self.device_picker.take() else {
+            return;
+        };
+        let Some(device) = picker.devices.get(picker.selected) else {
+            return;
+        };
+        self.status = Some(match self.audio.switch_device(device) {
+            Ok(()) => format!("output: {}", device),
+            Err(err) => format!("couldn't open {}: {}", device, err),
+        });
+    }
+
+    /// Falls back to the default device if the current one disappeared.
+    pub fn check_output(&mut self) {
+        if let Some(lost) = self.audio.check_output() {
+            self.status = Some(format!("{} disappeared, switched to default", lost));
+        }
+    }
+}
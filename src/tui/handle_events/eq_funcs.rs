@@ -0,0 +1,71 @@
+use crate::{data::config::Shelf, tui::render::app::App};
+
+/// Rows of the equalizer view: preamp, ten bands, low shelf and high shelf.
+pub const EQ_ROWS: usize = 13;
+/// Gains are limited to this many dB in either direction.
+pub const EQ_LIMIT: f32 = 12.0;
+/// Corner frequencies used when a shelf is enabled from the equalizer view.
+const DEFAULT_LOW_SHELF: f32 = 100.0;
+const DEFAULT_HIGH_SHELF: f32 = 10000.0;
+
+impl App {
+    /// Moves the equalizer cursor up one row or goes to the bottom if at the top.
+    pub fn eq_navigate_up(&mut self) {
+        self.eq_row = match self.eq_row {
+            0 => EQ_ROWS - 1,
+            row => row - 1,
+        };
+    }
+
+    /// Moves the equalizer cursor down one row or goes to the top if at the bottom.
+    pub fn eq_navigate_down(&mut self) {
+        self.eq_row = (self.eq_row + 1) % EQ_ROWS;
+    }
+
+    /// Adjusts the gain of the selected row by a given delta.
+    pub fn eq_adjust(&mut self, delta: f32) {
+        let row = self.eq_row;
+        let clamp = |gain: f32| (gain + delta).clamp(-EQ_LIMIT, EQ_LIMIT);
+        let adjust_shelf = |shelf: Option<Shelf>, frequency: f32| {
+            let shelf = shelf.unwrap_or(Shelf {
+                frequency,
+                gain: 0.0,
+            });
+            Some(Shelf {
+                gain: clamp(shelf.gain),
+                ..shelf
+            })
+        };
+
+        self.audio.equalizer.update(|settings| {
+            let preset = &mut settings.preset;
+            match row {
+                0 => preset.preamp = clamp(preset.preamp),
+                1..=10 => preset.bands[row - 1] = clamp(preset.bands[row - 1]),
+                11 => preset.low_shelf = adjust_shelf(preset.low_shelf, DEFAULT_LOW_SHELF),
+                _ => preset.high_shelf = adjust_shelf(preset.high_shelf, DEFAULT_HIGH_SHELF),
+            }
+        });
+    }
+
+    /// Turns the equalizer on or off.
+    pub fn eq_toggle(&mut self) {
+        self.audio
+            .equalizer
+            .update(|settings| settings.enabled = !settings.enabled);
+    }
+
+    /// Loads the next (or previous, for a negative step) preset from config.toml, discarding live edits.
+    pub fn eq_cycle_preset(&mut self, step: isize) {
+        let names = self.config.equalizer.preset_names();
+        let current = self.audio.equalizer.settings().name;
+        let index = names.iter().position(|name| *name == current).unwrap_or(0) as isize;
+        let name = names[(index + step).rem_euclid(names.len() as isize) as usize].clone();
+        let preset = self.config.equalizer.get_preset(&name);
+
+        self.audio.equalizer.update(|settings| {
+            settings.name = name;
+            settings.preset = preset;
+        });
+    }
+}
@@ -1,6 +1,6 @@
-use super::super::render::app::{State, Tab};
-use crate::{data::metadata::file_metadata::FileMetadata, tui::render::app::App};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use super::super::render::app::{Prompt, PromptKind, State, Tab};
+use crate::tui::render::app::App;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use std::io;
 
 /// Handles events.
@@ -10,6 +10,7 @@ impl App {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                 self.handle_key_event(key_event)
             }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
             _ => {}
         };
         Ok(())
@@ -18,6 +19,61 @@ impl App {
     /// Handles key events.
     fn handle_key_event(&mut self, key_event: KeyEvent) {
         let vol_delta = self.config.controls.vol_delta;
+        let speed_delta = self.config.controls.speed_delta;
+        let seek_delta = self.config.controls.seek_delta;
+
+        if self.device_picker.is_some() {
+            self.handle_picker_key_event(key_event);
+            return;
+        }
+
+        if self.prompt.is_some() {
+            self.handle_prompt_key_event(key_event);
+            return;
+        }
+
+        if self.tag_editor.is_some() {
+            self.handle_tag_key_event(key_event);
+            return;
+        }
+
+        if self.renames.is_some() {
+            self.handle_rename_key_event(key_event);
+            return;
+        }
+
+        if self.guesses.is_some() {
+            self.handle_guess_key_event(key_event);
+            return;
+        }
+
+        // Alt with a digit rates, so it has to come before the tabs' own keys.
+        if key_event.modifiers.contains(KeyModifiers::ALT)
+            && let KeyCode::Char(digit @ '0'..='5') = key_event.code
+        {
+            self.rate(digit as u8 - b'0');
+            return;
+        }
+
+        if matches!(self.tab, Tab::Playlist) && self.handle_queue_key_event(key_event) {
+            return;
+        }
+
+        if matches!(self.tab, Tab::Equalizer) && self.handle_eq_key_event(key_event) {
+            return;
+        }
+
+        if matches!(self.tab, Tab::Chapters) && self.handle_chapter_key_event(key_event) {
+            return;
+        }
+
+        if matches!(self.tab, Tab::Podcasts) && self.handle_podcast_key_event(key_event) {
+            return;
+        }
+
+        if matches!(self.tab, Tab::Lyrics) && self.handle_lyrics_key_event(key_event) {
+            return;
+        }
 
         match key_event.code {
             KeyCode::Char('q') => self.state = State::Quit,
@@ -36,19 +92,221 @@ impl App {
 
             KeyCode::Char('g') => self.file_browser.goto_music_dir(),
 
-            KeyCode::Char('c') => {
-                self.audio.clear_sink();
-                self.path_queue.clear();
-                self.data = FileMetadata::new();
-            }
+            KeyCode::Char(' ') => self.file_browser.toggle_mark(),
+            KeyCode::Char('R') => self.start_scan(),
+            KeyCode::Char('e') => self.open_tag_editor(),
+            KeyCode::Char('O') => self.open_rename_prompt(),
+            KeyCode::Char('U') => self.undo_renames(),
+            KeyCode::Char('G') => self.open_tag_pattern_prompt(),
+            KeyCode::Char('L') => self.refresh_library(),
+            KeyCode::Char('Q') => self.open_query_prompt(),
+
+            KeyCode::Char('c') => self.handle_clear(),
 
             KeyCode::Char('=') | KeyCode::Char('+') => self.audio.adjust_volume(vol_delta),
-            KeyCode::Char('-') | KeyCode::Char('_') => self.audio.adjust_volume(vol_delta * -1),
+            KeyCode::Char('-') | KeyCode::Char('_') => self.audio.adjust_volume(-vol_delta),
+            KeyCode::Char('m') => self.audio.toggle_mute(),
             KeyCode::Char('p') => self.audio.toggle_play_pause(),
 
+            KeyCode::Char('>') => self.audio.adjust_speed(speed_delta),
+            KeyCode::Char('<') => self.audio.adjust_speed(-speed_delta),
+            KeyCode::Backspace => self.audio.reset_speed(),
+
+            KeyCode::Char('.') => self.handle_seek(seek_delta),
+            KeyCode::Char(',') => self.handle_seek(-seek_delta),
+            KeyCode::Char(')') => self.next_chapter(),
+            KeyCode::Char('(') => self.previous_chapter(),
+            KeyCode::Char('b') => self.resume_bookmark(),
+            KeyCode::Char('A') => self.set_loop_start(),
+            KeyCode::Char('B') => self.set_loop_end(),
+            KeyCode::Char('C') => self.clear_loop(),
+            KeyCode::Char('z') => self.open_prompt(PromptKind::SleepTimer),
+            KeyCode::Char('Z') => self.toggle_stop_after_current(),
+            KeyCode::Char('W') => self.toggle_waveform_seekbar(),
+            KeyCode::Char('T') => self.audio.set_preserve_pitch(!self.audio.preserve_pitch),
+
+            KeyCode::Char('o') => self.open_device_picker(),
+            KeyCode::Char('u') => self.open_prompt(PromptKind::StreamUrl),
+
             KeyCode::Char('1') => self.tab = Tab::Browser,
             KeyCode::Char('2') => self.tab = Tab::Playlist,
+            KeyCode::Char('3') => self.tab = Tab::Equalizer,
+            KeyCode::Char('4') => self.tab = Tab::Chapters,
+            KeyCode::Char('5') => self.tab = Tab::Podcasts,
+            KeyCode::Char('6') => self.tab = Tab::Visualizer,
+            KeyCode::Char('7') => self.tab = Tab::NowPlaying,
+            KeyCode::Char('8') => self.tab = Tab::Lyrics,
+
+            _ => {}
+        }
+    }
+
+    /// Handles the keys specific to the playlist tab. Returns false if the key isn't one of them.
+    fn handle_queue_key_event(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.queue_navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.queue_navigate_down(),
+            KeyCode::Enter => self.play_queue_row(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles the keys specific to the equalizer tab. Returns false if the key isn't one of them.
+    fn handle_eq_key_event(&mut self, key_event: KeyEvent) -> bool {
+        let eq_delta = self.config.controls.eq_delta;
+
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.eq_navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.eq_navigate_down(),
+            KeyCode::Left | KeyCode::Char('h') => self.eq_adjust(-eq_delta),
+            KeyCode::Right | KeyCode::Char('l') => self.eq_adjust(eq_delta),
+            KeyCode::Char('e') => self.eq_toggle(),
+            KeyCode::Char('[') => self.eq_cycle_preset(-1),
+            KeyCode::Char(']') => self.eq_cycle_preset(1),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles the keys specific to the chapters tab. Returns false if the key isn't one of them.
+    fn handle_chapter_key_event(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.chapter_navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.chapter_navigate_down(),
+            KeyCode::Enter => self.play_chapter(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles the keys specific to the lyrics tab. Returns false if the key isn't one of them.
+    fn handle_lyrics_key_event(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.lyrics_scroll_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.lyrics_scroll_down(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles keys while the output device picker is open.
+    fn handle_picker_key_event(&mut self, key_event: KeyEvent) {
+        let Some(picker) = self.device_picker.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => picker.navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => picker.navigate_down(),
+            KeyCode::Enter => self.confirm_device(),
+            KeyCode::Esc | KeyCode::Char('o') | KeyCode::Char('q') => self.device_picker = None,
+            _ => {}
+        }
+    }
+
+    /// Handles keys while the tag editor is open. Typing a value takes every key until Enter or Esc.
+    fn handle_tag_key_event(&mut self, key_event: KeyEvent) {
+        let Some(editor) = self.tag_editor.as_mut() else {
+            return;
+        };
+        if let Some(input) = editor.input.as_mut() {
+            match key_event.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => self.finish_tag_input(),
+                KeyCode::Esc => editor.input = None,
+                _ => {}
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => editor.navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => editor.navigate_down(),
+            KeyCode::Enter => editor.start_input(),
+            KeyCode::Char('x') => editor.revert(),
+            KeyCode::Char('w') => self.confirm_tags(),
+            KeyCode::Esc | KeyCode::Char('e') | KeyCode::Char('q') => self.tag_editor = None,
+            _ => {}
+        }
+    }
 
+    /// Handles keys while the organiser's preview is open.
+    fn handle_rename_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.rename_navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.rename_navigate_down(),
+            KeyCode::Enter | KeyCode::Char('w') => self.apply_renames(),
+            KeyCode::Esc | KeyCode::Char('q') => self.renames = None,
+            _ => {}
+        }
+    }
+
+    /// Handles keys while the preview of tags read from paths is open.
+    fn handle_guess_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.guess_navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.guess_navigate_down(),
+            KeyCode::Enter | KeyCode::Char('w') => self.write_guesses(),
+            KeyCode::Esc | KeyCode::Char('q') => self.guesses = None,
+            _ => {}
+        }
+    }
+
+    /// Handles the keys specific to the podcasts tab. Returns false if the key isn't one of them.
+    fn handle_podcast_key_event(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => self.podcast_navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.podcast_navigate_down(),
+            KeyCode::Right | KeyCode::Char('l') => self.podcast_open(),
+            KeyCode::Enter => match self.podcast_feed {
+                Some(_) => self.play_episode(),
+                None => self.podcast_open(),
+            },
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Esc => self.podcast_back(),
+            KeyCode::Char('a') => self.append_episode(),
+            KeyCode::Char('d') => self.download_episode(),
+            KeyCode::Char('x') => self.toggle_played(),
+            KeyCode::Char('r') => self.refresh_podcasts(),
+            KeyCode::Char('n') => self.open_prompt(PromptKind::FeedUrl),
+            KeyCode::Char('D') => self.unsubscribe(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Opens a text prompt.
+    fn open_prompt(&mut self, kind: PromptKind) {
+        self.prompt = Some(Prompt {
+            kind,
+            input: String::new(),
+        });
+    }
+
+    /// Handles keys while a text prompt is open. Enter hands the text to what the prompt is for.
+    fn handle_prompt_key_event(&mut self, key_event: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => prompt.input.push(c),
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    match prompt.kind {
+                        PromptKind::StreamUrl => self.append_url(&prompt.input),
+                        PromptKind::FeedUrl => self.subscribe(&prompt.input),
+                        PromptKind::SleepTimer => self.set_sleep_timer(&prompt.input),
+                        PromptKind::RenamePattern => self.preview_renames(&prompt.input),
+                        PromptKind::TagPattern => self.preview_guesses(&prompt.input),
+                        PromptKind::SmartPlaylist => self.run_query(&prompt.input),
+                    }
+                }
+            }
+            KeyCode::Esc => self.prompt = None,
             _ => {}
         }
     }
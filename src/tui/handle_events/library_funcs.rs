@@ -0,0 +1,98 @@
+use crate::{
+    data::library::query::Query,
+    tui::render::app::{App, Prompt, PromptKind},
+};
+use std::{fs, sync::mpsc, thread};
+
+impl App {
+    /// Brings the library index up to date with the music directory on a new thread.
+    pub fn refresh_library(&mut self) {
+        if self.library_rx.is_some() {
+            return;
+        }
+        let root = self.config.directories.music_directory.clone();
+        let mut library = self.library.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let read = library.refresh(&root);
+            let _ = tx.send((library, read));
+        });
+        self.library_rx = Some(rx);
+        self.status = Some("indexing library...".to_string());
+    }
+
+    /// Takes the refreshed library index once it's ready, and saves it.
+    pub fn poll_library(&mut self) {
+        let Some(rx) = &self.library_rx else {
+            return;
+        };
+        let Ok((mut library, read)) = rx.try_recv() else {
+            return;
+        };
+        self.library_rx = None;
+        // Listening went on while the files were read, so the statistics of the app are the latest.
+        for (path, track) in &mut library.tracks {
+            if let Some(current) = self.library.tracks.get(path) {
+                track.stats = current.stats.clone();
+            }
+        }
+        self.library = library;
+        self.status = Some(format!(
+            "library: {} tracks, {} read",
+            self.library.tracks.len(),
+            read
+        ));
+        self.write_library();
+    }
+
+    /// Opens the prompt for a smart playlist query.
+    pub fn open_query_prompt(&mut self) {
+        self.prompt = Some(Prompt {
+            kind: PromptKind::SmartPlaylist,
+            input: String::new(),
+        });
+    }
+
+    /// Appends the tracks of the library matching a query to the queue.
+    /// `name = query` saves it instead as name.smart in the browser's directory, to be played like other playlists.
+    pub fn run_query(&mut self, input: &str) {
+        // Terms never contain unquoted spaces, so a spaced `=` before any quote can only separate a name.
+        let named = input
+            .split_once(" = ")
+            .filter(|(name, _)| !name.contains('"'))
+            .map(|(name, query)| (name.trim(), query.trim()));
+        let text = named.map_or(input.trim(), |(_, query)| query);
+        let query = match Query::parse(text) {
+            Ok(query) => query,
+            Err(err) => {
+                self.status = Some(err);
+                return;
+            }
+        };
+        if self.library.tracks.is_empty() {
+            self.status = Some("the library index is empty, refresh it first".to_string());
+            return;
+        }
+        let tracks = query.run(&self.library);
+
+        match named {
+            Some((name, _)) if name.is_empty() || name.contains(['/', '\\']) => {
+                self.status = Some(format!("{} can't be a file name", name));
+            }
+            Some((name, _)) => {
+                let path = self
+                    .file_browser
+                    .current_dir
+                    .join(format!("{}.smart", name));
+                self.status = Some(match fs::write(&path, format!("{}\n", text)) {
+                    Ok(()) => format!("saved {}.smart, {} tracks for now", name, tracks.len()),
+                    Err(err) => format!("couldn't save {}.smart: {}", name, err),
+                });
+            }
+            None => {
+                self.status = Some(format!("queued {} tracks", tracks.len()));
+                self.append_paths(tracks);
+            }
+        }
+    }
+}
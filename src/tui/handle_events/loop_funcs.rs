@@ -0,0 +1,68 @@
+use crate::{
+    handlers::dsp::ab_loop::LoopRange,
+    tui::{handle_events::chapter_funcs::format_position, render::app::App},
+};
+use std::time::Duration;
+
+/// The shortest range that can be looped.
+const MIN_LOOP: Duration = Duration::from_millis(100);
+
+impl App {
+    /// Sets the start of the loop at the current position. An end before it is dropped.
+    pub fn set_loop_start(&mut self) {
+        if self.started.is_none() {
+            return;
+        }
+        let pos = Duration::from_millis(self.audio.sink_pos_millis() as u64);
+        self.loop_a = Some(pos);
+        if self.loop_b.is_some_and(|end| end < pos + MIN_LOOP) {
+            self.loop_b = None;
+        }
+        self.apply_loop();
+    }
+
+    /// Sets the end of the loop at the current position, starting the loop from the beginning of the track if there's no start yet.
+    pub fn set_loop_end(&mut self) {
+        if self.started.is_none() {
+            return;
+        }
+        let pos = Duration::from_millis(self.audio.sink_pos_millis() as u64);
+        let start = self.loop_a.unwrap_or(Duration::ZERO);
+        if pos < start + MIN_LOOP {
+            self.status = Some("the loop end must come after its start".to_string());
+            return;
+        }
+        self.loop_a = Some(start);
+        self.loop_b = Some(pos);
+        self.apply_loop();
+    }
+
+    /// Stops looping and forgets both points.
+    pub fn clear_loop(&mut self) {
+        self.loop_a = None;
+        self.loop_b = None;
+        self.apply_loop();
+    }
+
+    /// Hands the loop to the playback chain once both points are set.
+    fn apply_loop(&mut self) {
+        let range = match (&self.started, self.loop_a, self.loop_b) {
+            (Some(path), Some(start), Some(end)) => Some(LoopRange {
+                path: path.clone(),
+                start,
+                end,
+            }),
+            _ => None,
+        };
+        self.audio.set_loop(range);
+    }
+
+    /// Describes the loop for the header, e.g. "0:12-0:30", or "0:12-" while the end isn't set.
+    pub fn loop_display(&self) -> Option<String> {
+        let start = format_position(self.loop_a?);
+        Some(match self.loop_b {
+            Some(end) => format!("{}-{}", start, format_position(end)),
+            None => format!("{}-", start),
+        })
+    }
+}
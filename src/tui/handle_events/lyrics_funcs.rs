@@ -0,0 +1,38 @@
+use crate::{
+    data::metadata::lyrics::{line_at, read_lyrics},
+    tui::render::app::App,
+};
+use std::{path::Path, time::Duration};
+
+impl App {
+    /// Loads the lyrics of the track that just started.
+    pub fn load_lyrics(&mut self, path: &Path) {
+        self.lyrics = read_lyrics(path);
+        self.lyrics_scroll = 0;
+    }
+
+    /// Returns true if the lyrics have timestamps to follow the track with.
+    pub fn lyrics_synced(&self) -> bool {
+        self.lyrics.first().is_some_and(|line| line.time.is_some())
+    }
+
+    /// Returns the index of the synced line being sung.
+    pub fn current_lyric(&self) -> Option<usize> {
+        line_at(
+            &self.lyrics,
+            Duration::from_millis(self.audio.sink_pos_millis() as u64),
+        )
+    }
+
+    /// Scrolls unsynced lyrics up; synced ones follow the track.
+    pub fn lyrics_scroll_up(&mut self) {
+        self.lyrics_scroll = self.lyrics_scroll.saturating_sub(1);
+    }
+
+    /// Scrolls unsynced lyrics down, keeping the last line in view.
+    pub fn lyrics_scroll_down(&mut self) {
+        if self.lyrics_scroll + 1 < self.lyrics.len() {
+            self.lyrics_scroll += 1;
+        }
+    }
+}
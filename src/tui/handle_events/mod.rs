@@ -1,2 +1,19 @@
+pub mod chapter_funcs;
+pub mod cover_funcs;
+pub mod device_funcs;
+pub mod eq_funcs;
 pub mod key_event;
+pub mod library_funcs;
+pub mod loop_funcs;
+pub mod lyrics_funcs;
+pub mod mouse_event;
+pub mod organise_funcs;
+pub mod podcast_funcs;
 pub mod queue_funcs;
+pub mod scan_funcs;
+pub mod sleep_funcs;
+pub mod stats_funcs;
+pub mod stream_funcs;
+pub mod tag_funcs;
+pub mod visualizer_funcs;
+pub mod waveform_funcs;
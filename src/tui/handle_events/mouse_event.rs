@@ -0,0 +1,261 @@
+use crate::tui::{
+    handle_events::eq_funcs::EQ_ROWS,
+    render::{
+        app::{App, Tab},
+        display::Areas,
+    },
+};
+use crossterm::{
+    event::{MouseButton, MouseEvent, MouseEventKind},
+    terminal,
+};
+use ratatui::layout::{Position, Rect};
+use std::time::{Duration, Instant};
+
+/// Two clicks on the same cell within this time make a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+/// Cells taken by each tab indicator in the `top_center` border, e.g. " 1 ".
+const TAB_WIDTH: u16 = 3;
+
+impl App {
+    /// Handles mouse events: clicks on the seekbar, tabs and list rows, and the wheel over lists and the volume.
+    pub fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if self.device_picker.is_some()
+            || self.prompt.is_some()
+            || self.tag_editor.is_some()
+            || self.renames.is_some()
+            || self.guesses.is_some()
+        {
+            return;
+        }
+        let Ok((width, height)) = terminal::size() else {
+            return;
+        };
+        let areas = Areas::new(Rect::new(0, 0, width, height));
+        let position = Position::new(mouse_event.column, mouse_event.row);
+        let vol_delta = self.config.controls.vol_delta;
+
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double = self
+                    .last_click
+                    .is_some_and(|(time, last)| last == position && time.elapsed() < DOUBLE_CLICK);
+                self.last_click = Some((Instant::now(), position));
+
+                if areas.bottom.contains(position) {
+                    self.seek_to_column(position.x - areas.bottom.x, areas.bottom.width);
+                } else if let Some(tab) = tab_at(areas.top_center, position) {
+                    self.tab = tab;
+                } else if let Some(row) = list_row(areas.middle, position) {
+                    self.click_row(row, areas.middle, double);
+                }
+            }
+            MouseEventKind::ScrollUp if areas.top_right.contains(position) => {
+                self.audio.adjust_volume(vol_delta)
+            }
+            MouseEventKind::ScrollDown if areas.top_right.contains(position) => {
+                self.audio.adjust_volume(-vol_delta)
+            }
+            MouseEventKind::ScrollUp if areas.middle.contains(position) => self.scroll_up(),
+            MouseEventKind::ScrollDown if areas.middle.contains(position) => self.scroll_down(),
+            _ => {}
+        }
+    }
+
+    /// Seeks to the part of the track under a column of the seekbar.
+    fn seek_to_column(&mut self, column: u16, width: u16) {
+        let Some(duration) = self
+            .data
+            .duration_as_secs
+            .filter(|duration| *duration > 0.0)
+        else {
+            return;
+        };
+        if self.audio.is_empty() {
+            return;
+        }
+        let ratio = column as f64 / width.max(1) as f64;
+        self.seek_to(Duration::from_secs_f64(duration * ratio));
+    }
+
+    /// Selects the clicked row of the list in the current tab, or opens it on a double click.
+    fn click_row(&mut self, row: usize, middle: Rect, double: bool) {
+        let offset = |selected: usize| list_offset(middle, selected);
+
+        match self.tab {
+            Tab::Browser => {
+                let index = offset(self.file_browser.selected) + row;
+                if index >= self.file_browser.entries.len() {
+                    return;
+                }
+                self.file_browser.selected = index;
+                self.file_browser
+                    .sel_map
+                    .insert(self.file_browser.current_dir.clone(), index);
+                if double {
+                    match self.file_browser.entries[index].is_dir() {
+                        true => self.file_browser.navigate_into(),
+                        false => self.handle_play(),
+                    }
+                }
+            }
+            Tab::Playlist => {
+                let Some(selected) = self.queue_selected() else {
+                    return;
+                };
+                let index = offset(selected) + row;
+                if index >= self.path_queue.len() {
+                    return;
+                }
+                self.queue_row = index;
+                if double {
+                    self.play_queue_row();
+                }
+            }
+            Tab::Equalizer => {
+                let index = offset(self.eq_row) + row;
+                if index < EQ_ROWS {
+                    self.eq_row = index;
+                }
+            }
+            Tab::Chapters => {
+                let index = offset(self.chapter_row) + row;
+                if index >= self.chapters.len() {
+                    return;
+                }
+                self.chapter_row = index;
+                if double {
+                    self.play_chapter();
+                }
+            }
+            Tab::Podcasts => {
+                let index = offset(self.podcast_row) + row;
+                let rows = match self.podcast_feed {
+                    Some(feed) => self
+                        .podcasts
+                        .feeds
+                        .get(feed)
+                        .map_or(0, |feed| feed.episodes.len()),
+                    None => self.podcasts.feeds.len(),
+                };
+                if index >= rows {
+                    return;
+                }
+                self.podcast_row = index;
+                if double {
+                    match self.podcast_feed {
+                        Some(_) => self.play_episode(),
+                        None => self.podcast_open(),
+                    }
+                }
+            }
+            Tab::Visualizer | Tab::NowPlaying | Tab::Lyrics => {}
+        }
+    }
+
+    /// Moves the cursor of the list in the current tab up.
+    fn scroll_up(&mut self) {
+        match self.tab {
+            Tab::Browser => self.file_browser.navigate_up(),
+            Tab::Playlist => self.queue_navigate_up(),
+            Tab::Equalizer => self.eq_navigate_up(),
+            Tab::Chapters => self.chapter_navigate_up(),
+            Tab::Podcasts => self.podcast_navigate_up(),
+            Tab::Lyrics => self.lyrics_scroll_up(),
+            Tab::Visualizer | Tab::NowPlaying => {}
+        }
+    }
+
+    /// Moves the cursor of the list in the current tab down.
+    fn scroll_down(&mut self) {
+        match self.tab {
+            Tab::Browser => self.file_browser.navigate_down(),
+            Tab::Playlist => self.queue_navigate_down(),
+            Tab::Equalizer => self.eq_navigate_down(),
+            Tab::Chapters => self.chapter_navigate_down(),
+            Tab::Podcasts => self.podcast_navigate_down(),
+            Tab::Lyrics => self.lyrics_scroll_down(),
+            Tab::Visualizer | Tab::NowPlaying => {}
+        }
+    }
+}
+
+/// Returns the tab whose indicator, centred in the bottom border of `top_center`, is at the position.
+fn tab_at(top_center: Rect, position: Position) -> Option<Tab> {
+    let tabs = [
+        Tab::Browser,
+        Tab::Playlist,
+        Tab::Equalizer,
+        Tab::Chapters,
+        Tab::Podcasts,
+        Tab::Visualizer,
+        Tab::NowPlaying,
+        Tab::Lyrics,
+    ];
+    if top_center.height == 0 || position.y != top_center.bottom() - 1 {
+        return None;
+    }
+    // The indicators sit between "┤" and "├".
+    let width = tabs.len() as u16 * TAB_WIDTH + 2;
+    let start = top_center.x + top_center.width.saturating_sub(width) / 2 + 1;
+    let index = position.x.checked_sub(start)? / TAB_WIDTH;
+    tabs.into_iter().nth(index as usize)
+}
+
+/// Returns the index of the first row shown of a list in the middle block.
+///
+/// Lists are drawn with the selection scrolled just into view.
+fn list_offset(middle: Rect, selected: usize) -> usize {
+    let visible = middle.height.saturating_sub(2).max(1) as usize;
+    selected.saturating_sub(visible - 1)
+}
+
+/// Returns the row of the list in the middle block under the position, inside its border.
+fn list_row(middle: Rect, position: Position) -> Option<usize> {
+    let inner = Rect::new(
+        middle.x + 1,
+        middle.y + 1,
+        middle.width.saturating_sub(2),
+        middle.height.saturating_sub(2),
+    );
+    inner
+        .contains(position)
+        .then(|| (position.y - inner.y) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_clicked_tab_indicator() {
+        // 60 cells wide from 15: "┤ 1  2  3  4  5  6  7  8 ├" starts at 32, so " 1 " covers 33 to 35.
+        let top_center = Rect::new(15, 0, 60, 4);
+        let at = |x| tab_at(top_center, Position::new(x, 3));
+        assert!(at(32).is_none());
+        assert!(at(33) == Some(Tab::Browser));
+        assert!(at(35) == Some(Tab::Browser));
+        assert!(at(36) == Some(Tab::Playlist));
+        assert!(at(48) == Some(Tab::Visualizer));
+        assert!(at(56) == Some(Tab::Lyrics));
+        assert!(at(57).is_none());
+        assert!(tab_at(top_center, Position::new(33, 2)).is_none());
+    }
+
+    #[test]
+    fn finds_the_clicked_row_of_a_scrolled_list() {
+        // 12 rows high from 5, so rows 6 to 15 show 10 queued tracks inside the border.
+        let middle = Rect::new(0, 5, 80, 12);
+        let clicked = |selected, y| {
+            list_row(middle, Position::new(10, y)).map(|row| list_offset(middle, selected) + row)
+        };
+        assert_eq!(clicked(0, 5), None);
+        assert_eq!(clicked(0, 6), Some(0));
+        assert_eq!(clicked(0, 15), Some(9));
+        assert_eq!(clicked(0, 16), None);
+        // The selection stays at the bottom once it's scrolled past the first page.
+        assert_eq!(clicked(9, 6), Some(0));
+        assert_eq!(clicked(14, 6), Some(5));
+        assert_eq!(clicked(14, 15), Some(14));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    data::{
+        metadata::tag_fields::is_editable,
+        organiser::{Move, RenameStatus, move_file, plan},
+    },
+    tui::render::app::{App, Prompt, PromptKind},
+};
+use std::path::PathBuf;
+
+impl App {
+    /// Opens the prompt for the organiser's pattern, filled in with the configured one.
+    pub fn open_rename_prompt(&mut self) {
+        self.prompt = Some(Prompt {
+            kind: PromptKind::RenamePattern,
+            input: self.config.organiser.pattern.clone(),
+        });
+    }
+
+    /// Lists where each file of the browser selection would go with the pattern, without moving anything.
+    pub fn preview_renames(&mut self, pattern: &str) {
+        // Only files whose tags can be read have anything to fill the pattern with.
+        let paths: Vec<PathBuf> = self
+            .file_browser
+            .selection()
+            .into_iter()
+            .filter(|path| is_editable(path))
+            .collect();
+        if paths.is_empty() {
+            self.status = Some("no tagged files selected".to_string());
+            return;
+        }
+        match plan(&paths, pattern, &self.config.directories.music_directory) {
+            Ok(renames) => {
+                self.renames = Some(renames);
+                self.rename_row = 0;
+            }
+            Err(err) => self.status = Some(err),
+        }
+    }
+
+    /// Moves the cursor up one row in the rename preview.
+    pub fn rename_navigate_up(&mut self) {
+        self.rename_row = self.rename_row.saturating_sub(1);
+    }
+
+    /// Moves the cursor down one row in the rename preview.
+    pub fn rename_navigate_down(&mut self) {
+        let len = self.renames.as_ref().map_or(0, Vec::len);
+        self.rename_row = (self.rename_row + 1).min(len.saturating_sub(1));
+    }
+
+    /// Moves the previewed files, skipping collisions, and records the moves so they can be undone.
+    pub fn apply_renames(&mut self) {
+        let Some(renames) = self.renames.take() else {
+            return;
+        };
+        let root = &self.config.directories.music_directory;
+        let mut moves = Vec::new();
+        let mut skipped = 0;
+        for rename in renames {
+            match rename.status {
+                RenameStatus::Move => match move_file(&rename.from, &rename.to, root) {
+                    Ok(()) => moves.push(Move {
+                        from: rename.from,
+                        to: rename.to,
+                    }),
+                    Err(_) => skipped += 1,
+                },
+                RenameStatus::Collision => skipped += 1,
+                RenameStatus::Unchanged => {}
+            }
+        }
+
+        let moved = moves.len();
+        self.undo_log.push(moves);
+        self.file_browser.marked.clear();
+        self.status = Some(match (self.undo_log.save(), skipped) {
+            (Err(err), _) => format!("moved {} files, couldn't save undo log: {}", moved, err),
+            (Ok(()), 0) => format!("moved {} files", moved),
+            (Ok(()), _) => format!("moved {} files, {} skipped", moved, skipped),
+        });
+    }
+
+    /// Moves the files of the last organiser run back where they were.
+    pub fn undo_renames(&mut self) {
+        let Some(moves) = self.undo_log.pop() else {
+            self.status = Some("nothing to undo".to_string());
+            return;
+        };
+        let root = &self.config.directories.music_directory;
+        let mut failed = 0;
+        for step in moves.iter().rev() {
+            if move_file(&step.to, &step.from, root).is_err() {
+                failed += 1;
+            }
+        }
+        let _ = self.undo_log.save();
+        self.status = Some(match failed {
+            0 => format!("moved {} files back", moves.len()),
+            _ => format!(
+                "moved {} files back, {} failed",
+                moves.len() - failed,
+                failed
+            ),
+        });
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    data::{
+        files::is_stream,
+        metadata::file_metadata::FileMetadata,
+        podcasts::{Episode, Feed, PodcastEvent, download, fetch_feed},
+    },
+    tui::render::app::App,
+};
+use std::{path::Path, thread};
+
+impl App {
+    /// Fetches the feed at the URL on a new thread and subscribes to it once it arrives.
+    /// URLs without a scheme are fetched over https.
+    pub fn subscribe(&mut self, url: &str) {
+        let url = url.trim();
+        if url.is_empty() {
+            return;
+        }
+        let url = match url.contains("://") {
+            true => url.to_string(),
+            false => format!("https://{}", url),
+        };
+        self.fetch(url.clone());
+        self.status = Some(format!("fetching {}...", url));
+    }
+
+    /// Refreshes the open feed, or every feed from the list of feeds.
+    pub fn refresh_podcasts(&mut self) {
+        let urls: Vec<String> = match self.podcast_feed {
+            Some(index) => self
+                .podcasts
+                .feeds
+                .get(index)
+                .map(|feed| feed.url.clone())
+                .into_iter()
+                .collect(),
+            None => self
+                .podcasts
+                .feeds
+                .iter()
+                .map(|feed| feed.url.clone())
+                .collect(),
+        };
+        self.status = Some(match urls.len() {
+            1 => "refreshing 1 feed...".to_string(),
+            len => format!("refreshing {} feeds...", len),
+        });
+        for url in urls {
+            self.fetch(url);
+        }
+    }
+
+    fn fetch(&self, url: String) {
+        let tx = self.podcast_tx.clone();
+        thread::spawn(move || {
+            let result = fetch_feed(&url).map_err(|err| err.to_string());
+            let _ = tx.send(PodcastEvent::Fetched { url, result });
+        });
+    }
+
+    /// Downloads the episode under the cursor on a new thread.
+    pub fn download_episode(&mut self) {
+        let Some((feed, episode)) = self.selected_episode() else {
+            return;
+        };
+        if self.downloads.contains_key(&episode.url) {
+            return;
+        }
+        if episode.file.as_ref().is_some_and(|file| file.exists()) {
+            self.status = Some(format!("{} is already downloaded", episode.title));
+            return;
+        }
+        let (episode, title) = (episode.clone(), feed.title.clone());
+        let dir = self.config.directories.podcast_directory.clone();
+        let tx = self.podcast_tx.clone();
+        self.downloads
+            .insert(episode.url.clone(), "queued".to_string());
+        thread::spawn(move || {
+            let result = download(&episode, &title, &dir, |received, total| {
+                let _ = tx.send(PodcastEvent::Progress {
+                    url: episode.url.clone(),
+                    received,
+                    total,
+                });
+            })
+            .map_err(|err| err.to_string());
+            let _ = tx.send(PodcastEvent::Downloaded {
+                url: episode.url.clone(),
+                result,
+            });
+        });
+    }
+
+    /// Applies the results of fetches and downloads.
+    pub fn poll_podcasts(&mut self) {
+        let events: Vec<PodcastEvent> = self.podcast_rx.try_iter().collect();
+        let mut changed = false;
+        for event in events {
+            match event {
+                PodcastEvent::Fetched { url, result } => match result {
+                    Ok(parsed) => {
+                        changed = true;
+                        self.podcasts.update(&url, parsed);
+                        if let Some(feed) = self.podcasts.feeds.iter().find(|feed| feed.url == url)
+                        {
+                            self.status =
+                                Some(format!("{}: {} unplayed", feed.title, feed.unplayed()));
+                        }
+                    }
+                    Err(err) => self.status = Some(format!("couldn't fetch {}: {}", url, err)),
+                },
+                PodcastEvent::Progress {
+                    url,
+                    received,
+                    total,
+                } => {
+                    let progress = match total {
+                        Some(total) if total > 0 => format!("{}%", received * 100 / total),
+                        _ => format!("{:.1} MB", received as f64 / 1_000_000.0),
+                    };
+                    self.downloads.insert(url, progress);
+                }
+                PodcastEvent::Downloaded { url, result } => {
+                    self.downloads.remove(&url);
+                    match result {
+                        Ok(path) => {
+                            changed = true;
+                            self.status = Some(format!("downloaded {}", path.to_string_lossy()));
+                            if let Some(episode) = self.podcasts.episode_mut(Path::new(&url)) {
+                                episode.file = Some(path);
+                            }
+                        }
+                        Err(err) => self.status = Some(format!("download failed: {}", err)),
+                    }
+                }
+            }
+        }
+        if changed {
+            self.write_podcasts();
+        }
+    }
+
+    /// Writes the podcasts file, reporting failures in the status line.
+    pub fn write_podcasts(&mut self) {
+        if let Err(err) = self.podcasts.save() {
+            self.status = Some(format!("can't save podcasts: {}", err));
+        }
+    }
+
+    /// Returns the feed and episode under the cursor, when a feed is open.
+    fn selected_episode(&self) -> Option<(&Feed, &Episode)> {
+        let feed = self.podcasts.feeds.get(self.podcast_feed?)?;
+        Some((feed, feed.episodes.get(self.podcast_row)?))
+    }
+
+    /// Returns the number of rows in the podcasts tab.
+    fn podcast_rows(&self) -> usize {
+        match self.podcast_feed {
+            Some(index) => self
+                .podcasts
+                .feeds
+                .get(index)
+                .map_or(0, |feed| feed.episodes.len()),
+            None => self.podcasts.feeds.len(),
+        }
+    }
+
+    /// Moves the podcasts cursor up one element or goes to the bottom if at the top.
+    pub fn podcast_navigate_up(&mut self) {
+        let rows = self.podcast_rows();
+        if rows == 0 {
+            return;
+        }
+        self.podcast_row = match self.podcast_row {
+            0 => rows - 1,
+            row => row - 1,
+        };
+    }
+
+    /// Moves the podcasts cursor down one element or goes to the top if at the bottom.
+    pub fn podcast_navigate_down(&mut self) {
+        let rows = self.podcast_rows();
+        if rows == 0 {
+            return;
+        }
+        self.podcast_row = (self.podcast_row + 1) % rows;
+    }
+
+    /// Lists the episodes of the feed under the cursor.
+    pub fn podcast_open(&mut self) {
+        if self.podcast_feed.is_none() && self.podcast_row < self.podcasts.feeds.len() {
+            self.podcast_feed = Some(self.podcast_row);
+            self.podcast_row = 0;
+        }
+    }
+
+    /// Goes back to the list of feeds, with the cursor on the feed that was open.
+    pub fn podcast_back(&mut self) {
+        if let Some(index) = self.podcast_feed.take() {
+            self.podcast_row = index;
+        }
+    }
+
+    /// Plays the episode under the cursor, from its download if there is one.
+    pub fn play_episode(&mut self) {
+        if let Some((_, episode)) = self.selected_episode() {
+            self.play_paths(vec![episode.source()]);
+        }
+    }
+
+    /// Appends the episode under the cursor to the queue.
+    pub fn append_episode(&mut self) {
+        if let Some((_, episode)) = self.selected_episode() {
+            self.append_paths(vec![episode.source()]);
+        }
+    }
+
+    /// Marks the episode under the cursor as played, or unplayed again.
+    pub fn toggle_played(&mut self) {
+        let (Some(feed), row) = (self.podcast_feed, self.podcast_row) else {
+            return;
+        };
+        if let Some(episode) = self
+            .podcasts
+            .feeds
+            .get_mut(feed)
+            .and_then(|feed| feed.episodes.get_mut(row))
+        {
+            episode.played = !episode.played;
+            episode.position = 0;
+            self.write_podcasts();
+        }
+    }
+
+    /// Removes the feed under the cursor. Downloaded files are kept.
+    pub fn unsubscribe(&mut self) {
+        if self.podcast_feed.is_some() || self.podcast_row >= self.podcasts.feeds.len() {
+            return;
+        }
+        let feed = self.podcasts.feeds.remove(self.podcast_row);
+        self.podcast_row = self
+            .podcast_row
+            .min(self.podcasts.feeds.len().saturating_sub(1));
+        self.status = Some(format!("unsubscribed from {}", feed.title));
+        self.write_podcasts();
+    }
+
+    /// Returns empty metadata for a path, filled in from the feed when it's an episode, since streams have no tags.
+    pub fn new_metadata(&self, path: &Path) -> FileMetadata {
+        let mut data = FileMetadata::new();
+        if let Some((feed, episode)) = self.podcasts.episode(path) {
+            data.title = Some(episode.title.clone());
+            data.album = Some(feed.title.clone());
+            if is_stream(path) {
+                data.duration_as_secs = episode.duration.map(|duration| duration as f64);
+                data.duration_display = data.duration_as_secs.map(FileMetadata::sec_to_min_sec);
+            }
+        }
+        data
+    }
+}
@@ -1,62 +1,124 @@
-use crate::{data::metadata::file_metadata::FileMetadata, tui::render::app::App};
+use crate::{
+    data::{
+        config::ReplayGainMode,
+        library::query::Query,
+        metadata::file_metadata::FileMetadata,
+        playlist::{is_playlist, is_smart_playlist, read_playlist, read_smart_query},
+    },
+    tui::render::app::App,
+};
+use std::path::{Path, PathBuf};
 
 impl App {
     /// Creates a sink and appends audio if the sink is empty or non-existant.
     /// Plays the audio and appens the current sink elements if the sink isn't empty.
+    /// Playlists are played as their entries.
     /// # Examples
     /// ```
     /// sink = [1, 2]
     /// handle_play(3)
     /// sink = [3, 1, 2]
     pub fn handle_play(&mut self) {
-        if let Some(path) = self.file_browser.entries.get(self.file_browser.selected) {
-            if !path.is_dir() {
-                match self.audio.is_empty() {
-                    true => {
-                        self.audio.play(path);
-                        self.meta_manager
-                            .update_current(FileMetadata::new(), path, true);
-                        self.data = self.meta_manager.current.clone();
-                        self.path_queue.push(path.clone());
-                    }
-                    false => {
-                        self.path_queue.insert(0, path.clone());
-                        self.audio.play(&self.path_queue[0]);
+        if let Some(path) = self
+            .file_browser
+            .entries
+            .get(self.file_browser.selected)
+            .cloned()
+            && !path.is_dir()
+        {
+            self.play_paths(self.expand(&path));
+        }
+    }
 
-                        self.audio.clear_sink();
-                        for element in self.path_queue.iter().skip(1) {
-                            self.audio.append(element);
-                        }
+    /// Plays the paths in front of the queue.
+    pub fn play_paths(&mut self, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+        self.leave_track(false);
+        match self.audio.is_empty() {
+            true => {
+                self.meta_manager
+                    .update_current(self.new_metadata(&paths[0]), &paths[0], true);
+                for path in &paths[1..] {
+                    self.meta_manager
+                        .queue_metadata(self.new_metadata(path), path);
+                }
+                self.data = self.meta_manager.current.clone();
+                self.path_queue = paths;
 
-                        self.meta_manager
-                            .update_current(FileMetadata::new(), path, false);
-                        self.data = self.meta_manager.current.clone();
+                let gains = self.queue_gains();
+                self.audio.play(&self.path_queue[0], gains[0]);
+                for (element, gain) in self.path_queue.iter().zip(gains).skip(1) {
+                    self.audio.append(element, gain);
+                }
+            }
+            false => {
+                for (index, path) in paths.iter().enumerate() {
+                    match index {
+                        0 => self
+                            .meta_manager
+                            .update_current(self.new_metadata(path), path, false),
+                        _ => {
+                            self.meta_manager
+                                .insert_metadata(self.new_metadata(path), path, index)
+                        }
                     }
                 }
+                self.data = self.meta_manager.current.clone();
+                self.path_queue.splice(0..0, paths);
+
+                let gains = self.queue_gains();
+                self.audio.play(&self.path_queue[0], gains[0]);
+
+                self.audio.clear_sink();
+                for (element, gain) in self.path_queue.iter().zip(gains).skip(1) {
+                    self.audio.append(element, gain);
+                }
             }
         }
     }
+
     /// Creates sink if it's empty (equivalent to handle play).
     /// Appends sond to the end of the sink is it isn't empty.
+    /// Playlists are appended as their entries.
     /// # Examples
     /// ```
     /// sink = [1, 2]
     /// handle_append(3)
     /// sink = [1, 2, 3]
     pub fn handle_append(&mut self) {
-        if let Some(path) = self.file_browser.entries.get(self.file_browser.selected) {
-            if !path.is_dir() {
-                match self.audio.is_empty() {
-                    true => {
-                        self.audio.play(path);
-                        self.meta_manager
-                            .update_current(FileMetadata::new(), path, true);
-                        self.data = self.meta_manager.current.clone();
-                    }
-                    false => {
-                        self.audio.append(path);
-                        self.meta_manager.queue_metadata(FileMetadata::new(), path);
-                    }
+        if let Some(path) = self
+            .file_browser
+            .entries
+            .get(self.file_browser.selected)
+            .cloned()
+            && !path.is_dir()
+        {
+            self.append_paths(self.expand(&path));
+        }
+    }
+
+    /// Appends the paths to the end of the queue, starting playback if nothing is playing.
+    pub fn append_paths(&mut self, paths: Vec<PathBuf>) {
+        let start = self.audio.is_empty();
+        for (index, path) in paths.iter().enumerate() {
+            match index == 0 && start {
+                true => {
+                    self.meta_manager
+                        .update_current(self.new_metadata(path), path, true);
+                    self.data = self.meta_manager.current.clone();
+                    self.path_queue.clear();
+
+                    let gain = self.replay_gain_at(0);
+                    self.audio.play(path, gain);
+                }
+                false => {
+                    self.meta_manager
+                        .queue_metadata(self.new_metadata(path), path);
+
+                    let gain = self.replay_gain_at(self.meta_manager.queue.len() - 1);
+                    self.audio.append(path, gain);
                 }
             }
             self.path_queue.push(path.clone());
@@ -65,22 +127,139 @@ impl App {
 
     /// Skips the current element in the sink, re-appends the next elements to the sink, and gets the metadata for the new head of the sink.
     pub fn handle_skip(&mut self) {
-        if self.audio.get_len() > 0 {
-            self.path_queue.remove(0);
+        self.skip_to(1);
+    }
+
+    /// Skips to the queued track at the index, dropping the ones before it.
+    pub fn skip_to(&mut self, index: usize) {
+        if self.audio.get_len() > 0 && index > 0 && index <= self.path_queue.len() {
+            self.count_skip();
+            self.leave_track(false);
+            self.path_queue.drain(..index);
+            let passed = (index - 1).min(self.meta_manager.queue.len());
+            self.meta_manager.queue.drain(..passed);
             self.audio.clear_sink();
+            self.data = self.meta_manager.pop_next().unwrap_or(FileMetadata::new());
 
-            match self.path_queue.get(0) {
-                Some(next_path) => {
-                    self.audio.play(next_path);
-                    for element in self.path_queue.iter().skip(1) {
-                        self.audio.append(element);
-                    }
-                    self.data = self.meta_manager.pop_next().unwrap_or(FileMetadata::new());
-                }
-                None => {
-                    self.data = FileMetadata::new();
+            if let Some(next_path) = self.path_queue.first() {
+                let gains = self.queue_gains();
+                self.audio.play(next_path, gains[0]);
+                for (element, gain) in self.path_queue.iter().zip(gains).skip(1) {
+                    self.audio.append(element, gain);
                 }
             }
         }
     }
+
+    /// Returns the playlist cursor, or None if the queue is empty.
+    pub fn queue_selected(&self) -> Option<usize> {
+        (!self.path_queue.is_empty()).then(|| self.queue_row.min(self.path_queue.len() - 1))
+    }
+
+    /// Moves the playlist cursor up one element or goes to the bottom if at the top.
+    pub fn queue_navigate_up(&mut self) {
+        if let Some(row) = self.queue_selected() {
+            self.queue_row = match row {
+                0 => self.path_queue.len() - 1,
+                row => row - 1,
+            };
+        }
+    }
+
+    /// Moves the playlist cursor down one element or goes to the top if at the bottom.
+    pub fn queue_navigate_down(&mut self) {
+        if let Some(row) = self.queue_selected() {
+            self.queue_row = (row + 1) % self.path_queue.len();
+        }
+    }
+
+    /// Plays the queued track under the playlist cursor.
+    pub fn play_queue_row(&mut self) {
+        if let Some(row) = self.queue_selected() {
+            self.skip_to(row);
+            self.queue_row = 0;
+        }
+    }
+
+    /// Stops playback and empties the queue.
+    pub fn handle_clear(&mut self) {
+        self.leave_track(false);
+        self.audio.clear_sink();
+        self.path_queue.clear();
+        self.data = FileMetadata::new();
+    }
+
+    /// Follows the sink when it moves on to the next queued track by itself, or runs out of tracks.
+    pub fn sync_queue(&mut self) {
+        if self.audio.is_empty() {
+            if self.started.is_some() {
+                self.leave_track(true);
+                self.path_queue.clear();
+            }
+            return;
+        }
+        let Some(playing) = self.audio.now_playing() else {
+            return;
+        };
+        if self.path_queue.first() != Some(&playing) && self.path_queue.get(1) == Some(&playing) {
+            self.leave_track(true);
+            self.path_queue.remove(0);
+            self.data = self.meta_manager.pop_next().unwrap_or(FileMetadata::new());
+        }
+        if self.path_queue.first() == Some(&playing) && self.started.as_ref() != Some(&playing) {
+            self.started = Some(playing.clone());
+            self.enter_track(&playing);
+        }
+    }
+
+    /// Seeks the current track by a number of seconds. Formats that can't seek are reported by `sync_stream`.
+    pub fn handle_seek(&mut self, delta: i64) {
+        self.audio.seek_by(delta);
+    }
+
+    /// Returns the ReplayGain factor for the queued track at the given index, according to the configured mode.
+    fn replay_gain_at(&self, index: usize) -> f32 {
+        let playback = &self.config.playback;
+        let queue = &self.meta_manager.queue;
+        let Some(track) = queue.get(index) else {
+            return 1.0;
+        };
+
+        let album = match playback.replay_gain {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => false,
+            ReplayGainMode::Album => true,
+            ReplayGainMode::Auto => {
+                let same_album = |other: Option<&FileMetadata>| {
+                    other.is_some_and(|other| track.album.is_some() && other.album == track.album)
+                };
+                same_album(index.checked_sub(1).and_then(|prev| queue.get(prev)))
+                    || same_album(queue.get(index + 1))
+            }
+        };
+
+        track.replay_gain.factor(
+            album,
+            playback.replay_gain_preamp,
+            playback.replay_gain_clip_prevention,
+        )
+    }
+
+    /// Returns the entries of a playlist, or the path itself. Smart playlists are run against the library.
+    fn expand(&self, path: &Path) -> Vec<PathBuf> {
+        match (is_smart_playlist(path), is_playlist(path)) {
+            (true, _) => read_smart_query(path)
+                .and_then(|query| Query::parse(&query).ok())
+                .map_or_else(Vec::new, |query| query.run(&self.library)),
+            (false, true) => read_playlist(path),
+            (false, false) => vec![path.to_path_buf()],
+        }
+    }
+
+    /// Returns the ReplayGain factor of every path in the queue.
+    fn queue_gains(&self) -> Vec<f32> {
+        (0..self.path_queue.len())
+            .map(|index| self.replay_gain_at(index))
+            .collect()
+    }
 }
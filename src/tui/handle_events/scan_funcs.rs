@@ -0,0 +1,66 @@
+use crate::{
+    data::loudness::scanner::{ScanEvent, scan},
+    tui::render::app::App,
+};
+use std::{sync::mpsc, thread};
+
+impl App {
+    /// Starts a ReplayGain scan of the browser selection on a new thread.
+    pub fn start_scan(&mut self) {
+        if self.scan_rx.is_some() {
+            return;
+        }
+        let paths = self.file_browser.selection();
+        if paths.is_empty() {
+            return;
+        }
+
+        let write = self.config.scanner.write_tags;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            scan(&paths, write, |event| {
+                let _ = tx.send(event);
+            });
+        });
+        self.scan_rx = Some(rx);
+        self.scan_errors = 0;
+        self.status = Some("scanning...".to_string());
+    }
+
+    /// Drains the progress of a running scan into the status line.
+    pub fn poll_scan(&mut self) {
+        let Some(rx) = &self.scan_rx else {
+            return;
+        };
+
+        let mut finished = false;
+        for event in rx.try_iter() {
+            match event {
+                ScanEvent::Measured { done, total, path } => {
+                    self.status = Some(format!(
+                        "scanning {}/{}: {}",
+                        done,
+                        total,
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ));
+                }
+                ScanEvent::Tagged { written, .. } => {
+                    if let Some(Err(_)) = written {
+                        self.scan_errors += 1;
+                    }
+                }
+                ScanEvent::Failed { .. } => self.scan_errors += 1,
+                ScanEvent::Finished => finished = true,
+            }
+        }
+
+        if finished {
+            self.scan_rx = None;
+            self.file_browser.marked.clear();
+            self.status = Some(match self.scan_errors {
+                0 => "scan finished".to_string(),
+                errors => format!("scan finished with {} errors", errors),
+            });
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::tui::render::app::{App, SleepTimer};
+use std::time::{Duration, Instant};
+
+impl App {
+    /// Sets the sleep timer from what was typed: minutes, "track", "album" or "queue". Anything else turns it off.
+    pub fn set_sleep_timer(&mut self, input: &str) {
+        let input = input.trim().to_lowercase();
+        self.sleep = match input.as_str() {
+            "t" | "track" => Some(SleepTimer::EndOfTrack),
+            "a" | "album" => Some(SleepTimer::EndOfAlbum),
+            "q" | "queue" => Some(SleepTimer::EndOfQueue),
+            _ => input
+                .parse::<f64>()
+                .ok()
+                .filter(|minutes| minutes.is_finite() && *minutes > 0.0)
+                .map(|minutes| SleepTimer::Remaining(Duration::from_secs_f64(minutes * 60.0))),
+        };
+        self.sleep_tick = Instant::now();
+        self.audio.set_fade(1.0);
+        self.status = Some(match self.sleep {
+            Some(SleepTimer::Remaining(remaining)) => {
+                format!(
+                    "sleeping in {} min",
+                    (remaining.as_secs_f64() / 60.0).round()
+                )
+            }
+            Some(SleepTimer::EndOfTrack) => "sleeping after this track".to_string(),
+            Some(SleepTimer::EndOfAlbum) => "sleeping after this album".to_string(),
+            Some(SleepTimer::EndOfQueue) => "sleeping after the queue".to_string(),
+            None => "sleep timer off".to_string(),
+        });
+        self.arm_stop_after();
+    }
+
+    /// Toggles stopping once the current track is over.
+    pub fn toggle_stop_after_current(&mut self) {
+        self.stop_after_current = !self.stop_after_current;
+        self.status = Some(
+            match self.stop_after_current {
+                true => "stopping after this track",
+                false => "not stopping after this track",
+            }
+            .to_string(),
+        );
+        self.arm_stop_after();
+    }
+
+    /// Tells the playback chain whether to hold at the end of the current track.
+    pub fn arm_stop_after(&self) {
+        let last_of_album = || {
+            let queue = &self.meta_manager.queue;
+            queue
+                .get(1)
+                .is_none_or(|next| next.album != self.meta_manager.current.album)
+        };
+        let stop = self.stop_after_current
+            || match self.sleep {
+                Some(SleepTimer::EndOfTrack) => true,
+                Some(SleepTimer::EndOfAlbum) => last_of_album(),
+                _ => false,
+            };
+        self.audio.stop_after(match stop {
+            true => self.started.as_deref(),
+            false => None,
+        });
+    }
+
+    /// Counts the sleep timer down while playing, fading out at the end, and stops playback when it's due.
+    pub fn tick_sleep(&mut self) {
+        let elapsed = self.sleep_tick.elapsed();
+        self.sleep_tick = Instant::now();
+
+        if self.audio.is_held() && !self.audio.paused {
+            self.audio.toggle_play_pause();
+            self.stop_after_current = false;
+            if matches!(
+                self.sleep,
+                Some(SleepTimer::EndOfTrack | SleepTimer::EndOfAlbum)
+            ) {
+                self.sleep = None;
+            }
+            self.status = Some("stopped".to_string());
+            return;
+        }
+        if self.audio.is_empty() {
+            // Whatever the timer waited for is over.
+            if !matches!(self.sleep, Some(SleepTimer::Remaining(_))) {
+                self.sleep = None;
+            }
+            self.stop_after_current = false;
+            return;
+        }
+
+        let Some(SleepTimer::Remaining(remaining)) = self.sleep else {
+            return;
+        };
+        if self.audio.paused {
+            return;
+        }
+        let remaining = remaining.saturating_sub(elapsed);
+        if remaining.is_zero() {
+            self.sleep = None;
+            self.audio.toggle_play_pause();
+            self.audio.set_fade(1.0);
+            self.status = Some("stopped by the sleep timer".to_string());
+            return;
+        }
+        self.sleep = Some(SleepTimer::Remaining(remaining));
+        let fade = Duration::from_secs(self.config.playback.sleep_fade_seconds);
+        if remaining < fade {
+            self.audio
+                .set_fade(remaining.as_secs_f32() / fade.as_secs_f32());
+        }
+    }
+
+    /// Describes the sleep timer for the header: the time left, or what it waits for.
+    pub fn sleep_display(&self) -> Option<String> {
+        if self.stop_after_current {
+            return Some("stop".to_string());
+        }
+        Some(match self.sleep? {
+            SleepTimer::Remaining(remaining) => {
+                let secs = remaining.as_secs_f64().ceil() as u64;
+                format!("{}:{:02}", secs / 60, secs % 60)
+            }
+            SleepTimer::EndOfTrack => "track".to_string(),
+            SleepTimer::EndOfAlbum => "album".to_string(),
+            SleepTimer::EndOfQueue => "queue".to_string(),
+        })
+    }
+}
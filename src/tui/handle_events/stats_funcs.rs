@@ -0,0 +1,164 @@
+use crate::{
+    data::{files::is_stream, metadata::tag_fields::is_editable},
+    tui::render::app::App,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How long changed statistics wait before the library index is saved.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How leaving a track counts towards its statistics.
+#[derive(Debug, PartialEq)]
+enum Listen {
+    Play,
+    Skip,
+    /// The duration is unknown, so neither can be told.
+    Neither,
+}
+
+/// Checks whether at least `fraction` of a track was heard.
+fn heard_enough(position_millis: u128, duration_secs: Option<f64>, fraction: f64) -> bool {
+    duration_secs
+        .filter(|duration| *duration > 0.0)
+        .is_some_and(|duration| position_millis as f64 / (duration * 1000.0) >= fraction)
+}
+
+/// Tells whether leaving a track counts as a play or a skip.
+fn classify(
+    finished: bool,
+    position_millis: u128,
+    duration_secs: Option<f64>,
+    fraction: f64,
+) -> Listen {
+    match (finished, duration_secs) {
+        (true, _) => Listen::Play,
+        (false, None) => Listen::Neither,
+        (false, Some(_)) => match heard_enough(position_millis, duration_secs, fraction) {
+            true => Listen::Play,
+            false => Listen::Skip,
+        },
+    }
+}
+
+impl App {
+    /// Tells how leaving the current track now counts.
+    fn listen(&self, finished: bool) -> Listen {
+        classify(
+            finished,
+            self.audio.sink_pos_millis(),
+            self.data.duration_as_secs,
+            self.config.statistics.play_fraction,
+        )
+    }
+
+    /// Counts a play of the track being left if it finished or was heard long enough.
+    pub fn record_listen(&mut self, path: &Path, finished: bool) {
+        if is_stream(path) || self.listen(finished) != Listen::Play {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let stats = self.library.stats_mut(path);
+        stats.plays += 1;
+        stats.last_played = Some(now);
+        self.library_dirty = true;
+    }
+
+    /// Counts a skip of the current track, unless it was already heard long enough to count as played.
+    pub fn count_skip(&mut self) {
+        let Some(path) = self.started.clone() else {
+            return;
+        };
+        if is_stream(&path) || self.listen(false) != Listen::Skip {
+            return;
+        }
+        self.library.stats_mut(&path).skips += 1;
+        self.library_dirty = true;
+    }
+
+    /// Rates the playing track, or the browser selection when nothing plays, from 1 to 5 stars; 0 clears the rating.
+    pub fn rate(&mut self, rating: u8) {
+        let paths: Vec<PathBuf> = match &self.started {
+            Some(path) if !is_stream(path) => vec![path.clone()],
+            Some(_) => return,
+            None => self.file_browser.selection(),
+        };
+        if paths.is_empty() {
+            return;
+        }
+        for path in &paths {
+            self.library.stats_mut(path).rating = rating;
+        }
+        self.library_dirty = true;
+        self.status = Some(match rating {
+            0 => format!("cleared the rating of {} tracks", paths.len()),
+            _ => format!("rated {} tracks {} stars", paths.len(), rating),
+        });
+
+        // Tags are written in the background, since rewriting files of a large selection takes a while.
+        if self.config.statistics.mirror_ratings {
+            let tagged: Vec<PathBuf> = paths.into_iter().filter(|path| is_editable(path)).collect();
+            if !tagged.is_empty() {
+                self.rating_writer.write(tagged, rating);
+            }
+        }
+    }
+
+    /// Reports the rating tags that couldn't be written.
+    pub fn poll_ratings(&mut self) {
+        let failed = self.rating_writer.failed();
+        if failed > 0 {
+            self.status = Some(format!("{} rating tags not written", failed));
+        }
+    }
+
+    /// Saves changed statistics once they have waited long enough, so a burst of changes is written once.
+    pub fn tick_library(&mut self) {
+        if self.library_dirty && self.library_saved.elapsed() >= SAVE_INTERVAL {
+            self.write_library();
+        }
+    }
+
+    /// Saves the library index if anything changed since it was last saved.
+    pub fn flush_library(&mut self) {
+        if self.library_dirty {
+            self.write_library();
+        }
+    }
+
+    /// Saves the library index, reporting in the status if it can't.
+    pub fn write_library(&mut self) {
+        self.library_dirty = false;
+        self.library_saved = Instant::now();
+        if let Err(err) = self.library.save() {
+            self.status = Some(format!("can't save the library: {}", err));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_count_as_heard_past_the_play_fraction() {
+        assert!(heard_enough(30_000, Some(60.0), 0.5));
+        assert!(!heard_enough(29_999, Some(60.0), 0.5));
+        assert!(heard_enough(60_000, Some(60.0), 1.0));
+        assert!(!heard_enough(10_000, None, 0.5));
+        assert!(!heard_enough(10_000, Some(0.0), 0.5));
+    }
+
+    #[test]
+    fn leaving_early_is_a_skip_and_leaving_late_a_play() {
+        assert_eq!(classify(false, 5_000, Some(200.0), 0.5), Listen::Skip);
+        assert_eq!(classify(false, 150_000, Some(200.0), 0.5), Listen::Play);
+        assert_eq!(classify(true, 5_000, Some(200.0), 0.5), Listen::Play);
+        assert_eq!(classify(true, 5_000, None, 0.5), Listen::Play);
+        assert_eq!(classify(false, 5_000, None, 0.5), Listen::Neither);
+    }
+}
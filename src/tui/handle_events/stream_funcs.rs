@@ -0,0 +1,67 @@
+use crate::{data::files::is_stream, handlers::stream::StreamStatus, tui::render::app::App};
+use std::path::PathBuf;
+
+impl App {
+    /// Appends the URL typed into the prompt to the queue.
+    pub fn append_url(&mut self, url: &str) {
+        let url = url.trim();
+        if url.is_empty() {
+            return;
+        }
+        match is_stream(&PathBuf::from(url)) {
+            true => self.append_paths(vec![PathBuf::from(url)]),
+            false => self.status = Some(format!("not an http(s) url: {}", url)),
+        }
+    }
+
+    /// Shows the live title and connection state of the stream being played, and errors of tracks that couldn't be opened or seeked.
+    pub fn sync_stream(&mut self) {
+        if let Some(error) = self.audio.take_error() {
+            self.status = Some(error);
+        }
+
+        let Some(url) = self.path_queue.first().filter(|path| is_stream(path)) else {
+            self.stream_status = None;
+            return;
+        };
+        let Some(info) = self.audio.stream_info(url) else {
+            return;
+        };
+
+        match (info.title(), info.station()) {
+            (Some(title), _) => match title.split_once(" - ") {
+                Some((artist, title)) => {
+                    self.data.artist = Some(artist.to_string());
+                    self.data.title = Some(title.to_string());
+                }
+                None => {
+                    self.data.artist = None;
+                    self.data.title = Some(title);
+                }
+            },
+            (None, Some(station)) => self.data.title = Some(station),
+            (None, None) => {}
+        }
+        if let Some(station) = info.station() {
+            self.data.album = Some(station);
+        }
+
+        let status = info.status();
+        if self.stream_status.as_ref() == Some(&status) {
+            return;
+        }
+        self.status = match &status {
+            StreamStatus::Connecting => Some("connecting...".to_string()),
+            StreamStatus::Buffering => Some("buffering...".to_string()),
+            StreamStatus::Playing => Some(format!(
+                "streaming {}",
+                info.station()
+                    .unwrap_or_else(|| url.to_string_lossy().to_string())
+            )),
+            StreamStatus::Reconnecting(attempt) => Some(format!("reconnecting ({})...", attempt)),
+            StreamStatus::Failed(reason) => Some(format!("stream failed: {}", reason)),
+            StreamStatus::Ended => Some("stream ended".to_string()),
+        };
+        self.stream_status = Some(status);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    data::{
+        metadata::{tag_fields::is_editable, tag_writer::write_tags},
+        path_tags::guess_tags,
+    },
+    tui::{
+        render::app::{App, Prompt, PromptKind},
+        tag_editor::TagEditor,
+    },
+};
+use std::path::PathBuf;
+
+impl App {
+    /// Opens the tag editor on the files of the browser selection whose tags can be written.
+    pub fn open_tag_editor(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .file_browser
+            .selection()
+            .into_iter()
+            .filter(|path| is_editable(path))
+            .collect();
+        match paths.is_empty() {
+            true => self.status = Some("no taggable files selected".to_string()),
+            false => self.tag_editor = Some(TagEditor::new(paths)),
+        }
+    }
+
+    /// Finishes typing the value of a field, reporting values that don't fit it.
+    pub fn finish_tag_input(&mut self) {
+        if let Some(editor) = self.tag_editor.as_mut()
+            && let Err(err) = editor.finish_input()
+        {
+            self.status = Some(err);
+        }
+    }
+
+    /// Writes the pending changes to every file being edited, then closes the editor.
+    pub fn confirm_tags(&mut self) {
+        let Some(editor) = self.tag_editor.take() else {
+            return;
+        };
+        let changes = editor.changes();
+        if changes.is_empty() {
+            self.status = Some("no tag changes".to_string());
+            return;
+        }
+
+        let mut written = Vec::new();
+        let mut failed = 0;
+        for path in &editor.paths {
+            match write_tags(path, &changes) {
+                Ok(()) => written.push(path.clone()),
+                Err(_) => failed += 1,
+            }
+        }
+        self.refresh_metadata(&written);
+        self.file_browser.marked.clear();
+        self.status = Some(match failed {
+            0 => format!("tagged {} files", written.len()),
+            _ => format!("tagged {} files, {} failed", written.len(), failed),
+        });
+    }
+
+    /// Opens the prompt for the pattern tags are read from paths with, filled in with the configured one.
+    pub fn open_tag_pattern_prompt(&mut self) {
+        self.prompt = Some(Prompt {
+            kind: PromptKind::TagPattern,
+            input: self.config.organiser.tag_pattern.clone(),
+        });
+    }
+
+    /// Reads the tags of the browser selection from their paths and lists them, without writing anything.
+    pub fn preview_guesses(&mut self, pattern: &str) {
+        let paths: Vec<PathBuf> = self
+            .file_browser
+            .selection()
+            .into_iter()
+            .filter(|path| is_editable(path))
+            .collect();
+        if paths.is_empty() {
+            self.status = Some("no taggable files selected".to_string());
+            return;
+        }
+        match guess_tags(&paths, pattern) {
+            Ok(guesses) => {
+                self.guesses = Some(guesses);
+                self.guess_row = 0;
+            }
+            Err(err) => self.status = Some(err),
+        }
+    }
+
+    /// Moves the cursor up one row in the preview of tags read from paths.
+    pub fn guess_navigate_up(&mut self) {
+        self.guess_row = self.guess_row.saturating_sub(1);
+    }
+
+    /// Moves the cursor down one row in the preview of tags read from paths.
+    pub fn guess_navigate_down(&mut self) {
+        let len = self
+            .guesses
+            .as_ref()
+            .map_or(0, |(_, guesses)| guesses.len());
+        self.guess_row = (self.guess_row + 1).min(len.saturating_sub(1));
+    }
+
+    /// Writes the tags read from the paths that matched the pattern, then closes the preview.
+    pub fn write_guesses(&mut self) {
+        let Some((_, guesses)) = self.guesses.take() else {
+            return;
+        };
+        let mut written = Vec::new();
+        let mut failed = 0;
+        let mut unmatched = 0;
+        for guess in guesses {
+            let Some(fields) = guess.fields else {
+                unmatched += 1;
+                continue;
+            };
+            match write_tags(&guess.path, &fields) {
+                Ok(()) => written.push(guess.path),
+                Err(_) => failed += 1,
+            }
+        }
+        self.refresh_metadata(&written);
+        self.file_browser.marked.clear();
+        self.status = Some(match (failed, unmatched) {
+            (0, 0) => format!("tagged {} files", written.len()),
+            (0, _) => format!("tagged {} files, {} didn't match", written.len(), unmatched),
+            _ => format!(
+                "tagged {} files, {} didn't match, {} failed",
+                written.len(),
+                unmatched,
+                failed
+            ),
+        });
+    }
+
+    /// Re-reads the metadata of queued tracks after their tags changed, including the now playing header.
+    pub fn refresh_metadata(&mut self, paths: &[PathBuf]) {
+        for (index, path) in self.path_queue.iter().enumerate() {
+            if paths.contains(path)
+                && let Some(data) = self.meta_manager.queue.get_mut(index)
+            {
+                data.get_file_data(path);
+                data.fill_duration(path);
+            }
+        }
+        if self
+            .path_queue
+            .first()
+            .is_some_and(|path| paths.contains(path))
+            && let Some(current) = self.meta_manager.queue.first()
+        {
+            self.meta_manager.current = current.clone();
+            self.data = current.clone();
+        }
+    }
+}
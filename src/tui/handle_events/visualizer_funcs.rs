@@ -0,0 +1,41 @@
+use crate::{handlers::dsp::fft::spectrum, tui::render::app::App};
+use std::time::Instant;
+
+/// Samples per spectrum, about 46 ms at 44.1 kHz.
+pub const FFT_LEN: usize = 2048;
+/// Level shown as an empty bar, in dB below full scale.
+const FLOOR_DB: f32 = -70.0;
+/// How fast bars fall after a peak, in full heights per second.
+const FALL_RATE: f32 = 1.5;
+
+impl App {
+    /// Returns true if samples are flowing through the visualizer tap.
+    pub fn visualizer_active(&self) -> bool {
+        !self.audio.is_empty() && !self.audio.paused && !self.audio.is_held()
+    }
+
+    /// Analyses the latest samples, letting bars fall slowly instead of jumping down.
+    pub fn update_spectrum(&mut self) {
+        let elapsed = self.spectrum_tick.elapsed().as_secs_f32();
+        self.spectrum_tick = Instant::now();
+
+        let levels = match self.visualizer_active() {
+            true => {
+                let (samples, rate) = self.audio.latest_samples(FFT_LEN);
+                self.spectrum_rate = rate;
+                spectrum(&samples)
+                    .into_iter()
+                    .map(|magnitude| {
+                        let db = 20.0 * magnitude.max(1e-9).log10();
+                        ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+                    })
+                    .collect()
+            }
+            false => vec![0.0; FFT_LEN / 2],
+        };
+        self.spectrum.resize(FFT_LEN / 2, 0.0);
+        for (shown, level) in self.spectrum.iter_mut().zip(levels) {
+            *shown = level.max(*shown - FALL_RATE * elapsed).max(0.0);
+        }
+    }
+}
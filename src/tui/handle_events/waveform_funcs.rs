@@ -0,0 +1,51 @@
+use crate::{
+    data::{files::is_stream, waveform},
+    tui::render::app::App,
+};
+use std::{path::Path, thread};
+
+impl App {
+    /// Computes the peak envelope of a track on a new thread, unless the waveform seekbar is off or it's known already.
+    /// Envelopes of tracks that left the queue are dropped.
+    pub fn request_waveform(&mut self, path: &Path) {
+        if !self.config.visualizer.waveform_seekbar || is_stream(path) {
+            return;
+        }
+        self.waveforms
+            .retain(|known, _| self.path_queue.contains(known));
+        if self.waveforms.contains_key(path) {
+            return;
+        }
+        self.waveforms.insert(path.to_path_buf(), None);
+        let path = path.to_path_buf();
+        let tx = self.waveform_tx.clone();
+        thread::spawn(move || {
+            let envelope = waveform::load(&path).ok();
+            let _ = tx.send((path, envelope));
+        });
+    }
+
+    /// Keeps the envelopes computed in the background.
+    pub fn poll_waveforms(&mut self) {
+        for (path, envelope) in self.waveform_rx.try_iter() {
+            self.waveforms.insert(path, envelope);
+        }
+    }
+
+    /// Switches between the waveform seekbar and the plain one.
+    pub fn toggle_waveform_seekbar(&mut self) {
+        let visualizer = &mut self.config.visualizer;
+        visualizer.waveform_seekbar = !visualizer.waveform_seekbar;
+        if let Some(path) = self.started.clone() {
+            self.request_waveform(&path);
+        }
+    }
+
+    /// Returns the envelope of the track being played, once it's been computed.
+    pub fn current_waveform(&self) -> Option<&[f32]> {
+        match self.config.visualizer.waveform_seekbar {
+            true => self.waveforms.get(self.started.as_ref()?)?.as_deref(),
+            false => None,
+        }
+    }
+}
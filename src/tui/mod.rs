@@ -1,3 +1,5 @@
+pub mod device_picker;
 pub mod fs_browser;
 pub mod handle_events;
 pub mod render;
+pub mod tag_editor;
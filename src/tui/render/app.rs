@@ -1,33 +1,59 @@
 use crate::{
     data::{
-        config::{ConfigData, load_config},
-        metadata::{file_metadata::FileMetadata, metadata_queue::MetadataQueue},
+        bookmarks::Bookmarks,
+        config::{ConfigData, GraphicsProtocol, load_config},
+        cover::Image,
+        library::Library,
+        loudness::scanner::ScanEvent,
+        metadata::{
+            chapters::Chapter, file_metadata::FileMetadata, lyrics::LyricLine,
+            metadata_queue::MetadataQueue, tag_fields::TagField, tag_writer::RatingWriter,
+        },
+        organiser::{Rename, UndoLog},
+        path_tags::Guess,
+        podcasts::{PodcastEvent, Podcasts},
+    },
+    handlers::{
+        dsp::equalizer::{EqControl, EqSettings},
+        input_handler::InputHandler,
+        stream::StreamStatus,
+    },
+    tui::{
+        device_picker::DevicePicker, fs_browser::FileBrowser, render::cover_view::detect_protocol,
+        tag_editor::TagEditor,
     },
-    handlers::input_handler::InputHandler,
-    tui::fs_browser::FileBrowser,
 };
 use crossterm::{
-    event::poll,
+    event::{DisableMouseCapture, EnableMouseCapture, poll},
     execute,
     terminal::{LeaveAlternateScreen, disable_raw_mode},
 };
-use ratatui::DefaultTerminal;
+use ratatui::{
+    DefaultTerminal,
+    layout::{Position, Rect},
+};
 use std::{
+    collections::HashMap,
     env,
     error::Error,
     io::stdout,
     path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
     thread::sleep,
     time::{Duration, Instant},
 };
 
+/// How long a status message is shown before the view it replaced comes back.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Runs the TUI application.
 pub fn run_tui() -> Result<(), Box<dyn Error>> {
     let mut terminal = ratatui::init();
+    execute!(stdout(), EnableMouseCapture)?;
     let current_dir = env::current_dir()?;
     let mut app = App::new(current_dir)?;
     let res = app.run(&mut terminal);
-    execute!(stdout(), LeaveAlternateScreen)?;
+    execute!(stdout(), DisableMouseCapture, LeaveAlternateScreen)?;
     disable_raw_mode()?;
     terminal.show_cursor()?;
     Ok(res?)
@@ -41,14 +67,120 @@ pub struct App {
     pub audio: InputHandler,
     pub data: FileMetadata,
     pub path_queue: Vec<PathBuf>,
-    pub prog_bar: f64,
+    /// The cursor in the playlist tab, kept within the queue by `queue_selected`.
+    pub queue_row: usize,
+    pub prog_bar: Option<f64>,
+    pub status: Option<String>,
+    /// The status message last seen by the tick and when it first appeared, so it can expire.
+    pub status_shown: Option<(String, Instant)>,
+    pub scan_rx: Option<Receiver<ScanEvent>>,
+    pub scan_errors: usize,
+    /// The index of the music directory, with the listening statistics.
+    pub library: Library,
+    /// Whether statistics changed since the library index was last saved.
+    pub library_dirty: bool,
+    pub library_saved: Instant,
+    pub rating_writer: RatingWriter,
+    /// Sends back the refreshed library index, and how many files were read.
+    pub library_rx: Option<Receiver<(Library, usize)>>,
+    pub eq_row: usize,
+    pub device_picker: Option<DevicePicker>,
+    pub tag_editor: Option<TagEditor>,
+    /// The moves previewed by the organiser, until they're applied or dismissed.
+    pub renames: Option<Vec<Rename>>,
+    pub rename_row: usize,
+    pub undo_log: UndoLog,
+    /// The tags read from file paths and the fields of the pattern, until they're written or dismissed.
+    pub guesses: Option<(Vec<TagField>, Vec<Guess>)>,
+    pub guess_row: usize,
+    pub chapters: Vec<Chapter>,
+    pub chapter_row: usize,
+    pub lyrics: Vec<LyricLine>,
+    /// First line shown of unsynced lyrics.
+    pub lyrics_scroll: usize,
+    pub bookmarks: Bookmarks,
+    /// The track whose start has been handled, i.e. chapters loaded and bookmark resumed.
+    pub started: Option<PathBuf>,
+    pub last_bookmark: Instant,
+    /// The start and end of the A-B loop on the current track.
+    pub loop_a: Option<Duration>,
+    pub loop_b: Option<Duration>,
+    pub sleep: Option<SleepTimer>,
+    /// Stops once the current track is over, then disarms.
+    pub stop_after_current: bool,
+    /// When the sleep timer was last counted down.
+    pub sleep_tick: Instant,
+    /// Level of each spectrum bin between 0 and 1, falling back slowly after peaks.
+    pub spectrum: Vec<f32>,
+    pub spectrum_rate: u32,
+    /// When the spectrum was last updated.
+    pub spectrum_tick: Instant,
+    /// Peak envelopes of queued tracks for the waveform seekbar, None while computing or if it failed.
+    pub waveforms: HashMap<PathBuf, Option<Vec<f32>>>,
+    pub waveform_tx: Sender<(PathBuf, Option<Vec<f32>>)>,
+    pub waveform_rx: Receiver<(PathBuf, Option<Vec<f32>>)>,
+    /// How cover art is drawn, with `auto` resolved for the terminal.
+    pub graphics: GraphicsProtocol,
+    /// The track whose cover was looked for, and the cover if one was found.
+    pub cover: Option<(PathBuf, Option<Image>)>,
+    pub cover_tx: Sender<(PathBuf, Option<Image>)>,
+    pub cover_rx: Receiver<(PathBuf, Option<Image>)>,
+    /// Where the terminal last drew the cover with kitty graphics or sixels, and for which track.
+    pub cover_placement: Option<(Rect, PathBuf)>,
+    /// Size of a cell in pixels, used to fit covers drawn by the terminal.
+    pub cell_pixels: (u16, u16),
+    pub prompt: Option<Prompt>,
+    /// The last stream status shown, so it's only reported when it changes.
+    pub stream_status: Option<StreamStatus>,
+    pub podcasts: Podcasts,
+    /// The feed whose episodes are listed, or None for the list of feeds.
+    pub podcast_feed: Option<usize>,
+    pub podcast_row: usize,
+    pub podcast_tx: Sender<PodcastEvent>,
+    pub podcast_rx: Receiver<PodcastEvent>,
+    /// Progress of the running downloads, keyed by episode URL.
+    pub downloads: HashMap<String, String>,
+    /// When and where the last click happened, to recognise double clicks.
+    pub last_click: Option<(Instant, Position)>,
     pub tab: Tab,
     pub state: State,
 }
 /// Current tab information.
+#[derive(PartialEq)]
 pub enum Tab {
     Playlist,
     Browser,
+    Equalizer,
+    Chapters,
+    Podcasts,
+    Visualizer,
+    NowPlaying,
+    Lyrics,
+}
+
+/// A line of text being typed, and what it's for.
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+pub enum PromptKind {
+    StreamUrl,
+    FeedUrl,
+    SleepTimer,
+    RenamePattern,
+    TagPattern,
+    SmartPlaylist,
+}
+
+/// When the sleep timer stops playback.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SleepTimer {
+    /// After this much more playing time.
+    Remaining(Duration),
+    EndOfTrack,
+    EndOfAlbum,
+    EndOfQueue,
 }
 
 /// App state.
@@ -60,58 +192,188 @@ pub enum State {
 
 impl App {
     pub fn new(initial_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
-        let music_dir = load_config().directories.music_directory;
+        let config = load_config();
+        let music_dir = config.directories.music_directory.clone();
 
         let final_dir = match music_dir.exists() {
             true => music_dir,
             false => initial_dir,
         };
 
+        let equalizer = EqControl::new(EqSettings {
+            enabled: config.equalizer.enabled,
+            name: config.equalizer.preset.clone(),
+            preset: config.equalizer.get_preset(&config.equalizer.preset),
+        });
+
+        let mut audio = InputHandler::new(equalizer, &config.playback.output_device)?;
+        audio.set_preserve_pitch(config.playback.preserve_pitch);
+        audio.set_transition_fade(Duration::from_millis(config.playback.transition_fade_ms));
+        audio.set_volume_curve(
+            config.playback.volume_curve,
+            config.playback.volume_db_range,
+            config.playback.max_volume,
+        );
+
+        let (podcast_tx, podcast_rx) = mpsc::channel();
+        let (waveform_tx, waveform_rx) = mpsc::channel();
+        let (cover_tx, cover_rx) = mpsc::channel();
+        let graphics = match config.cover_art.protocol {
+            GraphicsProtocol::Auto => detect_protocol(),
+            protocol => protocol,
+        };
+
         Ok(Self {
-            config: load_config(),
+            config,
             meta_manager: MetadataQueue::new(),
             file_browser: FileBrowser::new(final_dir),
-            audio: InputHandler::new()?,
+            audio,
             data: FileMetadata::new(),
             path_queue: Vec::new(),
-            prog_bar: 0.0,
+            queue_row: 0,
+            prog_bar: Some(0.0),
+            status: None,
+            status_shown: None,
+            scan_rx: None,
+            scan_errors: 0,
+            library: Library::load(),
+            library_dirty: false,
+            library_saved: Instant::now(),
+            rating_writer: RatingWriter::spawn(),
+            library_rx: None,
+            eq_row: 0,
+            device_picker: None,
+            tag_editor: None,
+            renames: None,
+            rename_row: 0,
+            undo_log: UndoLog::load(),
+            guesses: None,
+            guess_row: 0,
+            chapters: Vec::new(),
+            chapter_row: 0,
+            lyrics: Vec::new(),
+            lyrics_scroll: 0,
+            bookmarks: Bookmarks::load(),
+            started: None,
+            last_bookmark: Instant::now(),
+            loop_a: None,
+            loop_b: None,
+            sleep: None,
+            stop_after_current: false,
+            sleep_tick: Instant::now(),
+            spectrum: Vec::new(),
+            spectrum_rate: 44100,
+            spectrum_tick: Instant::now(),
+            waveforms: HashMap::new(),
+            waveform_tx,
+            waveform_rx,
+            graphics,
+            cover: None,
+            cover_tx,
+            cover_rx,
+            cover_placement: None,
+            cell_pixels: (8, 16),
+            prompt: None,
+            stream_status: None,
+            podcasts: Podcasts::load(),
+            podcast_feed: None,
+            podcast_row: 0,
+            podcast_tx,
+            podcast_rx,
+            downloads: HashMap::new(),
+            last_click: None,
             tab: Tab::Browser,
             state: State::Running,
         })
     }
 
+    /// Clears the status message once it has been shown for a while, so the view it replaced comes back.
+    pub fn tick_status(&mut self) {
+        match (&self.status, &self.status_shown) {
+            (None, _) => self.status_shown = None,
+            (Some(status), Some((shown, since))) if status == shown => {
+                if since.elapsed() >= STATUS_TIMEOUT {
+                    self.status = None;
+                    self.status_shown = None;
+                }
+            }
+            (Some(status), _) => self.status_shown = Some((status.clone(), Instant::now())),
+        }
+    }
+
     /// Update's the progress bar's apperance.
     ///
     /// Displays in milliseconds / milliseconds for higher resolution seekbar.Originally intended for gauge's use_unicode(), but it's being kept in case I decide to go back to gauge.
+    /// The ratio is None when the duration of the current track is unknown (e.g. streams).
     pub fn update_prog_bar(&mut self) {
         if self.audio.is_empty() {
-            self.prog_bar = 0.0;
+            self.prog_bar = Some(0.0);
             return;
         }
-        self.prog_bar = (self.audio.sink_pos_millis() as f64
-            / (self.data.duration_as_secs.unwrap() * 1000.0))
-            .clamp(0.0, 1.0);
+        self.prog_bar = self
+            .data
+            .duration_as_secs
+            .filter(|duration| *duration > 0.0)
+            .map(|duration| {
+                (self.audio.sink_pos_millis() as f64 / (duration * 1000.0)).clamp(0.0, 1.0)
+            });
     }
 
     /// Renders the tui.
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
         let update_interval = Duration::from_millis(250);
+        let mut last_update = Instant::now();
         while self.state == State::Running {
             let loop_start = Instant::now();
+            // The visualizer redraws more often than the rest is updated.
+            let frame_interval = match self.tab {
+                Tab::Visualizer => {
+                    Duration::from_secs(1) / self.config.visualizer.frame_rate.clamp(1, 120)
+                }
+                _ => update_interval,
+            };
 
-            while loop_start.elapsed() < update_interval {
+            let mut handled = false;
+            while loop_start.elapsed() < frame_interval {
                 if poll(Duration::from_millis(0))? {
                     self.handle_events()?;
+                    handled = true;
                     break;
                 }
                 // Sleep to avoid busy waiting
                 sleep(Duration::from_millis(1));
             }
 
-            self.file_browser.update_entries()?;
-            self.update_prog_bar();
+            if handled || last_update.elapsed() >= update_interval {
+                last_update = Instant::now();
+                self.file_browser.update_entries()?;
+                self.sync_queue();
+                self.sync_stream();
+                self.tick_bookmarks();
+                self.tick_sleep();
+                self.tick_library();
+                self.tick_status();
+                self.update_prog_bar();
+                self.poll_scan();
+                self.poll_library();
+                self.poll_ratings();
+                self.poll_podcasts();
+                self.poll_waveforms();
+                self.poll_covers();
+                self.check_output();
+            }
+            if self.tab == Tab::Visualizer {
+                self.update_spectrum();
+            }
+            let placement =
+                self.cover_placement_in(Rect::from((Position::ORIGIN, terminal.size()?)));
             terminal.draw(|frame| self.draw(frame))?;
+            self.draw_cover_graphics(placement)?;
         }
+        self.draw_cover_graphics(None)?;
+        self.leave_track(false);
+        self.flush_library();
+        self.rating_writer.finish();
         Ok(())
     }
 }
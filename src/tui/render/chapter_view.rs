@@ -0,0 +1,33 @@
+use crate::tui::{handle_events::chapter_funcs::format_position, render::app::App};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+};
+
+impl App {
+    /// Lists the chapters of the current file with their start times; the chapter being played is bold.
+    pub fn chapter_items(&self) -> Vec<ListItem<'_>> {
+        let timestamp = self.get_color(&self.config.colors.timestamp);
+        let title = self.get_color(&self.config.colors.title);
+        let current = self.current_chapter();
+
+        self.chapters
+            .iter()
+            .enumerate()
+            .map(|(index, chapter)| {
+                let style = match Some(index) == current {
+                    true => Style::default().add_modifier(Modifier::BOLD),
+                    false => Style::default(),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:>9}  ", format_position(chapter.start)),
+                        style.fg(timestamp),
+                    ),
+                    Span::styled(chapter.title.clone(), style.fg(title)),
+                ]))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,445 @@
+use crate::{
+    data::{config::GraphicsProtocol, cover::Image},
+    tui::render::{
+        app::{App, Tab},
+        display::Areas,
+    },
+};
+use crossterm::{cursor::MoveTo, queue, style::Print, terminal::window_size};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Margin, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget, Wrap},
+};
+use std::{
+    env,
+    io::{self, Write},
+};
+
+/// Kitty image id of the cover, so it can be replaced and deleted.
+const KITTY_ID: u32 = 4207;
+/// Pixel size of a cell when the terminal doesn't report one.
+const CELL_PIXELS: (u16, u16) = (8, 16);
+/// Base64 alphabet used for the kitty image data.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Picks the graphics protocol of the terminal from its environment. Inside tmux or screen only half-blocks get through.
+pub fn detect_protocol() -> GraphicsProtocol {
+    detect(|name| env::var(name).ok())
+}
+
+fn detect(var: impl Fn(&str) -> Option<String>) -> GraphicsProtocol {
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    if var("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+        GraphicsProtocol::Halfblocks
+    } else if var("KITTY_WINDOW_ID").is_some()
+        || ["xterm-kitty", "xterm-ghostty"].contains(&term.as_str())
+        || ["WezTerm", "ghostty"].contains(&program.as_str())
+    {
+        GraphicsProtocol::Kitty
+    } else if ["foot", "mlterm", "contour"]
+        .iter()
+        .any(|name| term.starts_with(name))
+        || term.contains("sixel")
+        || program == "iTerm.app"
+    {
+        GraphicsProtocol::Sixel
+    } else {
+        GraphicsProtocol::Halfblocks
+    }
+}
+
+/// Returns the size of a cell in pixels, as reported by the terminal.
+fn cell_pixels() -> (u16, u16) {
+    match window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            (size.width / size.columns, size.height / size.rows)
+        }
+        _ => CELL_PIXELS,
+    }
+}
+
+/// Returns the cells a picture covers once fitted into an area and centred, given the pixel size of a cell.
+pub fn placement(image: &Image, area: Rect, cell: (u16, u16)) -> Rect {
+    let (cell_width, cell_height) = (cell.0.max(1) as f64, cell.1.max(1) as f64);
+    let scale = (area.width as f64 * cell_width / image.width as f64)
+        .min(area.height as f64 * cell_height / image.height as f64);
+    let width = ((image.width as f64 * scale / cell_width).round() as u16).clamp(1, area.width);
+    let height = ((image.height as f64 * scale / cell_height).round() as u16).clamp(1, area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Draws a picture with "▀", the upper pixel of each cell as its foreground and the lower one as its background.
+pub struct HalfBlocks<'a>(pub &'a Image);
+
+impl Widget for HalfBlocks<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+        let image = self.0.fit(area.width as usize, area.height as usize * 2);
+        let rows = image.height.div_ceil(2) as u16;
+        let left = area.x + (area.width - image.width as u16) / 2;
+        let top = area.y + (area.height - rows) / 2;
+        let rgb = |[r, g, b]: [u8; 3]| Color::Rgb(r, g, b);
+        for row in 0..rows as usize {
+            for column in 0..image.width {
+                let cell = &mut buf[(left + column as u16, top + row as u16)];
+                cell.set_symbol("▀")
+                    .set_fg(rgb(image.pixel(column, row * 2)));
+                match row * 2 + 1 < image.height {
+                    true => cell.set_bg(rgb(image.pixel(column, row * 2 + 1))),
+                    false => cell.set_bg(Color::Reset),
+                };
+            }
+        }
+    }
+}
+
+/// Leaves cells to a picture drawn by the terminal, so they aren't overwritten.
+struct Reserved;
+
+impl Widget for Reserved {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf[(x, y)].set_skip(true);
+            }
+        }
+    }
+}
+
+/// Encodes RGB pixels for the kitty graphics protocol, split into chunks of at most 4096 bytes.
+/// The picture is scaled by the terminal to cover the columns and rows of the placement.
+pub fn kitty_image(image: &Image, placement: Rect) -> String {
+    let pixels: Vec<u8> = image.pixels.iter().flatten().copied().collect();
+    let data = base64(&pixels);
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(4096)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect();
+    let mut out = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = (index + 1 < chunks.len()) as u8;
+        match index {
+            0 => out.push_str(&format!(
+                "\x1b_Ga=T,f=24,s={},v={},c={},r={},i={},C=1,q=2,m={};{}\x1b\\",
+                image.width, image.height, placement.width, placement.height, KITTY_ID, more, chunk
+            )),
+            _ => out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk)),
+        }
+    }
+    out
+}
+
+/// Encodes a picture as sixels, with its colours reduced to a 6×6×6 cube.
+pub fn sixel_image(image: &Image) -> String {
+    let level = |channel: u8| (channel as u16 * 5 + 127) / 255;
+    let indices: Vec<u16> = image
+        .pixels
+        .iter()
+        .map(|[r, g, b]| level(*r) * 36 + level(*g) * 6 + level(*b))
+        .collect();
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", image.width, image.height);
+    let mut used = [false; 216];
+    for index in &indices {
+        used[*index as usize] = true;
+    }
+    for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let percent = |level: usize| level * 100 / 5;
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            index,
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        ));
+    }
+
+    for band in (0..image.height).step_by(6) {
+        let rows = (image.height - band).min(6);
+        let mut colours: Vec<u16> = (band..band + rows)
+            .flat_map(|y| {
+                indices[y * image.width..(y + 1) * image.width]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        colours.sort_unstable();
+        colours.dedup();
+        for (pass, colour) in colours.iter().enumerate() {
+            if pass > 0 {
+                out.push('$');
+            }
+            out.push_str(&format!("#{}", colour));
+            let sixels: Vec<u8> = (0..image.width)
+                .map(|x| {
+                    (0..rows)
+                        .filter(|row| indices[(band + row) * image.width + x] == *colour)
+                        .fold(0, |bits, row| bits | 1 << row)
+                })
+                .collect();
+            // Runs of the same sixel are written once with a repeat count.
+            let mut x = 0;
+            while x < sixels.len() {
+                let run = sixels[x..].iter().take_while(|s| **s == sixels[x]).count();
+                let symbol = (63 + sixels[x]) as char;
+                match run {
+                    1..=3 => out.extend(std::iter::repeat_n(symbol, run)),
+                    _ => out.push_str(&format!("!{}{}", run, symbol)),
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Encodes bytes as standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, byte)| {
+            value | (*byte as u32) << (16 - i * 8)
+        });
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(BASE64[(value >> (18 - i * 6)) as usize & 63] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+impl App {
+    /// Splits the middle of the now playing tab between the cover and the track details.
+    pub fn now_playing_areas(&self, inner: Rect) -> [Rect; 2] {
+        let cover_width = match self.graphics {
+            GraphicsProtocol::Off => 0,
+            _ => (inner.height * 2).min(inner.width / 2),
+        };
+        Layout::horizontal([Constraint::Length(cover_width), Constraint::Min(0)])
+            .spacing(2)
+            .areas(inner)
+    }
+
+    /// Draws the cover, or reserves its cells when the terminal draws it.
+    pub fn render_cover(&self, area: Rect, buf: &mut Buffer) {
+        let Some(image) = self.current_cover() else {
+            return;
+        };
+        match self.graphics {
+            GraphicsProtocol::Kitty | GraphicsProtocol::Sixel
+                if self.prompt.is_none()
+                    && self.device_picker.is_none()
+                    && self.tag_editor.is_none()
+                    && self.renames.is_none()
+                    && self.guesses.is_none() =>
+            {
+                Reserved.render(placement(image, area, self.cell_pixels), buf)
+            }
+            GraphicsProtocol::Kitty | GraphicsProtocol::Sixel | GraphicsProtocol::Off => {}
+            _ => HalfBlocks(image).render(area, buf),
+        }
+    }
+
+    /// Lists the details of the track being played next to the cover.
+    pub fn now_playing_details(&self) -> impl Widget {
+        let line = |text: String, color: &str| {
+            Line::from(vec![Span::styled(
+                text,
+                Style::default().fg(self.get_color(color)),
+            )])
+        };
+        let colors = &self.config.colors;
+        let lines = match self.audio.is_empty() {
+            true => vec![line("nothing playing".to_string(), &colors.status)],
+            false => vec![
+                line(self.data.display_title(), &colors.title),
+                line(self.data.display_artist(), &colors.artist),
+                line(self.data.display_album(), &colors.album),
+                Line::from(vec![
+                    Span::styled(
+                        self.data.display_year(),
+                        Style::default().fg(self.get_color(&colors.year)),
+                    ),
+                    Span::from(" "),
+                    Span::styled(
+                        self.data.display_track_number(),
+                        Style::default().fg(self.get_color(&colors.track_num)),
+                    ),
+                ]),
+                line(self.data.display_duration_display(), &colors.timestamp),
+            ],
+        };
+        Paragraph::new(lines).wrap(Wrap { trim: true })
+    }
+
+    /// Draws the cover with kitty graphics or sixels once its cells are reserved, and removes it once they aren't.
+    /// Nothing is written while the picture and its place stay the same.
+    pub fn draw_cover_graphics(&mut self, placement: Option<Rect>) -> io::Result<()> {
+        let placement = match (self.graphics, self.current_cover(), &self.started) {
+            (GraphicsProtocol::Kitty | GraphicsProtocol::Sixel, Some(_), Some(path)) => {
+                placement.map(|placement| (placement, path.clone()))
+            }
+            _ => None,
+        };
+        if placement == self.cover_placement {
+            return Ok(());
+        }
+        let mut stdout = io::stdout();
+        if self.graphics == GraphicsProtocol::Kitty && self.cover_placement.is_some() {
+            queue!(
+                stdout,
+                Print(format!("\x1b_Ga=d,d=i,i={},q=2\x1b\\", KITTY_ID))
+            )?;
+        }
+        self.cover_placement = placement.clone();
+        if let (Some((placement, _)), Some(image)) = (placement, self.current_cover()) {
+            let (cell_width, cell_height) = self.cell_pixels;
+            let image = image.fit(
+                (placement.width * cell_width) as usize,
+                (placement.height * cell_height) as usize,
+            );
+            // The cells are blanked first, since ratatui leaves whatever was there before.
+            let blank = " ".repeat(placement.width as usize);
+            for row in placement.top()..placement.bottom() {
+                queue!(stdout, MoveTo(placement.x, row), Print(&blank))?;
+            }
+            queue!(
+                stdout,
+                MoveTo(placement.x, placement.y),
+                Print(match self.graphics {
+                    GraphicsProtocol::Kitty => kitty_image(&image, placement),
+                    _ => sixel_image(&image),
+                })
+            )?;
+        }
+        stdout.flush()
+    }
+
+    /// Returns where the cover is placed on a screen of this size, if it's drawn by the terminal.
+    pub fn cover_placement_in(&mut self, screen: Rect) -> Option<Rect> {
+        self.cell_pixels = cell_pixels();
+        if self.tab != Tab::NowPlaying
+            || self.prompt.is_some()
+            || self.device_picker.is_some()
+            || self.tag_editor.is_some()
+            || self.renames.is_some()
+            || self.guesses.is_some()
+        {
+            return None;
+        }
+        // Inside the border and padding of the middle block.
+        let inner = Areas::new(screen).middle.inner(Margin::new(2, 1));
+        let [cover, _] = self.now_playing_areas(inner);
+        Some(placement(self.current_cover()?, cover, self.cell_pixels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    #[test]
+    fn half_blocks_render_two_pixels_per_cell() {
+        // Red over blue on the left, green over white on the right, and a last row of grey.
+        let image = Image {
+            width: 2,
+            height: 3,
+            pixels: vec![
+                [255, 0, 0],
+                [0, 255, 0],
+                [0, 0, 255],
+                [255, 255, 255],
+                [9, 9, 9],
+                [9, 9, 9],
+            ],
+        };
+        let mut terminal = Terminal::new(TestBackend::new(2, 3)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(HalfBlocks(&image), frame.area()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+
+        // 3 rows of pixels take 2 of the 3 rows of cells, centred.
+        let cell = &buffer[(0, 0)];
+        assert_eq!(cell.symbol(), "▀");
+        assert_eq!(
+            (cell.fg, cell.bg),
+            (Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255))
+        );
+        let cell = &buffer[(1, 0)];
+        assert_eq!(
+            (cell.fg, cell.bg),
+            (Color::Rgb(0, 255, 0), Color::Rgb(255, 255, 255))
+        );
+        let cell = &buffer[(1, 1)];
+        assert_eq!((cell.fg, cell.bg), (Color::Rgb(9, 9, 9), Color::Reset));
+        assert_eq!(buffer[(0, 2)].symbol(), " ");
+    }
+
+    #[test]
+    fn detects_the_protocol_from_the_environment() {
+        let detect_with = |vars: &[(&str, &str)]| {
+            detect(|name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(
+            detect_with(&[("TERM", "xterm-kitty")]),
+            GraphicsProtocol::Kitty
+        );
+        assert_eq!(
+            detect_with(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")]),
+            GraphicsProtocol::Kitty
+        );
+        assert_eq!(detect_with(&[("TERM", "foot")]), GraphicsProtocol::Sixel);
+        assert_eq!(
+            detect_with(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")]),
+            GraphicsProtocol::Halfblocks
+        );
+        assert_eq!(
+            detect_with(&[("TERM", "xterm")]),
+            GraphicsProtocol::Halfblocks
+        );
+    }
+
+    #[test]
+    fn encodes_kitty_and_sixel_images() {
+        assert_eq!(base64(b"rmpr"), "cm1wcg==");
+        assert_eq!(base64(b"cover"), "Y292ZXI=");
+        assert_eq!(base64(b"art"), "YXJ0");
+
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![[255, 0, 0]; 4],
+        };
+        let kitty = kitty_image(&image, Rect::new(0, 0, 1, 1));
+        assert!(kitty.starts_with("\x1b_Ga=T,f=24,s=2,v=2,c=1,r=1,"));
+        assert!(kitty.ends_with(";/wAA/wAA/wAA/wAA\x1b\\"));
+
+        // Red is entry 180 of the cube, both columns get the two upper sixels.
+        assert_eq!(
+            sixel_image(&image),
+            "\x1bP0;1;0q\"1;1;2;2#180;2;100;0;0#180BB-\x1b\\"
+        );
+    }
+}
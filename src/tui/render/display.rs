@@ -1,17 +1,58 @@
-use super::app::Tab;
-use crate::tui::render::app::App;
+use super::app::{PromptKind, Tab};
+use crate::{
+    data::{config::GraphicsProtocol, metadata::tag_fields::TagField, organiser::RenameStatus},
+    tui::render::app::App,
+};
 use ratatui::{
     Frame,
-    layout::{Alignment, Constraint, Layout, Margin},
+    layout::{Alignment, Constraint, Layout, Margin, Rect},
     style::{Color, Style},
     symbols::scrollbar,
     text::{Line, Span},
     widgets::{
-        Block, BorderType, Borders, List, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
-        ScrollbarState,
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Padding, Paragraph,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, TableState,
     },
 };
-use std::{path::PathBuf, str::FromStr};
+use std::{path::Path, str::FromStr};
+
+/// Where each part of the interface is drawn, shared with the mouse handling.
+pub struct Areas {
+    pub top_left: Rect,
+    pub top_center: Rect,
+    pub top_right: Rect,
+    pub info: Rect,
+    pub middle: Rect,
+    pub bottom: Rect,
+}
+
+impl Areas {
+    pub fn new(area: Rect) -> Self {
+        let [top, info, middle, bottom] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let [top_left, top_center, top_right] = Layout::horizontal([
+            Constraint::Length(15),
+            Constraint::Min(0),
+            Constraint::Length(15),
+        ])
+        .areas(top);
+
+        Self {
+            top_left,
+            top_center,
+            top_right,
+            info,
+            middle,
+            bottom,
+        }
+    }
+}
 
 impl App {
     /// Shortens the code necessary to set the color of a terminal element.
@@ -20,7 +61,7 @@ impl App {
     }
 
     /// Turns '/home/USER' into '~' when displaying a path.
-    pub fn format_display_path(&self, path: &PathBuf) -> String {
+    pub fn format_display_path(&self, path: &Path) -> String {
         let current_dir = path.to_string_lossy().to_string();
         if let Some(home) = dirs::home_dir() {
             let home_str = home.to_string_lossy();
@@ -47,20 +88,14 @@ impl App {
         //│ LAYOUT │
         //└────────┘
 
-        let [top, info, middle, bottom] = Layout::vertical([
-            Constraint::Length(4),
-            Constraint::Length(1),
-            Constraint::Min(0),
-            Constraint::Length(1),
-        ])
-        .areas(frame.area());
-
-        let [top_left, top_center, top_right] = Layout::horizontal([
-            Constraint::Length(15),
-            Constraint::Min(0),
-            Constraint::Length(15),
-        ])
-        .areas(top);
+        let Areas {
+            top_left,
+            top_center,
+            top_right,
+            info,
+            middle,
+            bottom,
+        } = Areas::new(frame.area());
 
         //┌────────────┐
         //│ BLOCK VARS │
@@ -83,7 +118,23 @@ impl App {
         // TOP RIGHT
         frame.render_widget(self.top_right(), top_right);
         // PROGRESS BAR
-        frame.render_widget(self.progress_bar(), bottom);
+        match self.prog_bar {
+            Some(_) => {
+                match self.current_waveform() {
+                    Some(envelope) => {
+                        frame.render_widget(self.waveform_bar(envelope, bottom.width), bottom)
+                    }
+                    None => frame.render_widget(self.progress_bar(), bottom),
+                }
+                // Loop points are drawn over the bar
+                for column in self.loop_marker_columns(bottom.width) {
+                    frame.buffer_mut()[(bottom.x + column, bottom.y)]
+                        .set_symbol("│")
+                        .set_fg(self.get_color(highlight_color));
+                }
+            }
+            None => frame.render_widget(self.indeterminate_bar(bottom.width), bottom),
+        }
 
         match self.tab {
             Tab::Playlist => {
@@ -124,18 +175,19 @@ impl App {
                     }
                 }
                 // MIDDLE
-                frame.render_widget(
-                    Paragraph::new("queue info here")
-                        .centered()
-                        .block(middle_block),
+                frame.render_stateful_widget(
+                    List::new(self.queue_items())
+                        .block(middle_block)
+                        .highlight_style(Style::default().fg(self.get_color(highlight_color))),
                     middle,
+                    &mut ListState::default().with_selected(self.queue_selected()),
                 );
             }
             Tab::Browser => {
                 // STATUS
                 frame.render_widget(
                     Paragraph::new(Line::from(vec![Span::styled(
-                        format!("{}", display_path),
+                        self.status.clone().unwrap_or(display_path),
                         Style::default().fg(self.get_color(status)),
                     )]))
                     .block(Block::new())
@@ -143,7 +195,7 @@ impl App {
                     info,
                 );
                 frame.render_stateful_widget(
-                    List::new(self.file_browser.list_items())
+                    List::new(self.file_browser.list_items(&self.library))
                         .block(middle_block)
                         .highlight_style(Style::default().fg(self.get_color(highlight_color))),
                     middle,
@@ -164,6 +216,337 @@ impl App {
                         .position(self.file_browser.selected),
                 );
             }
+            Tab::Equalizer => {
+                let settings = self.audio.equalizer.settings();
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        format!(
+                            "equalizer: {} ({})",
+                            settings.name,
+                            match settings.enabled {
+                                true => "on",
+                                false => "off",
+                            }
+                        ),
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                frame.render_stateful_widget(
+                    List::new(self.eq_items())
+                        .block(middle_block)
+                        .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                    middle,
+                    &mut ListState::default().with_selected(Some(self.eq_row)),
+                );
+            }
+            Tab::Chapters => {
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match (&self.status, self.chapters.len()) {
+                            (Some(status), _) => status.clone(),
+                            (None, 0) => "no chapters".to_string(),
+                            (None, 1) => "1 chapter".to_string(),
+                            (None, len) => format!("{} chapters", len),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                frame.render_stateful_widget(
+                    List::new(self.chapter_items())
+                        .block(middle_block)
+                        .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                    middle,
+                    &mut ListState::default()
+                        .with_selected((!self.chapters.is_empty()).then_some(self.chapter_row)),
+                );
+            }
+            Tab::Podcasts => {
+                let feed = self
+                    .podcast_feed
+                    .and_then(|index| self.podcasts.feeds.get(index));
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match (&self.status, feed, self.podcasts.feeds.len()) {
+                            (Some(status), _, _) => status.clone(),
+                            (None, Some(feed), _) => feed.title.clone(),
+                            (None, None, 0) => "no podcasts, press n to subscribe".to_string(),
+                            (None, None, 1) => "1 podcast".to_string(),
+                            (None, None, len) => format!("{} podcasts", len),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                let items = self.podcast_items();
+                let selected = (!items.is_empty()).then_some(self.podcast_row);
+                frame.render_stateful_widget(
+                    List::new(items)
+                        .block(middle_block)
+                        .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                    middle,
+                    &mut ListState::default().with_selected(selected),
+                );
+            }
+            Tab::Visualizer => {
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match (&self.status, self.visualizer_active()) {
+                            (Some(status), _) => status.clone(),
+                            (None, true) => format!("{} Hz", self.spectrum_rate),
+                            (None, false) => "nothing playing".to_string(),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                let inner = middle_block.inner(middle);
+                frame.render_widget(middle_block, middle);
+                let [spectrum, scope] =
+                    Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)])
+                        .areas(inner);
+                frame.render_widget(self.spectrum_chart(spectrum.width), spectrum);
+                frame.render_widget(self.oscilloscope(), scope);
+            }
+            Tab::NowPlaying => {
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match (&self.status, self.graphics, self.current_cover()) {
+                            (Some(status), _, _) => status.clone(),
+                            (None, GraphicsProtocol::Off, _) => "cover art is off".to_string(),
+                            (None, graphics, Some(_)) => {
+                                format!("cover art: {:?}", graphics).to_lowercase()
+                            }
+                            (None, _, None) => "no cover art".to_string(),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                let inner = middle_block.inner(middle);
+                frame.render_widget(middle_block, middle);
+                let [cover, details] = self.now_playing_areas(inner);
+                self.render_cover(cover, frame.buffer_mut());
+                frame.render_widget(self.now_playing_details(), details);
+            }
+            Tab::Lyrics => {
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match (&self.status, self.lyrics.is_empty(), self.lyrics_synced()) {
+                            (Some(status), _, _) => status.clone(),
+                            (None, true, _) => "no lyrics".to_string(),
+                            (None, false, true) => "synced lyrics".to_string(),
+                            (None, false, false) => "lyrics".to_string(),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                let inner = middle_block.inner(middle);
+                frame.render_widget(middle_block, middle);
+                frame.render_widget(self.lyrics_view(inner.height), inner);
+            }
+        }
+
+        // PROMPT
+        if let Some(prompt) = &self.prompt {
+            let [_, popup, _] = Layout::horizontal([
+                Constraint::Percentage(15),
+                Constraint::Percentage(70),
+                Constraint::Percentage(15),
+            ])
+            .areas(middle);
+            let [_, popup, _] = Layout::vertical([
+                Constraint::Min(0),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .areas(popup);
+            frame.render_widget(Clear, popup);
+            frame.render_widget(
+                Paragraph::new(format!("{}_", prompt.input)).block(
+                    Block::new()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(self.get_color(border)))
+                        .border_type(BorderType::Rounded)
+                        .padding(Padding::horizontal(1))
+                        .title(match prompt.kind {
+                            PromptKind::StreamUrl => " stream url ",
+                            PromptKind::FeedUrl => " feed url ",
+                            PromptKind::SleepTimer => " sleep: minutes, track, album or queue ",
+                            PromptKind::RenamePattern => " rename to pattern ",
+                            PromptKind::TagPattern => " read tags from paths with pattern ",
+                            PromptKind::SmartPlaylist => {
+                                " query to queue, or name = query to save "
+                            }
+                        })
+                        .title_alignment(Alignment::Center),
+                ),
+                popup,
+            );
+        }
+
+        // DEVICE PICKER
+        if let Some(picker) = &self.device_picker {
+            let [_, popup, _] = Layout::horizontal([
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ])
+            .areas(middle);
+            let [_, popup, _] = Layout::vertical([
+                Constraint::Min(0),
+                Constraint::Length(picker.devices.len() as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .areas(popup);
+            let items: Vec<ListItem> = picker
+                .devices
+                .iter()
+                .map(|device| match *device == self.audio.device {
+                    true => ListItem::new(format!("* {}", device)),
+                    false => ListItem::new(device.as_str()),
+                })
+                .collect();
+            frame.render_widget(Clear, popup);
+            frame.render_stateful_widget(
+                List::new(items)
+                    .block(
+                        Block::new()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(self.get_color(border)))
+                            .border_type(BorderType::Rounded)
+                            .title(" output device ")
+                            .title_alignment(Alignment::Center),
+                    )
+                    .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                popup,
+                &mut ListState::default().with_selected(Some(picker.selected)),
+            );
+        }
+
+        // RENAME PREVIEW
+        if let Some(renames) = &self.renames {
+            let popup = middle.inner(Margin::new(middle.width / 10, 1));
+            let collisions = renames
+                .iter()
+                .filter(|rename| rename.status == RenameStatus::Collision)
+                .count();
+            frame.render_widget(Clear, popup);
+            frame.render_stateful_widget(
+                List::new(self.rename_items(renames))
+                    .block(
+                        Block::new()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(self.get_color(border)))
+                            .border_type(BorderType::Rounded)
+                            .padding(Padding::horizontal(1))
+                            .title(match collisions {
+                                0 => format!(" rename {} files ", renames.len()),
+                                _ => format!(
+                                    " rename {} files, {} collisions skipped ",
+                                    renames.len(),
+                                    collisions
+                                ),
+                            })
+                            .title_alignment(Alignment::Center)
+                            .title_bottom(Line::from(" w apply, esc cancel ").centered()),
+                    )
+                    .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                popup,
+                &mut ListState::default().with_selected(Some(self.rename_row)),
+            );
+        }
+
+        // TAGS FROM PATHS
+        if let Some((fields, guesses)) = &self.guesses {
+            let popup = middle.inner(Margin::new(middle.width / 10, 1));
+            let matched = guesses
+                .iter()
+                .filter(|guess| guess.fields.is_some())
+                .count();
+            frame.render_widget(Clear, popup);
+            frame.render_stateful_widget(
+                self.guess_table(fields, guesses)
+                    .block(
+                        Block::new()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(self.get_color(border)))
+                            .border_type(BorderType::Rounded)
+                            .padding(Padding::horizontal(1))
+                            .title(format!(
+                                " tags from paths: {} of {} files matched ",
+                                matched,
+                                guesses.len()
+                            ))
+                            .title_alignment(Alignment::Center)
+                            .title_bottom(Line::from(" w write, esc cancel ").centered()),
+                    )
+                    .row_highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                popup,
+                &mut TableState::default().with_selected(Some(self.guess_row)),
+            );
+        }
+
+        // TAG EDITOR
+        if let Some(editor) = &self.tag_editor {
+            let [_, popup, _] = Layout::horizontal([
+                Constraint::Percentage(15),
+                Constraint::Percentage(70),
+                Constraint::Percentage(15),
+            ])
+            .areas(middle);
+            let [_, popup, _] = Layout::vertical([
+                Constraint::Min(0),
+                Constraint::Length(TagField::ALL.len() as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .areas(popup);
+            frame.render_widget(Clear, popup);
+            frame.render_stateful_widget(
+                List::new(self.tag_items(editor))
+                    .block(
+                        Block::new()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(self.get_color(border)))
+                            .border_type(BorderType::Rounded)
+                            .padding(Padding::horizontal(1))
+                            .title(match editor.paths.len() {
+                                1 => " tags of 1 file ".to_string(),
+                                count => format!(" tags of {} files ", count),
+                            })
+                            .title_alignment(Alignment::Center)
+                            .title_bottom(Line::from(" enter edit, x revert, w write ").centered()),
+                    )
+                    .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                popup,
+                &mut ListState::default().with_selected(Some(editor.selected)),
+            );
         }
     }
 }
@@ -0,0 +1,89 @@
+use crate::{
+    handlers::dsp::equalizer::BAND_FREQUENCIES,
+    tui::{
+        handle_events::eq_funcs::{EQ_LIMIT, EQ_ROWS},
+        render::app::App,
+    },
+};
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+    widgets::ListItem,
+};
+
+/// Cells on each side of the centre of a gain bar.
+const BAR_HALF_WIDTH: usize = 12;
+
+impl App {
+    /// Formats a band's centre frequency, e.g. "125 Hz" or "16 kHz".
+    fn band_label(frequency: f32) -> String {
+        match frequency >= 1000.0 {
+            true => format!("{} kHz", frequency / 1000.0),
+            false => format!("{} Hz", frequency),
+        }
+    }
+
+    /// Draws a gain as a bar growing left or right from the centre.
+    fn gain_bar(&self, gain: f32) -> Vec<Span<'static>> {
+        let filled = self.get_color(&self.config.colors.seekbar_filled);
+        let unfilled = self.get_color(&self.config.colors.seekbar_unfilled);
+        let cells = ((gain.abs() / EQ_LIMIT) * BAR_HALF_WIDTH as f32).round() as usize;
+        let cells = cells.min(BAR_HALF_WIDTH);
+        let (left, right) = match gain < 0.0 {
+            true => (cells, 0),
+            false => (0, cells),
+        };
+
+        vec![
+            Span::styled(
+                "─".repeat(BAR_HALF_WIDTH - left),
+                Style::default().fg(unfilled),
+            ),
+            Span::styled("━".repeat(left), Style::default().fg(filled)),
+            Span::styled("┼", Style::default().fg(unfilled)),
+            Span::styled("━".repeat(right), Style::default().fg(filled)),
+            Span::styled(
+                "─".repeat(BAR_HALF_WIDTH - right),
+                Style::default().fg(unfilled),
+            ),
+        ]
+    }
+
+    /// Lists the equalizer rows: preamp, the bands, and both shelves, each with its gain bar.
+    pub fn eq_items(&self) -> Vec<ListItem<'static>> {
+        let preset = self.audio.equalizer.settings().preset;
+        let status = self.get_color(&self.config.colors.status);
+        let volume = self.get_color(&self.config.colors.volume);
+
+        (0..EQ_ROWS)
+            .map(|row| {
+                let (label, gain) = match row {
+                    0 => ("preamp".to_string(), preset.preamp),
+                    1..=10 => (
+                        App::band_label(BAND_FREQUENCIES[row - 1]),
+                        preset.bands[row - 1],
+                    ),
+                    11 => (
+                        "low shelf".to_string(),
+                        preset.low_shelf.map_or(0.0, |shelf| shelf.gain),
+                    ),
+                    _ => (
+                        "high shelf".to_string(),
+                        preset.high_shelf.map_or(0.0, |shelf| shelf.gain),
+                    ),
+                };
+
+                let mut spans = vec![Span::styled(
+                    format!("{:>10}  ", label),
+                    Style::default().fg(status),
+                )];
+                spans.extend(self.gain_bar(gain));
+                spans.push(Span::styled(
+                    format!("  {:+.1} dB", gain),
+                    Style::default().fg(volume),
+                ));
+                ListItem::new(Line::from(spans))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+use crate::tui::render::app::App;
+use ratatui::{
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Paragraph, Widget, Wrap},
+};
+
+impl App {
+    /// Draws the lyrics. Synced ones keep the line being sung highlighted in the middle of the view,
+    /// unsynced ones are plain text scrolled by hand.
+    pub fn lyrics_view(&self, height: u16) -> impl Widget {
+        let highlight = self.get_color(&self.config.colors.highlight_color);
+        match self.lyrics_synced() {
+            true => {
+                let current = self.current_lyric();
+                let lines: Vec<Line> = self
+                    .lyrics
+                    .iter()
+                    .enumerate()
+                    .map(|(index, line)| match Some(index) == current {
+                        true => Line::styled(
+                            line.text.clone(),
+                            Style::default().fg(highlight).add_modifier(Modifier::BOLD),
+                        ),
+                        false => Line::from(line.text.clone()),
+                    })
+                    .collect();
+                let scroll = current.unwrap_or(0).saturating_sub(height as usize / 2);
+                Paragraph::new(lines).centered().scroll((scroll as u16, 0))
+            }
+            false => {
+                let lines: Vec<Line> = self
+                    .lyrics
+                    .iter()
+                    .map(|line| Line::from(line.text.clone()))
+                    .collect();
+                Paragraph::new(lines)
+                    .centered()
+                    .wrap(Wrap { trim: true })
+                    .scroll((self.lyrics_scroll as u16, 0))
+            }
+        }
+    }
+}
@@ -1,4 +1,12 @@
 pub mod app;
+pub mod chapter_view;
+pub mod cover_view;
 pub mod display;
+pub mod eq_view;
+pub mod lyrics_view;
+pub mod podcast_view;
+pub mod queue_view;
+pub mod rename_view;
+pub mod tag_view;
+pub mod visualizer_view;
 pub mod widget_funcs;
-
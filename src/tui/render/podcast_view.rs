@@ -0,0 +1,76 @@
+use crate::tui::{handle_events::chapter_funcs::format_position, render::app::App};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+};
+use std::time::Duration;
+
+impl App {
+    /// Lists the feeds with their unplayed counts, or the episodes of the open feed.
+    /// Unplayed episodes are bold; downloads show their progress or a mark once complete.
+    pub fn podcast_items(&self) -> Vec<ListItem<'_>> {
+        let timestamp = self.get_color(&self.config.colors.timestamp);
+        let title = self.get_color(&self.config.colors.title);
+        let status = self.get_color(&self.config.colors.status);
+
+        let Some(feed) = self
+            .podcast_feed
+            .and_then(|index| self.podcasts.feeds.get(index))
+        else {
+            return self
+                .podcasts
+                .feeds
+                .iter()
+                .map(|feed| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            format!("{:>4}  ", feed.unplayed()),
+                            Style::default().fg(status),
+                        ),
+                        Span::styled(feed.title.clone(), Style::default().fg(title)),
+                    ]))
+                })
+                .collect();
+        };
+
+        feed.episodes
+            .iter()
+            .map(|episode| {
+                let style = match episode.played {
+                    true => Style::default(),
+                    false => Style::default().add_modifier(Modifier::BOLD),
+                };
+                let download = match self.downloads.get(&episode.url) {
+                    Some(progress) => format!("  [{}]", progress),
+                    None if episode.file.as_ref().is_some_and(|file| file.exists()) => {
+                        "  [downloaded]".to_string()
+                    }
+                    None => String::new(),
+                };
+                let position = match episode.position {
+                    0 => String::new(),
+                    millis => format!(" @{}", format_position(Duration::from_millis(millis))),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:>10}  ", episode.published.as_deref().unwrap_or("")),
+                        style.fg(timestamp),
+                    ),
+                    Span::styled(
+                        format!(
+                            "{:>8}  ",
+                            episode
+                                .duration
+                                .map(|secs| format_position(Duration::from_secs(secs)))
+                                .unwrap_or_default()
+                        ),
+                        style.fg(timestamp),
+                    ),
+                    Span::styled(episode.title.clone(), style.fg(title)),
+                    Span::styled(format!("{}{}", position, download), style.fg(status)),
+                ]))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,40 @@
+use crate::tui::{handle_events::chapter_funcs::format_position, render::app::App};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+};
+use std::time::Duration;
+
+impl App {
+    /// Lists the queued tracks with their lengths; the track being played is bold.
+    pub fn queue_items(&self) -> Vec<ListItem<'_>> {
+        let timestamp = self.get_color(&self.config.colors.timestamp);
+        let title = self.get_color(&self.config.colors.title);
+
+        self.path_queue
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let style = match index {
+                    0 => Style::default().add_modifier(Modifier::BOLD),
+                    _ => Style::default(),
+                };
+                let data = self.meta_manager.queue.get(index);
+                let length = data
+                    .and_then(|data| data.duration_as_secs)
+                    .filter(|duration| *duration > 0.0)
+                    .map_or(String::new(), |duration| {
+                        format_position(Duration::from_secs_f64(duration))
+                    });
+                let name = data
+                    .and_then(|data| data.title.clone().or(data.raw_file.clone()))
+                    .unwrap_or_else(|| path.to_string_lossy().to_string());
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:>9}  ", length), style.fg(timestamp)),
+                    Span::styled(name, style.fg(title)),
+                ]))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{
+    data::organiser::{Rename, RenameStatus},
+    tui::render::app::App,
+};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::ListItem,
+};
+use std::path::Path;
+
+impl App {
+    /// Lists every previewed move as `source -> destination`, relative to the music directory.
+    /// Collisions are crossed out and files already in place dimmed, since neither is moved.
+    pub fn rename_items<'a>(&self, renames: &'a [Rename]) -> Vec<ListItem<'a>> {
+        let title = self.get_color(&self.config.colors.title);
+        let status = self.get_color(&self.config.colors.status);
+        let root = &self.config.directories.music_directory;
+        let relative = |path: &Path| match path.strip_prefix(root) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => self.format_display_path(path),
+        };
+
+        renames
+            .iter()
+            .map(|rename| {
+                let (marker, style) = match rename.status {
+                    RenameStatus::Move => ("  ", Style::default()),
+                    RenameStatus::Unchanged => ("= ", Style::default().add_modifier(Modifier::DIM)),
+                    RenameStatus::Collision => {
+                        ("! ", Style::default().add_modifier(Modifier::CROSSED_OUT))
+                    }
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, Style::default().fg(status)),
+                    Span::styled(relative(&rename.from), style.fg(title)),
+                    Span::styled(" -> ", Style::default().fg(status)),
+                    Span::styled(relative(&rename.to), style.fg(status)),
+                ]))
+            })
+            .collect()
+    }
+}
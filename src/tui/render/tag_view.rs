@@ -0,0 +1,103 @@
+use crate::{
+    data::{metadata::tag_fields::TagField, path_tags::Guess},
+    tui::{render::app::App, tag_editor::TagEditor},
+};
+use ratatui::{
+    layout::Constraint,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, ListItem, Row, Table},
+};
+
+/// Width of the field names column.
+const NAME_WIDTH: usize = 14;
+
+impl App {
+    /// Lists the fields of the tag editor: values the files differ on show as `<various>`, pending changes as `old -> new` in bold.
+    pub fn tag_items<'a>(&self, editor: &'a TagEditor) -> Vec<ListItem<'a>> {
+        let timestamp = self.get_color(&self.config.colors.timestamp);
+        let title = self.get_color(&self.config.colors.title);
+        let status = self.get_color(&self.config.colors.status);
+
+        TagField::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let name = Span::styled(
+                    format!("{:<width$}", field.name(), width = NAME_WIDTH),
+                    Style::default().fg(timestamp),
+                );
+                let current = match &editor.current[index] {
+                    Some(value) => value.clone(),
+                    None => "<various>".to_string(),
+                };
+                let value = match (&editor.input, &editor.pending[index]) {
+                    (Some(input), _) if index == editor.selected => {
+                        vec![Span::styled(
+                            format!("{}_", input),
+                            Style::default().fg(title),
+                        )]
+                    }
+                    (_, Some(pending)) => vec![
+                        Span::styled(format!("{} -> ", current), Style::default().fg(title)),
+                        Span::styled(
+                            match pending.is_empty() {
+                                true => "<removed>".to_string(),
+                                false => pending.clone(),
+                            },
+                            Style::default().fg(status).add_modifier(Modifier::BOLD),
+                        ),
+                    ],
+                    (_, None) => vec![Span::styled(current, Style::default().fg(title))],
+                };
+                ListItem::new(Line::from([vec![name], value].concat()))
+            })
+            .collect()
+    }
+
+    /// Tabulates the tags read from each path, one column per field of the pattern.
+    /// Files that didn't match are dimmed and won't be written.
+    pub fn guess_table<'a>(&self, fields: &[TagField], guesses: &'a [Guess]) -> Table<'a> {
+        let timestamp = self.get_color(&self.config.colors.timestamp);
+        let title = self.get_color(&self.config.colors.title);
+
+        let header = Row::new(
+            ["file"]
+                .into_iter()
+                .chain(fields.iter().map(|field| field.name()))
+                .map(|name| Cell::from(name).style(Style::default().fg(timestamp))),
+        );
+        let rows = guesses.iter().map(|guess| {
+            let name = guess
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match &guess.fields {
+                Some(values) => Row::new(
+                    [name]
+                        .into_iter()
+                        .chain(fields.iter().map(|field| {
+                            values
+                                .iter()
+                                .find(|(other, _)| other == field)
+                                .map(|(_, value)| value.clone())
+                                .unwrap_or_default()
+                        }))
+                        .map(Cell::from),
+                )
+                .style(Style::default().fg(title)),
+                None => Row::new([Cell::from(name), Cell::from("no match")])
+                    .style(Style::default().add_modifier(Modifier::DIM)),
+            }
+        });
+        let widths =
+            [Constraint::Fill(2)]
+                .into_iter()
+                .chain(fields.iter().map(|field| match field {
+                    TagField::Year | TagField::Track | TagField::Disc => Constraint::Length(6),
+                    _ => Constraint::Fill(1),
+                }));
+        Table::new(rows, widths).header(header).column_spacing(2)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::tui::{handle_events::visualizer_funcs::FFT_LEN, render::app::App};
+use ratatui::{
+    style::Style,
+    symbols::Marker,
+    widgets::{
+        Bar, BarChart, BarGroup, Widget,
+        canvas::{Canvas, Line},
+    },
+};
+
+/// Frequencies covered by the spectrum analyzer, in Hz.
+const LOW_FREQUENCY: f32 = 30.0;
+const HIGH_FREQUENCY: f32 = 16000.0;
+/// Samples shown by the oscilloscope, about 23 ms at 44.1 kHz.
+const SCOPE_LEN: usize = 1024;
+
+impl App {
+    /// Draws the spectrum as bars on a log frequency scale, as many as fit in the width.
+    pub fn spectrum_chart(&self, width: u16) -> impl Widget {
+        let color = self.get_color(&self.config.colors.spectrum);
+        let count = (width as usize).div_ceil(3).max(1);
+        let nyquist = self.spectrum_rate as f32 / 2.0;
+        let high = HIGH_FREQUENCY.min(nyquist);
+        let bin_width = nyquist / (FFT_LEN / 2) as f32;
+        let ratio = (high / LOW_FREQUENCY).powf(1.0 / count as f32);
+
+        let bars: Vec<Bar> = (0..count)
+            .map(|bar| {
+                let low = LOW_FREQUENCY * ratio.powi(bar as i32);
+                let first = (low / bin_width) as usize;
+                let last = ((low * ratio / bin_width) as usize).max(first + 1);
+                let level = self
+                    .spectrum
+                    .get(first..last.min(self.spectrum.len()))
+                    .unwrap_or_default()
+                    .iter()
+                    .fold(0.0f32, |peak, level| peak.max(*level));
+                Bar::default()
+                    .value((level * 1000.0) as u64)
+                    .text_value(String::new())
+            })
+            .collect();
+
+        BarChart::default()
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(2)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(color))
+            .max(1000)
+    }
+
+    /// Draws the latest samples as a line, starting at a rising zero crossing so the waveform holds still.
+    pub fn oscilloscope(&self) -> impl Widget + '_ {
+        let color = self.get_color(&self.config.colors.oscilloscope);
+        let samples = match self.visualizer_active() {
+            true => {
+                let (samples, _) = self.audio.latest_samples(SCOPE_LEN * 2);
+                let start = samples
+                    .windows(2)
+                    .take(SCOPE_LEN)
+                    .position(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+                    .unwrap_or(0);
+                samples[start..start + SCOPE_LEN.min(samples.len() - start)].to_vec()
+            }
+            false => vec![0.0; SCOPE_LEN],
+        };
+
+        Canvas::default()
+            .marker(Marker::Braille)
+            .x_bounds([0.0, SCOPE_LEN as f64 - 1.0])
+            .y_bounds([-1.0, 1.0])
+            .paint(move |ctx| {
+                for (x, pair) in samples.windows(2).enumerate() {
+                    ctx.draw(&Line {
+                        x1: x as f64,
+                        y1: pair[0].clamp(-1.0, 1.0) as f64,
+                        x2: x as f64 + 1.0,
+                        y2: pair[1].clamp(-1.0, 1.0) as f64,
+                        color,
+                    });
+                }
+            })
+    }
+}
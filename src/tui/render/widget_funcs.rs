@@ -2,7 +2,8 @@ use super::app::Tab;
 use crate::tui::render::app::App;
 use ratatui::{
     layout::Alignment,
-    style::Style,
+    style::{Modifier, Style},
+    symbols,
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, LineGauge, Padding, Paragraph, Widget},
 };
@@ -10,6 +11,7 @@ use ratatui::{
 impl App {
     pub fn top_left(&self) -> impl Widget {
         let border = &self.config.colors.border;
+        let options = &self.config.colors.options;
         let paused = &self.config.colors.paused;
         let timestamp = &self.config.colors.timestamp;
         Paragraph::new(vec![
@@ -29,18 +31,13 @@ impl App {
                 Style::default().fg(self.get_color(timestamp)),
             )]),
             Line::from(vec![Span::styled(
-                format!(
-                    "{}",
-                    match self.audio.is_empty() {
-                        true => {
-                            "stopped"
-                        }
-                        false => match self.audio.paused {
-                            true => "paused",
-                            false => "playing",
-                        },
-                    }
-                ),
+                match self.audio.is_empty() {
+                    true => "stopped",
+                    false => match self.audio.paused {
+                        true => "paused",
+                        false => "playing",
+                    },
+                },
                 Style::default().fg(self.get_color(paused)),
             )]),
         ])
@@ -49,7 +46,15 @@ impl App {
                 .borders(Borders::TOP | Borders::BOTTOM | Borders::LEFT)
                 .border_style(Style::default().fg(self.get_color(border)))
                 .border_type(BorderType::Rounded)
-                .padding(Padding::horizontal(1)),
+                .padding(Padding::horizontal(1))
+                // Sleep timer
+                .title_bottom(match self.sleep_display() {
+                    Some(sleep) => Line::from(vec![Span::styled(
+                        format!(" z {} ", sleep),
+                        Style::default().fg(self.get_color(options)),
+                    )]),
+                    None => Line::default(),
+                }),
         )
         .alignment(Alignment::Left)
     }
@@ -70,28 +75,28 @@ impl App {
                 vec![
                     Line::from(vec![
                         Span::styled(
-                            format!("{}", self.data.display_artist()),
+                            self.data.display_artist(),
                             Style::default().fg(self.get_color(artist)),
                         ),
                         Span::from(" "),
                         Span::styled(
-                            format!("{}", self.data.display_title()),
+                            self.data.display_title(),
                             Style::default().fg(self.get_color(title)),
                         ),
                     ]),
                     Line::from(vec![
                         Span::styled(
-                            format!("{}", self.data.display_album()),
+                            self.data.display_album(),
                             Style::default().fg(self.get_color(album)),
                         ),
                         Span::from(" "),
                         Span::styled(
-                            format!("{}", self.data.display_year()),
+                            self.data.display_year(),
                             Style::default().fg(self.get_color(year)),
                         ),
                         Span::from(" "),
                         Span::styled(
-                            format!("{}", self.data.display_track_number()),
+                            self.data.display_track_number(),
                             Style::default().fg(self.get_color(track_num)),
                         ),
                     ]),
@@ -120,6 +125,52 @@ impl App {
                                 _ => Style::default().fg(self.get_color(tab_unselected)),
                             },
                         ),
+                        Span::styled(
+                            " 3 ",
+                            match self.tab {
+                                Tab::Equalizer => Style::default().fg(self.get_color(tab_selected)),
+                                _ => Style::default().fg(self.get_color(tab_unselected)),
+                            },
+                        ),
+                        Span::styled(
+                            " 4 ",
+                            match self.tab {
+                                Tab::Chapters => Style::default().fg(self.get_color(tab_selected)),
+                                _ => Style::default().fg(self.get_color(tab_unselected)),
+                            },
+                        ),
+                        Span::styled(
+                            " 5 ",
+                            match self.tab {
+                                Tab::Podcasts => Style::default().fg(self.get_color(tab_selected)),
+                                _ => Style::default().fg(self.get_color(tab_unselected)),
+                            },
+                        ),
+                        Span::styled(
+                            " 6 ",
+                            match self.tab {
+                                Tab::Visualizer => {
+                                    Style::default().fg(self.get_color(tab_selected))
+                                }
+                                _ => Style::default().fg(self.get_color(tab_unselected)),
+                            },
+                        ),
+                        Span::styled(
+                            " 7 ",
+                            match self.tab {
+                                Tab::NowPlaying => {
+                                    Style::default().fg(self.get_color(tab_selected))
+                                }
+                                _ => Style::default().fg(self.get_color(tab_unselected)),
+                            },
+                        ),
+                        Span::styled(
+                            " 8 ",
+                            match self.tab {
+                                Tab::Lyrics => Style::default().fg(self.get_color(tab_selected)),
+                                _ => Style::default().fg(self.get_color(tab_unselected)),
+                            },
+                        ),
                         Span::styled("├", self.get_color(border)),
                     ])
                     .centered(),
@@ -130,20 +181,50 @@ impl App {
     pub fn top_right(&self) -> impl Widget {
         let border = &self.config.colors.border;
         let options = &self.config.colors.options;
+        let playback_speed = &self.config.colors.playback_speed;
         let volume = &self.config.colors.volume;
         Paragraph::new(vec![
-            Line::from(vec![Span::styled(
-                format!("{}%", self.audio.vol),
-                Style::default().fg(self.get_color(volume)),
-            )]),
             Line::from(vec![
-                Span::styled(format!("-"), Style::default().fg(self.get_color(options))),
-                Span::styled(format!("-"), Style::default().fg(self.get_color(options))),
-                Span::styled(format!("-"), Style::default().fg(self.get_color(options))),
-                Span::styled(format!("-"), Style::default().fg(self.get_color(options))),
-                Span::styled(format!("-"), Style::default().fg(self.get_color(options))),
-                Span::styled(format!("-"), Style::default().fg(self.get_color(options))),
+                Span::styled(
+                    format!(
+                        "{:.2}x{} ",
+                        self.audio.speed,
+                        // Marks resampling, where the pitch follows the speed
+                        match self.audio.preserve_pitch {
+                            true => "",
+                            false => "~",
+                        }
+                    ),
+                    Style::default().fg(self.get_color(playback_speed)),
+                ),
+                Span::styled(
+                    match self.audio.muted {
+                        true => "mute".to_string(),
+                        false => format!("{}%", self.audio.vol),
+                    },
+                    // Boost above 100% is softly limited, which changes the sound
+                    match self.audio.vol > 100 && !self.audio.muted {
+                        true => Style::default()
+                            .fg(self.get_color(volume))
+                            .add_modifier(Modifier::BOLD),
+                        false => Style::default().fg(self.get_color(volume)),
+                    },
+                ),
             ]),
+            match self.loop_display() {
+                Some(range) => Line::from(vec![Span::styled(
+                    range,
+                    Style::default().fg(self.get_color(options)),
+                )]),
+                None => Line::from(vec![
+                    Span::styled("-", Style::default().fg(self.get_color(options))),
+                    Span::styled("-", Style::default().fg(self.get_color(options))),
+                    Span::styled("-", Style::default().fg(self.get_color(options))),
+                    Span::styled("-", Style::default().fg(self.get_color(options))),
+                    Span::styled("-", Style::default().fg(self.get_color(options))),
+                    Span::styled("-", Style::default().fg(self.get_color(options))),
+                ]),
+            },
         ])
         .block(
             Block::new()
@@ -160,8 +241,86 @@ impl App {
         LineGauge::default()
             .block(Block::new())
             .label("")
-            .ratio(self.prog_bar)
-            .filled_style(Style::default().fg(self.get_color(&seekbar_filled)))
-            .unfilled_style(Style::default().fg(self.get_color(&seekbar_unfilled)))
+            .ratio(self.prog_bar.unwrap_or(0.0))
+            .filled_style(Style::default().fg(self.get_color(seekbar_filled)))
+            .unfilled_style(Style::default().fg(self.get_color(seekbar_unfilled)))
+    }
+
+    /// Seekbar drawn as the track's peak envelope, one block character per column.
+    pub fn waveform_bar(&self, envelope: &[f32], width: u16) -> impl Widget {
+        const BLOCKS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+        let filled = Style::default().fg(self.get_color(&self.config.colors.seekbar_filled));
+        let unfilled = Style::default().fg(self.get_color(&self.config.colors.seekbar_unfilled));
+        let width = width as usize;
+        let played = (self.prog_bar.unwrap_or(0.0) * width as f64) as usize;
+
+        Paragraph::new(Line::from(
+            (0..width)
+                .map(|column| {
+                    let first = column * envelope.len() / width;
+                    let last = ((column + 1) * envelope.len() / width).max(first + 1);
+                    let peak = envelope[first..last.min(envelope.len())]
+                        .iter()
+                        .fold(0.0f32, |peak, level| peak.max(*level));
+                    let block = BLOCKS[((peak * 7.0).round() as usize).min(7)];
+                    Span::styled(
+                        block,
+                        match column < played {
+                            true => filled,
+                            false => unfilled,
+                        },
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /// Returns the columns of the seekbar where the A-B loop points fall.
+    pub fn loop_marker_columns(&self, width: u16) -> Vec<u16> {
+        let Some(duration) = self
+            .data
+            .duration_as_secs
+            .filter(|duration| *duration > 0.0)
+        else {
+            return Vec::new();
+        };
+        [self.loop_a, self.loop_b]
+            .into_iter()
+            .flatten()
+            .map(|point| {
+                let ratio = (point.as_secs_f64() / duration).clamp(0.0, 1.0);
+                ((ratio * width as f64) as u16).min(width.saturating_sub(1))
+            })
+            .collect()
+    }
+
+    /// Seekbar for tracks of unknown length: a segment sweeping back and forth across the bar.
+    pub fn indeterminate_bar(&self, width: u16) -> impl Widget {
+        let seekbar_filled = &self.config.colors.seekbar_filled;
+        let seekbar_unfilled = &self.config.colors.seekbar_unfilled;
+        let width = width as usize;
+        let segment = (width / 8).max(1);
+        let travel = width.saturating_sub(segment).max(1);
+        // Moves ten cells per second of playback.
+        let step = (self.audio.sink_pos_millis() / 100) as usize % (travel * 2);
+        let start = match step < travel {
+            true => step,
+            false => travel * 2 - step,
+        };
+        let line = symbols::line::HORIZONTAL;
+        Paragraph::new(Line::from(vec![
+            Span::styled(
+                line.repeat(start),
+                Style::default().fg(self.get_color(seekbar_unfilled)),
+            ),
+            Span::styled(
+                line.repeat(segment),
+                Style::default().fg(self.get_color(seekbar_filled)),
+            ),
+            Span::styled(
+                line.repeat(width.saturating_sub(start + segment)),
+                Style::default().fg(self.get_color(seekbar_unfilled)),
+            ),
+        ]))
     }
 }
@@ -0,0 +1,148 @@
+use crate::data::metadata::tag_fields::{TagField, read_fields};
+use std::path::PathBuf;
+
+/// Encapsulates the tag editor state.
+pub struct TagEditor {
+    pub paths: Vec<PathBuf>,
+    /// The value of each field shared by all the files, or None when they differ.
+    pub current: Vec<Option<String>>,
+    /// The new value of each field, set across all the files on confirm.
+    pub pending: Vec<Option<String>>,
+    pub selected: usize,
+    /// The text typed for the selected field, while it's being edited.
+    pub input: Option<String>,
+}
+
+impl TagEditor {
+    /// Reads the fields of the files to edit.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let fields: Vec<Vec<String>> = paths.iter().map(|path| read_fields(path)).collect();
+        Self {
+            paths,
+            current: common_values(&fields),
+            pending: vec![None; TagField::ALL.len()],
+            selected: 0,
+            input: None,
+        }
+    }
+
+    /// Moves the cursor up one field or goes to the bottom if at the top.
+    pub fn navigate_up(&mut self) {
+        self.selected = match self.selected {
+            0 => TagField::ALL.len() - 1,
+            selected => selected - 1,
+        };
+    }
+
+    /// Moves the cursor down one field or goes to the top if at the bottom.
+    pub fn navigate_down(&mut self) {
+        self.selected = (self.selected + 1) % TagField::ALL.len();
+    }
+
+    /// Starts typing a value for the selected field, from its pending or current value.
+    pub fn start_input(&mut self) {
+        let value = self.pending[self.selected]
+            .clone()
+            .or_else(|| self.current[self.selected].clone())
+            .unwrap_or_default();
+        self.input = Some(value);
+    }
+
+    /// Makes the typed text the pending value of the selected field, unless it's what the files already have.
+    pub fn finish_input(&mut self) -> Result<(), String> {
+        let Some(value) = self.input.as_ref() else {
+            return Ok(());
+        };
+        let value = value.trim().to_string();
+        TagField::ALL[self.selected].validate(&value)?;
+        self.pending[self.selected] = match self.current[self.selected] == Some(value.clone()) {
+            true => None,
+            false => Some(value),
+        };
+        self.input = None;
+        Ok(())
+    }
+
+    /// Drops the pending value of the selected field.
+    pub fn revert(&mut self) {
+        self.pending[self.selected] = None;
+    }
+
+    /// Returns the fields to write and their new values.
+    pub fn changes(&self) -> Vec<(TagField, String)> {
+        TagField::ALL
+            .iter()
+            .zip(&self.pending)
+            .filter_map(|(field, value)| Some((*field, value.clone()?)))
+            .collect()
+    }
+}
+
+/// Returns the value of each field if it's the same in every file, or None if they differ.
+fn common_values(fields: &[Vec<String>]) -> Vec<Option<String>> {
+    (0..TagField::ALL.len())
+        .map(|index| {
+            let first = fields.first()?.get(index)?;
+            fields
+                .iter()
+                .all(|values| values.get(index) == Some(first))
+                .then(|| first.clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(fields: &[Vec<String>]) -> TagEditor {
+        TagEditor {
+            paths: Vec::new(),
+            current: common_values(fields),
+            pending: vec![None; TagField::ALL.len()],
+            selected: 0,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn edits_apply_only_to_changed_fields() {
+        let file = |title: &str, year: &str| {
+            let mut values = vec![String::new(); TagField::ALL.len()];
+            values[0] = title.to_string();
+            values[4] = year.to_string();
+            values
+        };
+        let mut editor = editor(&[file("One", "1999"), file("Two", "1999")]);
+        assert_eq!(editor.current[0], None);
+        assert_eq!(editor.current[4], Some("1999".to_string()));
+
+        // Re-entering the shared value isn't a change.
+        editor.selected = 4;
+        editor.start_input();
+        assert_eq!(editor.input.as_deref(), Some("1999"));
+        editor.finish_input().unwrap();
+        assert!(editor.changes().is_empty());
+
+        editor.input = Some("soon".to_string());
+        assert!(editor.finish_input().is_err());
+        editor.input = Some(" 2001 ".to_string());
+        editor.finish_input().unwrap();
+
+        editor.navigate_up();
+        editor.navigate_up();
+        editor.navigate_up();
+        editor.navigate_up();
+        editor.input = Some(String::new());
+        editor.finish_input().unwrap();
+        assert_eq!(
+            editor.changes(),
+            vec![
+                (TagField::Title, String::new()),
+                (TagField::Year, "2001".to_string())
+            ]
+        );
+        editor.revert();
+        assert_eq!(editor.changes().len(), 1);
+    }
+}